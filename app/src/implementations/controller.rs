@@ -33,8 +33,8 @@ impl SdlController {
     }
 
     pub fn set_keys_state(&mut self, keyboard_state: KeyboardState) {
-        for i in 0..16 {
-            self.key_down[i] = keyboard_state.is_scancode_pressed(KEYS[i]);
+        for (i, key) in KEYS.iter().enumerate() {
+            self.key_down[i] = keyboard_state.is_scancode_pressed(*key);
         }
     }
 }
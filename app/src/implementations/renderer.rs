@@ -4,10 +4,17 @@ use sdl2::render::Canvas;
 use sdl2::surface::Surface;
 use sdl2::video::{Window, WindowContext};
 
+// colours of the pixels by plane index, only the first 2 are used outside of XO-CHIP
+const PALETTE: [[u8; 3]; 4] = [
+    [0x00, 0x00, 0x00],
+    [0xFF, 0xFF, 0xFF],
+    [0xAA, 0xAA, 0xAA],
+    [0x55, 0x55, 0x55],
+];
+
 pub struct SdlRenderer {
     canvas: Canvas<Window>,
     pixel_format: sdl2::pixels::PixelFormatEnum,
-    pitch: u32,
     texture_creator: sdl2::render::TextureCreator<WindowContext>,
 }
 
@@ -18,28 +25,36 @@ impl SdlRenderer {
         Self {
             canvas: input_canvas,
             pixel_format: PixelFormatEnum::RGB24,
-            pitch: PixelFormatEnum::RGB24.byte_size_of_pixels(64) as u32,
             texture_creator: new_texture_creator,
         }
     }
+
+    pub fn set_title(&mut self, title: &str) {
+        // titles come from the application, they never contain a nul byte
+        let _ = self.canvas.window_mut().set_title(title);
+    }
 }
 
 impl Renderer for SdlRenderer {
-    fn render(&mut self, screen: &Vec<u8>) {
-        let mut buffer: Vec<u8> = vec![0; 32 * 64 * 3];
-
-        for i in 0..32 * 64 {
-            if screen[i] != 0 {
-                buffer[i * 3] = 0xFF;
-                buffer[i * 3 + 1] = 0xFF;
-                buffer[i * 3 + 2] = 0xFF;
-            };
+    fn render(&mut self, screen: &[u8], width: usize, height: usize) {
+        let mut buffer: Vec<u8> = vec![0; width * height * 3];
+
+        for (pixel, colour) in screen.iter().zip(buffer.chunks_mut(3)) {
+            colour.copy_from_slice(&PALETTE[(pixel & 0x3) as usize]);
         }
 
         self.canvas.clear();
 
-        let surface =
-            Surface::from_data(&mut buffer, 64, 32, self.pitch, self.pixel_format).unwrap();
+        // the pitch depends on the resolution, which can change at runtime
+        let pitch = self.pixel_format.byte_size_of_pixels(width) as u32;
+        let surface = Surface::from_data(
+            &mut buffer,
+            width as u32,
+            height as u32,
+            pitch,
+            self.pixel_format,
+        )
+        .unwrap();
         let texture = surface.as_texture(&self.texture_creator).unwrap();
 
         self.canvas.copy(&texture, None, None).unwrap();
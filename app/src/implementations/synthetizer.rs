@@ -3,6 +3,7 @@ use sdl2::audio::{AudioCallback, AudioDevice, AudioSpecDesired};
 
 pub struct SdlSynthetizer {
     audio_device: AudioDevice<SquareWave>,
+    frequency: i32,
     playing: bool,
 }
 
@@ -23,16 +24,17 @@ impl SdlSynthetizer {
                 phase_inc: 440.0 / spec.freq as f32,
                 phase: 0.0,
                 volume: 0.25,
+                pattern: None,
             }
         });
 
-        if result.is_ok() {
-            Self {
-                audio_device: result.unwrap(),
+        match result {
+            Ok(audio_device) => Self {
+                frequency: audio_device.spec().freq,
+                audio_device,
                 playing: false,
-            }
-        } else {
-            panic!("Could not create audio device.");
+            },
+            Err(_) => panic!("Could not create audio device."),
         }
     }
 }
@@ -51,25 +53,39 @@ impl Synthetizer for SdlSynthetizer {
             self.audio_device.pause();
         }
     }
+
+    fn set_pattern(&mut self, pattern: &[u8; 16], pitch: u8) {
+        let sample_rate = 4000.0 * 2f32.powf((pitch as f32 - 64.0) / 48.0);
+        let mut wave = self.audio_device.lock();
+
+        // the phase now goes through the 128 samples of the pattern
+        wave.phase_inc = sample_rate / 128.0 / self.frequency as f32;
+        wave.pattern = Some(*pattern);
+    }
 }
 
 struct SquareWave {
     phase_inc: f32,
     phase: f32,
     volume: f32,
+    pattern: Option<[u8; 16]>,
 }
 
 impl AudioCallback for SquareWave {
     type Channel = f32;
 
     fn callback(&mut self, out: &mut [f32]) {
-        // Generate a square wave
+        // Generate a square wave, or play the XO-CHIP pattern if there is one
         for x in out.iter_mut() {
-            *x = if self.phase <= 0.5 {
-                self.volume
-            } else {
-                -self.volume
+            let high = match self.pattern {
+                Some(pattern) => {
+                    let sample = (self.phase * 128.0) as usize % 128;
+                    pattern[sample / 8] & (0x80 >> (sample % 8)) != 0
+                }
+                None => self.phase <= 0.5,
             };
+
+            *x = if high { self.volume } else { -self.volume };
             self.phase = (self.phase + self.phase_inc) % 1.0;
         }
     }
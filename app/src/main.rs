@@ -1,22 +1,32 @@
+use chip8_asm::assemble;
+use chip8_lib::cli::ConfigArgs;
+use chip8_lib::clock::{FramePacer, MonotonicClock};
 use chip8_lib::emulator::Emulator;
+use chip8_lib::interfaces::Synthetizer;
+use chip8_lib::movie::{Movie, Playback};
+use chip8_lib::rewind::Rewind;
 
 use clap::Parser;
 
 use crate::implementations::controller::SdlController;
 use crate::implementations::renderer::SdlRenderer;
 use crate::implementations::synthetizer::SdlSynthetizer;
+use crate::save_slots::SaveSlots;
 
 use sdl2::event::Event;
-use sdl2::keyboard::Keycode;
-use std::time::Duration;
+use sdl2::keyboard::{Keycode, Mod, Scancode};
+use std::fs;
 
 mod implementations;
+mod save_slots;
 
-const DEFAULT_DURATION: Duration = Duration::new(0, 1_000_000_000u32 / 60);
+const FRAMES_PER_SECOND: u32 = 60;
 
-// maybe an enum for this? -> 420Hz: 7, 480Hz: 8, 540Hz: 9, 600Hz: 10, 660Hz: 11, 720Hz: 12, 780Hz: 13
-// in steps of 60Hz to facilitate frame speed
-const INSTRUCTIONS_PER_FRAME: u32 = 10;
+const WINDOW_TITLE: &str = "rust-chip8-sdl2";
+
+// a snapshot every frame, deltas are small enough for minutes of rewind within the budget
+const REWIND_INTERVAL: u32 = 1;
+const REWIND_BUDGET: usize = 16 * 1024 * 1024;
 
 fn main() -> Result<(), String> {
     // cli arguments
@@ -28,7 +38,7 @@ fn main() -> Result<(), String> {
     let mut event_pump = sdl_context.event_pump()?;
 
     let window = video_subsystem
-        .window("rust-chip8-sdl2", 1024, 512)
+        .window(WINDOW_TITLE, 1024, 512)
         .position_centered()
         .build()
         .expect("could not initialize video subsystem");
@@ -42,7 +52,51 @@ fn main() -> Result<(), String> {
     let mut controller = SdlController::new();
     let mut renderer = SdlRenderer::new(canvas);
     let mut synthetizer = SdlSynthetizer::new();
-    let mut chip8_emulator = Emulator::new(&args.path);
+    let mut config = args.config.config();
+
+    // Octo sources are assembled on load
+    let rom = if args.path.ends_with(".8o") {
+        let source = fs::read_to_string(&args.path).map_err(|error| error.to_string())?;
+
+        assemble(&source)
+            .map_err(|error| format!("{}: {}", args.path, error))?
+            .rom
+    } else {
+        fs::read(&args.path).map_err(|error| format!("{}: {}", args.path, error))?
+    };
+
+    // a movie is played with the config and seed it was recorded with
+    let mut playback = match &args.play {
+        Some(path) => {
+            let movie = fs::read(path)
+                .map_err(|error| error.to_string())
+                .and_then(|bytes| Movie::from_bytes(&bytes).map_err(|error| error.to_string()))
+                .and_then(|movie| {
+                    movie.check_rom(&rom).map_err(|error| error.to_string())?;
+                    Ok(movie)
+                })
+                .map_err(|error| format!("{}: {}", path, error))?;
+
+            config = *movie.config();
+            Some(Playback::new(movie))
+        }
+        None => None,
+    };
+
+    let mut chip8_emulator =
+        Emulator::from_bytes(&rom, config).map_err(|error| format!("{}: {}", args.path, error))?;
+    let save_slots = SaveSlots::new(&args.path);
+    let mut rewind = Rewind::new(REWIND_INTERVAL, REWIND_BUDGET);
+    let mut movie = args.record.as_ref().map(|_| Movie::new(&chip8_emulator));
+
+    // loading a state or rewinding would make the movie impossible to replay
+    let can_travel = movie.is_none() && playback.is_none();
+
+    // the title tells when the rom waits for a key, or why the cpu halted
+    let mut title = WINDOW_TITLE.to_string();
+
+    let clock = MonotonicClock::new();
+    let mut pacer = FramePacer::new(&clock, FRAMES_PER_SECOND);
 
     // main loop
     'running: loop {
@@ -55,27 +109,101 @@ fn main() -> Result<(), String> {
                 } => {
                     break 'running;
                 }
+                // F1-F10 save to a slot, and load from it with shift held
+                Event::KeyDown {
+                    keycode: Some(keycode),
+                    keymod,
+                    repeat: false,
+                    ..
+                } => {
+                    if let Some(slot) = SaveSlots::slot_for(keycode) {
+                        if keymod.intersects(Mod::LSHIFTMOD | Mod::RSHIFTMOD) {
+                            if !can_travel {
+                                continue;
+                            }
+
+                            save_slots.load(slot, &mut chip8_emulator);
+                        } else {
+                            save_slots.save(slot, &chip8_emulator);
+                        }
+                    }
+                }
                 _ => {}
             }
         }
 
-        // set input state
-        controller.set_keys_state(event_pump.keyboard_state());
-
-        // emulator execution
-        let time_elapsed = chip8_emulator.step_frame(
-            INSTRUCTIONS_PER_FRAME,
-            &controller,
-            &mut renderer,
-            &mut synthetizer,
-        );
-
-        // sleep until 1/16th of a second has passed
-        if time_elapsed.is_ok() {
-            ::std::thread::sleep(DEFAULT_DURATION.abs_diff(time_elapsed.unwrap()));
-        } else {
-            ::std::thread::sleep(DEFAULT_DURATION);
+        // the rom exited the interpreter
+        if chip8_emulator.has_exited() {
+            break 'running;
         }
+
+        // frames run at 60 Hz of host time, catching up when the host was late
+        for _ in 0..pacer.frames_due(&clock) {
+            // play frames backward while backspace is held
+            if can_travel
+                && event_pump
+                    .keyboard_state()
+                    .is_scancode_pressed(Scancode::Backspace)
+            {
+                if rewind.rewind(&mut chip8_emulator) {
+                    chip8_emulator.render(&mut renderer);
+                }
+
+                synthetizer.stop();
+                continue;
+            }
+
+            // set input state
+            controller.set_keys_state(event_pump.keyboard_state());
+
+            if let Some(movie) = movie.as_mut() {
+                movie.record_frame(&controller);
+            }
+
+            // emulator execution, with the keys of the movie while it plays
+            match playback.as_mut() {
+                Some(playback) => {
+                    chip8_emulator.step_frame(playback, &mut renderer, &mut synthetizer);
+                    playback.next_frame();
+                }
+                None => {
+                    chip8_emulator.step_frame(&controller, &mut renderer, &mut synthetizer);
+                }
+            }
+
+            if playback.as_ref().is_some_and(Playback::is_finished) {
+                println!("movie finished, the keyboard is back in control");
+                playback = None;
+            }
+
+            if can_travel {
+                rewind.record(&chip8_emulator);
+            }
+        }
+
+        let status = match chip8_emulator.get_fault() {
+            Some(fault) => format!("{} - halted: {}", WINDOW_TITLE, fault),
+            None if chip8_emulator.is_waiting_for_key() => {
+                format!("{} - press a key", WINDOW_TITLE)
+            }
+            None => WINDOW_TITLE.to_string(),
+        };
+
+        if status != title {
+            if let Some(fault) = chip8_emulator.get_fault() {
+                eprintln!("{}", fault);
+            }
+
+            renderer.set_title(&status);
+            title = status;
+        }
+
+        // sleep until the next frame is due
+        ::std::thread::sleep(pacer.until_next_frame(&clock));
+    }
+
+    if let (Some(movie), Some(path)) = (movie, &args.record) {
+        fs::write(path, movie.to_bytes()).map_err(|error| format!("{}: {}", path, error))?;
     }
 
     Ok(())
@@ -85,7 +213,18 @@ fn main() -> Result<(), String> {
 #[derive(Parser, Debug)]
 #[command(version, about, long_about = None)]
 struct Args {
-    /// Name of the person to greet
+    /// Path to the rom, or to an Octo source (.8o) to assemble
     #[arg(short, long)]
     path: String,
+
+    #[command(flatten)]
+    config: ConfigArgs,
+
+    /// Record the keys of every frame to a movie file, written on exit
+    #[arg(long, conflicts_with = "play")]
+    record: Option<String>,
+
+    /// Play a movie recorded with --record, the keyboard taking over once it ends
+    #[arg(long)]
+    play: Option<String>,
 }
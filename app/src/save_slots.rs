@@ -0,0 +1,67 @@
+use chip8_lib::emulator::Emulator;
+use sdl2::keyboard::Keycode;
+use std::fs;
+
+const SLOT_KEYS: [Keycode; 10] = [
+    Keycode::F1,
+    Keycode::F2,
+    Keycode::F3,
+    Keycode::F4,
+    Keycode::F5,
+    Keycode::F6,
+    Keycode::F7,
+    Keycode::F8,
+    Keycode::F9,
+    Keycode::F10,
+];
+
+// numbered save states, stored next to the rom as <rom>.slot<n>.state
+pub struct SaveSlots {
+    rom_path: String,
+}
+
+impl SaveSlots {
+    pub fn new(rom_path: &str) -> Self {
+        Self {
+            rom_path: rom_path.to_string(),
+        }
+    }
+
+    // F1 to F10 are bound to slots 1 to 10
+    pub fn slot_for(keycode: Keycode) -> Option<usize> {
+        SLOT_KEYS
+            .iter()
+            .position(|key| *key == keycode)
+            .map(|index| index + 1)
+    }
+
+    pub fn save(&self, slot: usize, emulator: &Emulator) {
+        let path = self.slot_path(slot);
+
+        match fs::write(&path, emulator.save_state()) {
+            Ok(()) => println!("saved state to {}", path),
+            Err(error) => eprintln!("could not save state to {}: {}", path, error),
+        }
+    }
+
+    pub fn load(&self, slot: usize, emulator: &mut Emulator) {
+        let path = self.slot_path(slot);
+
+        let result = fs::read(&path)
+            .map_err(|error| error.to_string())
+            .and_then(|state| {
+                emulator
+                    .load_state(&state)
+                    .map_err(|error| error.to_string())
+            });
+
+        match result {
+            Ok(()) => println!("loaded state from {}", path),
+            Err(error) => eprintln!("could not load state from {}: {}", path, error),
+        }
+    }
+
+    fn slot_path(&self, slot: usize) -> String {
+        format!("{}.slot{}.state", self.rom_path, slot)
+    }
+}
@@ -0,0 +1,877 @@
+use chip8_lib::instruction::{encode, Instruction};
+use std::collections::{BTreeMap, HashMap, VecDeque};
+use std::error::Error;
+use std::fmt;
+
+// roms are loaded at 0x200
+const ORIGIN: u16 = 0x200;
+
+// macros expanding into macros deeper than this are most likely recursive
+const MAX_MACRO_DEPTH: usize = 64;
+
+// Assembled rom, with the address of every label and the source line of every instruction
+pub struct Program {
+    pub rom: Vec<u8>,
+    pub symbols: BTreeMap<String, u16>,
+    pub lines: BTreeMap<u16, usize>,
+}
+
+#[derive(Debug, PartialEq, Eq)]
+pub struct AssembleError {
+    pub line: usize,
+    pub message: String,
+}
+
+impl fmt::Display for AssembleError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "line {}: {}", self.line, self.message)
+    }
+}
+
+impl Error for AssembleError {}
+
+// Assembles Octo source code (https://johnearnest.github.io/Octo/docs/Manual.html)
+// Supported: labels, :const, :alias, :macro, :org, :byte, :call, if/then, if/begin/else/end,
+// loop/while/again, the CHIP-8, SUPER-CHIP and XO-CHIP statements, and raw bytes for sprite data
+pub fn assemble(source: &str) -> Result<Program, AssembleError> {
+    let mut assembler = Assembler::new(source);
+
+    assembler.run()?;
+
+    Ok(Program {
+        rom: assembler.rom,
+        symbols: assembler.symbols,
+        lines: assembler.lines,
+    })
+}
+
+impl Program {
+    // one "name 0x0000" line per symbol, sorted by name
+    pub fn symbol_table(&self) -> String {
+        self.symbols
+            .iter()
+            .map(|(name, value)| format!("{} {:#06x}\n", name, value))
+            .collect()
+    }
+
+    // first instruction of the line, or of the next line with code, e.g. for a breakpoint on a comment
+    pub fn address_of_line(&self, line: usize) -> Option<(u16, usize)> {
+        self.lines
+            .iter()
+            .filter(|(_, instruction_line)| **instruction_line >= line)
+            .min_by_key(|(address, instruction_line)| (**instruction_line, **address))
+            .map(|(address, instruction_line)| (*address, *instruction_line))
+    }
+
+    // label the address belongs to, the closest one before it
+    pub fn label_of(&self, address: u16) -> Option<&str> {
+        self.symbols
+            .iter()
+            .filter(|(_, label_address)| **label_address <= address)
+            .max_by_key(|(_, label_address)| **label_address)
+            .map(|(name, _)| name.as_str())
+    }
+}
+
+#[derive(Clone, Debug)]
+struct Token {
+    text: String,
+    line: usize,
+    // macro expansions the token comes from, 0 for the tokens of the source
+    depth: usize,
+}
+
+fn tokenize(source: &str) -> VecDeque<Token> {
+    let mut tokens = VecDeque::new();
+
+    for (index, line) in source.lines().enumerate() {
+        // everything after a # is a comment
+        let code = line.split('#').next().unwrap_or("");
+
+        for text in code.split_whitespace() {
+            tokens.push_back(Token {
+                text: text.to_string(),
+                line: index + 1,
+                depth: 0,
+            });
+        }
+    }
+
+    tokens
+}
+
+struct Macro {
+    parameters: Vec<String>,
+    body: Vec<Token>,
+}
+
+// where a label has to be written once it is known
+enum FixupKind {
+    // lower 12 bits of the instruction at the position
+    Address,
+    // 16 bits following F000
+    LongAddress,
+}
+
+struct Fixup {
+    position: usize,
+    kind: FixupKind,
+    label: String,
+    line: usize,
+}
+
+enum Block {
+    // jump to patch with the address of else or end
+    If { jump: usize },
+    // jump to patch with the address of end
+    Else { jump: usize },
+    // start of the loop, and the jumps out of it created by while
+    Loop { start: u16, exits: Vec<usize> },
+}
+
+// skip instructions for a condition, the first one skipping when the condition holds
+struct Condition {
+    skip_if_true: Instruction,
+    skip_if_false: Instruction,
+}
+
+struct Assembler {
+    tokens: VecDeque<Token>,
+    line: usize,
+
+    rom: Vec<u8>,
+    position: usize,
+
+    symbols: BTreeMap<String, u16>,
+    // source line of the instructions, by address
+    lines: BTreeMap<u16, usize>,
+    constants: HashMap<String, i32>,
+    aliases: HashMap<String, u8>,
+    macros: HashMap<String, Macro>,
+
+    fixups: Vec<Fixup>,
+    blocks: Vec<Block>,
+}
+
+impl Assembler {
+    fn new(source: &str) -> Self {
+        Self {
+            tokens: tokenize(source),
+            line: 1,
+            rom: Vec::new(),
+            position: 0,
+            symbols: BTreeMap::new(),
+            lines: BTreeMap::new(),
+            constants: HashMap::new(),
+            aliases: HashMap::new(),
+            macros: HashMap::new(),
+            fixups: Vec::new(),
+            blocks: Vec::new(),
+        }
+    }
+
+    fn run(&mut self) -> Result<(), AssembleError> {
+        // the program starts at main, jumped to from 0x200 if there is one
+        let main = self
+            .tokens
+            .iter()
+            .zip(self.tokens.iter().skip(1))
+            .find(|(colon, name)| colon.text == ":" && name.text == "main")
+            .map(|(colon, _)| colon.line);
+
+        // the jump belongs to the line of the label
+        if let Some(line) = main {
+            self.line = line;
+            self.emit_jump_to("main", FixupKind::Address, 0x1000)?;
+        }
+
+        while let Some(token) = self.next_token() {
+            self.statement(token)?;
+        }
+
+        if !self.blocks.is_empty() {
+            return Err(self.error("missing end or again at the end of the file"));
+        }
+
+        self.resolve_fixups()
+    }
+
+    fn error(&self, message: impl Into<String>) -> AssembleError {
+        AssembleError {
+            line: self.line,
+            message: message.into(),
+        }
+    }
+
+    // address of the next byte, which has to be in the 64 KiB of memory
+    fn here(&self) -> Result<u16, AssembleError> {
+        u16::try_from(ORIGIN as usize + self.position)
+            .map_err(|_| self.error("the program goes past the end of memory"))
+    }
+
+    fn next_token(&mut self) -> Option<Token> {
+        let token = self.tokens.pop_front()?;
+
+        self.line = token.line;
+
+        Some(token)
+    }
+
+    fn expect_token(&mut self) -> Result<Token, AssembleError> {
+        self.next_token()
+            .ok_or_else(|| self.error("unexpected end of file"))
+    }
+
+    fn expect(&mut self, text: &str) -> Result<(), AssembleError> {
+        let token = self.expect_token()?;
+
+        if token.text == text {
+            Ok(())
+        } else {
+            Err(self.error(format!("expected '{}', found '{}'", text, token.text)))
+        }
+    }
+
+    fn peek_is(&self, text: &str) -> bool {
+        self.tokens.front().is_some_and(|token| token.text == text)
+    }
+
+    fn emit_byte(&mut self, value: u8) -> Result<(), AssembleError> {
+        self.here()?;
+
+        if self.position < self.rom.len() {
+            self.rom[self.position] = value;
+        } else {
+            self.rom.resize(self.position, 0);
+            self.rom.push(value);
+        }
+
+        self.position += 1;
+
+        Ok(())
+    }
+
+    fn emit_word(&mut self, value: u16) -> Result<(), AssembleError> {
+        self.lines.insert(self.here()?, self.line);
+
+        self.emit_byte((value >> 8) as u8)?;
+        self.emit_byte(value as u8)
+    }
+
+    fn emit(&mut self, instruction: Instruction) -> Result<(), AssembleError> {
+        self.emit_word(encode(&instruction))
+    }
+
+    fn write_word(&mut self, position: usize, value: u16) {
+        self.rom[position] = (value >> 8) as u8;
+        self.rom[position + 1] = value as u8;
+    }
+
+    // emits a jump, call or load of I to a label that might not be defined yet
+    fn emit_jump_to(
+        &mut self,
+        label: &str,
+        kind: FixupKind,
+        op_code: u16,
+    ) -> Result<(), AssembleError> {
+        self.fixups.push(Fixup {
+            position: self.position,
+            kind,
+            label: label.to_string(),
+            line: self.line,
+        });
+
+        self.emit_word(op_code)
+    }
+
+    fn emit_address_operand(&mut self, op_code: u16) -> Result<(), AssembleError> {
+        let token = self.expect_token()?;
+
+        match self.value_of(&token.text) {
+            Some(value) => {
+                let address = self.check_range(value, 0, 0xFFF)? as u16;
+
+                self.emit_word(op_code | address)?;
+            }
+            None => {
+                self.check_identifier(&token.text)?;
+                self.emit_jump_to(&token.text, FixupKind::Address, op_code)?;
+            }
+        }
+
+        Ok(())
+    }
+
+    fn resolve_fixups(&mut self) -> Result<(), AssembleError> {
+        for fixup in std::mem::take(&mut self.fixups) {
+            let address = match self.symbols.get(&fixup.label) {
+                Some(address) => *address,
+                None => {
+                    return Err(AssembleError {
+                        line: fixup.line,
+                        message: format!("undefined label '{}'", fixup.label),
+                    })
+                }
+            };
+
+            match fixup.kind {
+                FixupKind::Address => {
+                    if address > 0xFFF {
+                        return Err(AssembleError {
+                            line: fixup.line,
+                            message: format!("label '{}' is out of 12 bits range", fixup.label),
+                        });
+                    }
+
+                    let op_code = ((self.rom[fixup.position] as u16) << 8) & 0xF000;
+                    self.write_word(fixup.position, op_code | address);
+                }
+                FixupKind::LongAddress => self.write_word(fixup.position + 2, address),
+            }
+        }
+
+        Ok(())
+    }
+
+    fn parse_number(text: &str) -> Option<i32> {
+        let (negative, digits) = match text.strip_prefix('-') {
+            Some(digits) => (true, digits),
+            None => (false, text),
+        };
+
+        let value = if let Some(hex) = digits.strip_prefix("0x") {
+            i32::from_str_radix(hex, 16).ok()?
+        } else if let Some(binary) = digits.strip_prefix("0b") {
+            i32::from_str_radix(binary, 2).ok()?
+        } else {
+            digits.parse::<i32>().ok()?
+        };
+
+        Some(if negative { -value } else { value })
+    }
+
+    // numbers, constants and already defined labels
+    fn value_of(&self, text: &str) -> Option<i32> {
+        Self::parse_number(text)
+            .or_else(|| self.constants.get(text).copied())
+            .or_else(|| self.symbols.get(text).map(|address| *address as i32))
+    }
+
+    fn check_range(&self, value: i32, min: i32, max: i32) -> Result<i32, AssembleError> {
+        if value < min || value > max {
+            Err(self.error(format!("value {} is out of range", value)))
+        } else {
+            Ok(value)
+        }
+    }
+
+    fn check_identifier(&self, text: &str) -> Result<(), AssembleError> {
+        let valid = text
+            .chars()
+            .next()
+            .is_some_and(|first| first.is_alphabetic() || first == '_')
+            && text
+                .chars()
+                .all(|c| c.is_alphanumeric() || c == '_' || c == '-');
+
+        if valid && self.register(text).is_none() {
+            Ok(())
+        } else {
+            Err(self.error(format!("invalid name '{}'", text)))
+        }
+    }
+
+    fn register(&self, text: &str) -> Option<u8> {
+        if let Some(index) = self.aliases.get(text) {
+            return Some(*index);
+        }
+
+        let lower = text.to_ascii_lowercase();
+        let digit = lower.strip_prefix('v')?;
+
+        if digit.len() == 1 {
+            u8::from_str_radix(digit, 16).ok()
+        } else {
+            None
+        }
+    }
+
+    fn expect_register(&mut self) -> Result<u8, AssembleError> {
+        let token = self.expect_token()?;
+
+        self.register(&token.text)
+            .ok_or_else(|| self.error(format!("expected a register, found '{}'", token.text)))
+    }
+
+    fn expect_value(&mut self, min: i32, max: i32) -> Result<i32, AssembleError> {
+        let token = self.expect_token()?;
+
+        match self.value_of(&token.text) {
+            Some(value) => self.check_range(value, min, max),
+            None => Err(self.error(format!("expected a value, found '{}'", token.text))),
+        }
+    }
+
+    // bytes can also be written as negative numbers
+    fn expect_byte(&mut self) -> Result<u8, AssembleError> {
+        Ok(self.expect_value(-128, 255)? as u8)
+    }
+
+    fn statement(&mut self, token: Token) -> Result<(), AssembleError> {
+        if let Some(expansion) = self.expand_macro(&token)? {
+            for token in expansion.into_iter().rev() {
+                self.tokens.push_front(token);
+            }
+
+            return Ok(());
+        }
+
+        if let Some(x) = self.register(&token.text) {
+            return self.register_statement(x);
+        }
+
+        match token.text.as_str() {
+            ":" => {
+                let name = self.expect_token()?.text;
+                self.define_symbol(&name, self.here()?)?;
+            }
+            ":const" => {
+                let name = self.expect_token()?.text;
+                let value = self.expect_value(-0x8000, 0xFFFF)?;
+
+                self.check_identifier(&name)?;
+                self.constants.insert(name, value);
+            }
+            ":alias" => {
+                let name = self.expect_token()?.text;
+                let x = self.expect_register()?;
+
+                self.check_identifier(&name)?;
+                self.aliases.insert(name, x);
+            }
+            ":macro" => self.define_macro()?,
+            ":org" => {
+                self.position =
+                    (self.expect_value(ORIGIN as i32, 0xFFFF)? as u16 - ORIGIN) as usize;
+            }
+            ":byte" => {
+                let value = self.expect_byte()?;
+                self.emit_byte(value)?;
+            }
+            ":call" => self.emit_address_operand(0x2000)?,
+            // debugging hints, not part of the rom
+            ":breakpoint" => {
+                self.expect_token()?;
+            }
+            ":monitor" => {
+                self.expect_token()?;
+                self.expect_token()?;
+            }
+            "clear" => self.emit(Instruction::Cls)?,
+            "return" | ";" => self.emit(Instruction::Ret)?,
+            "exit" => self.emit(Instruction::Exit)?,
+            "lores" => self.emit(Instruction::Low)?,
+            "hires" => self.emit(Instruction::High)?,
+            "scroll-down" => {
+                let n = self.expect_value(0, 15)? as u8;
+                self.emit(Instruction::Scd { n })?;
+            }
+            "scroll-up" => {
+                let n = self.expect_value(0, 15)? as u8;
+                self.emit(Instruction::Scu { n })?;
+            }
+            "scroll-left" => self.emit(Instruction::Scl)?,
+            "scroll-right" => self.emit(Instruction::Scr)?,
+            "audio" => self.emit(Instruction::Audio)?,
+            "plane" => {
+                let n = self.expect_value(0, 3)? as u8;
+                self.emit(Instruction::Plane { n })?;
+            }
+            "jump" => self.emit_address_operand(0x1000)?,
+            "jump0" => self.emit_address_operand(0xB000)?,
+            "bcd" => {
+                let x = self.expect_register()?;
+                self.emit(Instruction::LdB { x })?;
+            }
+            "save" | "load" => self.save_or_load(&token.text)?,
+            "saveflags" => {
+                let x = self.expect_register()?;
+                self.emit(Instruction::LdRVx { x })?;
+            }
+            "loadflags" => {
+                let x = self.expect_register()?;
+                self.emit(Instruction::LdVxR { x })?;
+            }
+            "sprite" => {
+                let x = self.expect_register()?;
+                let y = self.expect_register()?;
+                let n = self.expect_value(0, 15)? as u8;
+
+                self.emit(Instruction::Drw { x, y, n })?;
+            }
+            "i" => self.i_statement()?,
+            "delay" | "buzzer" | "pitch" => {
+                self.expect(":=")?;
+                let x = self.expect_register()?;
+
+                self.emit(match token.text.as_str() {
+                    "delay" => Instruction::LdDtVx { x },
+                    "buzzer" => Instruction::LdStVx { x },
+                    _ => Instruction::Pitch { x },
+                })?;
+            }
+            "if" => self.if_statement()?,
+            "else" => match self.blocks.pop() {
+                Some(Block::If { jump }) => {
+                    let end_jump = self.position;
+
+                    self.emit_word(0x1000)?;
+                    self.patch_jump(jump)?;
+                    self.blocks.push(Block::Else { jump: end_jump });
+                }
+                _ => return Err(self.error("else without if ... begin")),
+            },
+            "end" => match self.blocks.pop() {
+                Some(Block::If { jump }) | Some(Block::Else { jump }) => self.patch_jump(jump)?,
+                _ => return Err(self.error("end without if ... begin")),
+            },
+            "loop" => self.blocks.push(Block::Loop {
+                start: self.here()?,
+                exits: Vec::new(),
+            }),
+            "while" => {
+                let condition = self.condition()?;
+                let jump = self.position;
+
+                self.emit(condition.skip_if_true)?;
+                self.emit_word(0x1000)?;
+
+                match self.blocks.iter_mut().rev().find_map(|block| match block {
+                    Block::Loop { exits, .. } => Some(exits),
+                    _ => None,
+                }) {
+                    Some(exits) => exits.push(jump + 2),
+                    None => return Err(self.error("while outside of a loop")),
+                }
+            }
+            "again" => match self.blocks.pop() {
+                Some(Block::Loop { start, exits }) => {
+                    let addr = self.jump_target(start)?;
+                    self.emit(Instruction::Jp { addr })?;
+
+                    for exit in exits {
+                        self.patch_jump(exit)?;
+                    }
+                }
+                _ => return Err(self.error("again without loop")),
+            },
+            // labels are called, numbers and constants are raw bytes used for sprites and data
+            text => match Self::parse_number(text).or_else(|| self.constants.get(text).copied()) {
+                Some(value) => {
+                    let value = self.check_range(value, -128, 255)?;
+                    self.emit_byte(value as u8)?;
+                }
+                None => {
+                    self.check_identifier(text)?;
+                    self.emit_jump_to(text, FixupKind::Address, 0x2000)?;
+                }
+            },
+        }
+
+        Ok(())
+    }
+
+    fn define_symbol(&mut self, name: &str, address: u16) -> Result<(), AssembleError> {
+        self.check_identifier(name)?;
+
+        if self.symbols.insert(name.to_string(), address).is_some() {
+            return Err(self.error(format!("label '{}' is already defined", name)));
+        }
+
+        Ok(())
+    }
+
+    fn patch_jump(&mut self, position: usize) -> Result<(), AssembleError> {
+        let address = self.jump_target(self.here()?)?;
+
+        self.write_word(position, 0x1000 | address);
+
+        Ok(())
+    }
+
+    // jumps only reach the first 4 KiB, like the labels of resolve_fixups
+    fn jump_target(&self, address: u16) -> Result<u16, AssembleError> {
+        if address > 0xFFF {
+            return Err(self.error(format!(
+                "jump target {:#06x} is out of 12 bits range",
+                address
+            )));
+        }
+
+        Ok(address)
+    }
+
+    fn define_macro(&mut self) -> Result<(), AssembleError> {
+        let name = self.expect_token()?.text;
+        let mut parameters = Vec::new();
+
+        self.check_identifier(&name)?;
+
+        loop {
+            let token = self.expect_token()?;
+
+            if token.text == "{" {
+                break;
+            }
+
+            parameters.push(token.text);
+        }
+
+        let mut body = Vec::new();
+
+        loop {
+            let token = self.expect_token()?;
+
+            if token.text == "}" {
+                break;
+            }
+
+            body.push(token);
+        }
+
+        self.macros.insert(name, Macro { parameters, body });
+
+        Ok(())
+    }
+
+    fn expand_macro(&mut self, token: &Token) -> Result<Option<Vec<Token>>, AssembleError> {
+        let parameter_count = match self.macros.get(&token.text) {
+            Some(definition) => definition.parameters.len(),
+            None => return Ok(None),
+        };
+
+        if token.depth >= MAX_MACRO_DEPTH {
+            return Err(self.error(format!(
+                "macro '{}' expands too deeply, it is probably recursive",
+                token.text
+            )));
+        }
+
+        let mut arguments = Vec::new();
+
+        for _ in 0..parameter_count {
+            arguments.push(self.expect_token()?.text);
+        }
+
+        let definition = &self.macros[&token.text];
+        let expansion = definition
+            .body
+            .iter()
+            .map(|body_token| {
+                let text = match definition
+                    .parameters
+                    .iter()
+                    .position(|parameter| *parameter == body_token.text)
+                {
+                    Some(index) => arguments[index].clone(),
+                    None => body_token.text.clone(),
+                };
+
+                // errors in the expansion are reported at the invocation
+                Token {
+                    text,
+                    line: token.line,
+                    depth: token.depth + 1,
+                }
+            })
+            .collect();
+
+        Ok(Some(expansion))
+    }
+
+    fn save_or_load(&mut self, statement: &str) -> Result<(), AssembleError> {
+        let x = self.expect_register()?;
+
+        if self.peek_is("-") {
+            self.expect("-")?;
+            let y = self.expect_register()?;
+
+            self.emit(match statement {
+                "save" => Instruction::SaveRange { x, y },
+                _ => Instruction::LoadRange { x, y },
+            })?;
+        } else {
+            self.emit(match statement {
+                "save" => Instruction::LdIVx { x },
+                _ => Instruction::LdVxI { x },
+            })?;
+        }
+
+        Ok(())
+    }
+
+    fn i_statement(&mut self) -> Result<(), AssembleError> {
+        let operator = self.expect_token()?.text;
+
+        match operator.as_str() {
+            ":=" => {
+                if self.peek_is("hex") || self.peek_is("bighex") {
+                    let big = self.expect_token()?.text == "bighex";
+                    let x = self.expect_register()?;
+
+                    self.emit(if big {
+                        Instruction::LdHf { x }
+                    } else {
+                        Instruction::LdF { x }
+                    })?;
+                } else if self.peek_is("long") {
+                    self.expect("long")?;
+                    let token = self.expect_token()?;
+
+                    match self.value_of(&token.text) {
+                        Some(value) => {
+                            let address = self.check_range(value, 0, 0xFFFF)? as u16;
+
+                            self.emit(Instruction::LdLongI)?;
+                            self.emit_word(address)?;
+                        }
+                        None => {
+                            self.check_identifier(&token.text)?;
+                            self.emit_jump_to(&token.text, FixupKind::LongAddress, 0xF000)?;
+                            self.emit_word(0)?;
+                        }
+                    }
+                } else {
+                    self.emit_address_operand(0xA000)?;
+                }
+            }
+            "+=" => {
+                let x = self.expect_register()?;
+                self.emit(Instruction::AddI { x })?;
+            }
+            _ => return Err(self.error(format!("unknown operator '{}' for i", operator))),
+        }
+
+        Ok(())
+    }
+
+    fn register_statement(&mut self, x: u8) -> Result<(), AssembleError> {
+        let operator = self.expect_token()?.text;
+        let token = self.expect_token()?;
+        let y = self.register(&token.text);
+
+        let instruction = match (operator.as_str(), y) {
+            (":=", Some(y)) => Instruction::LdReg { x, y },
+            (":=", None) => match token.text.as_str() {
+                "random" => Instruction::Rnd {
+                    x,
+                    byte: self.expect_byte()?,
+                },
+                "delay" => Instruction::LdVxDt { x },
+                "key" => Instruction::LdVxK { x },
+                _ => Instruction::LdByte {
+                    x,
+                    byte: self.byte_of(&token.text)?,
+                },
+            },
+            ("+=", Some(y)) => Instruction::AddReg { x, y },
+            ("+=", None) => Instruction::AddByte {
+                x,
+                byte: self.byte_of(&token.text)?,
+            },
+            ("-=", Some(y)) => Instruction::Sub { x, y },
+            // subtracting a constant is adding its opposite
+            ("-=", None) => Instruction::AddByte {
+                x,
+                byte: self.byte_of(&token.text)?.wrapping_neg(),
+            },
+            ("|=", Some(y)) => Instruction::Or { x, y },
+            ("&=", Some(y)) => Instruction::And { x, y },
+            ("^=", Some(y)) => Instruction::Xor { x, y },
+            ("=-", Some(y)) => Instruction::Subn { x, y },
+            (">>=", Some(y)) => Instruction::Shr { x, y },
+            ("<<=", Some(y)) => Instruction::Shl { x, y },
+            ("|=" | "&=" | "^=" | "=-" | ">>=" | "<<=", None) => {
+                return Err(self.error(format!("expected a register, found '{}'", token.text)))
+            }
+            _ => return Err(self.error(format!("unknown operator '{}'", operator))),
+        };
+
+        self.emit(instruction)?;
+
+        Ok(())
+    }
+
+    fn byte_of(&self, text: &str) -> Result<u8, AssembleError> {
+        match self.value_of(text) {
+            Some(value) => Ok(self.check_range(value, -128, 255)? as u8),
+            None => Err(self.error(format!("expected a value, found '{}'", text))),
+        }
+    }
+
+    fn condition(&mut self) -> Result<Condition, AssembleError> {
+        let x = self.expect_register()?;
+        let operator = self.expect_token()?.text;
+
+        let condition = match operator.as_str() {
+            "key" => Condition {
+                skip_if_true: Instruction::Skp { x },
+                skip_if_false: Instruction::Sknp { x },
+            },
+            "-key" => Condition {
+                skip_if_true: Instruction::Sknp { x },
+                skip_if_false: Instruction::Skp { x },
+            },
+            "==" | "!=" => {
+                let token = self.expect_token()?;
+                let (equal, not_equal) = match self.register(&token.text) {
+                    Some(y) => (Instruction::SeReg { x, y }, Instruction::SneReg { x, y }),
+                    None => {
+                        let byte = self.byte_of(&token.text)?;
+
+                        (
+                            Instruction::SeByte { x, byte },
+                            Instruction::SneByte { x, byte },
+                        )
+                    }
+                };
+
+                if operator == "==" {
+                    Condition {
+                        skip_if_true: equal,
+                        skip_if_false: not_equal,
+                    }
+                } else {
+                    Condition {
+                        skip_if_true: not_equal,
+                        skip_if_false: equal,
+                    }
+                }
+            }
+            _ => return Err(self.error(format!("unsupported condition '{}'", operator))),
+        };
+
+        Ok(condition)
+    }
+
+    fn if_statement(&mut self) -> Result<(), AssembleError> {
+        let condition = self.condition()?;
+        let keyword = self.expect_token()?.text;
+
+        match keyword.as_str() {
+            // the next statement is skipped when the condition does not hold
+            "then" => self.emit(condition.skip_if_false)?,
+            // the jump to else or end is skipped when the condition holds
+            "begin" => {
+                self.emit(condition.skip_if_true)?;
+                self.blocks.push(Block::If {
+                    jump: self.position,
+                });
+                self.emit_word(0x1000)?;
+            }
+            _ => return Err(self.error(format!("expected 'then' or 'begin', found '{}'", keyword))),
+        }
+
+        Ok(())
+    }
+}
@@ -0,0 +1,66 @@
+use chip8_asm::assemble;
+
+use clap::Parser;
+use std::fs;
+use std::path::Path;
+use std::process::ExitCode;
+
+fn main() -> ExitCode {
+    // cli arguments
+    let args = Args::parse();
+
+    let source = match fs::read_to_string(&args.path) {
+        Ok(source) => source,
+        Err(error) => {
+            eprintln!("Could not load file {}: {}", args.path, error);
+            return ExitCode::FAILURE;
+        }
+    };
+
+    let program = match assemble(&source) {
+        Ok(program) => program,
+        Err(error) => {
+            eprintln!("{}: {}", args.path, error);
+            return ExitCode::FAILURE;
+        }
+    };
+
+    // next to the source by default
+    let output = args.output.unwrap_or_else(|| {
+        Path::new(&args.path)
+            .with_extension("ch8")
+            .to_string_lossy()
+            .into_owned()
+    });
+
+    if let Err(error) = fs::write(&output, &program.rom) {
+        eprintln!("Could not write file {}: {}", output, error);
+        return ExitCode::FAILURE;
+    }
+
+    if let Some(symbols) = args.symbols {
+        if let Err(error) = fs::write(&symbols, program.symbol_table()) {
+            eprintln!("Could not write file {}: {}", symbols, error);
+            return ExitCode::FAILURE;
+        }
+    }
+
+    ExitCode::SUCCESS
+}
+
+// cli arguments struct
+#[derive(Parser, Debug)]
+#[command(version, about = "Assembles Octo source code into a CHIP-8 rom", long_about = None)]
+struct Args {
+    /// Path to the .8o source
+    #[arg(short, long)]
+    path: String,
+
+    /// Path of the rom to write, defaults to the source path with a .ch8 extension
+    #[arg(short, long)]
+    output: Option<String>,
+
+    /// Path of the symbol table to write
+    #[arg(short, long)]
+    symbols: Option<String>,
+}
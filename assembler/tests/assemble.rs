@@ -0,0 +1,187 @@
+// Octo sources assembled into roms, and the errors they report
+
+use chip8_asm::{assemble, AssembleError};
+
+fn rom(source: &str) -> Vec<u8> {
+    assemble(source).unwrap().rom
+}
+
+fn error(source: &str) -> AssembleError {
+    assemble(source).err().unwrap()
+}
+
+#[test]
+fn statements() {
+    assert_eq!(
+        rom("clear v0 := 5 v1 += v0 i := 0x300 sprite v0 v1 4 return"),
+        [0x00, 0xE0, 0x60, 0x05, 0x81, 0x04, 0xA3, 0x00, 0xD0, 0x14, 0x00, 0xEE]
+    );
+    // subtracting a constant adds its opposite
+    assert_eq!(rom("v2 -= 1 v3 := random 0x0f"), [0x72, 0xFF, 0xC3, 0x0F]);
+    assert_eq!(
+        rom("i := long 0x1234 plane 3 save v1 - v4"),
+        [0xF0, 0x00, 0x12, 0x34, 0xF3, 0x01, 0x51, 0x42]
+    );
+}
+
+#[test]
+fn labels_jump_to_main() {
+    let program = assemble(": sprite 0x80 0x40\n: main\ni := sprite\njump main").unwrap();
+
+    assert_eq!(
+        program.rom,
+        [0x12, 0x04, 0x80, 0x40, 0xA2, 0x02, 0x12, 0x04]
+    );
+    assert_eq!(program.symbols["sprite"], 0x202);
+    assert_eq!(program.symbols["main"], 0x204);
+    assert_eq!(program.address_of_line(3), Some((0x204, 3)));
+}
+
+#[test]
+fn labels_defined_later_are_called() {
+    assert_eq!(
+        rom("draw return : draw clear ;"),
+        [0x22, 0x04, 0x00, 0xEE, 0x00, 0xE0, 0x00, 0xEE]
+    );
+}
+
+#[test]
+fn constants_aliases_and_macros() {
+    let source = "
+        :const speed 3
+        :alias x v4
+        :macro move register amount { register += amount }
+        move x speed
+        move v5 -1
+    ";
+
+    assert_eq!(rom(source), [0x74, 0x03, 0x75, 0xFF]);
+}
+
+#[test]
+fn if_then() {
+    // the next statement is skipped when the condition does not hold
+    assert_eq!(
+        rom("if v0 == 3 then v1 := 1 if v0 key then clear"),
+        [0x40, 0x03, 0x61, 0x01, 0xE0, 0xA1, 0x00, 0xE0]
+    );
+}
+
+#[test]
+fn if_begin_else_end() {
+    assert_eq!(
+        rom("if v0 != v1 begin v2 := 1 else v2 := 2 end"),
+        [
+            0x90, 0x10, // skips the jump to else when v0 != v1
+            0x12, 0x08, // jump to else
+            0x62, 0x01, // v2 := 1
+            0x12, 0x0A, // jump to end
+            0x62, 0x02, // v2 := 2
+        ]
+    );
+}
+
+#[test]
+fn loop_while_again() {
+    assert_eq!(
+        rom("loop v0 += 1 while v0 != 10 again"),
+        [
+            0x70, 0x01, // v0 += 1
+            0x40, 0x0A, // skips the jump out when v0 != 10
+            0x12, 0x08, // jump out of the loop
+            0x12, 0x00, // again
+        ]
+    );
+}
+
+#[test]
+fn org_and_byte() {
+    assert_eq!(
+        rom("clear :org 0x206 :byte 0xAB :byte -1"),
+        [0x00, 0xE0, 0x00, 0x00, 0x00, 0x00, 0xAB, 0xFF]
+    );
+}
+
+#[test]
+fn errors() {
+    assert_eq!(
+        error("clear\njump nowhere"),
+        AssembleError {
+            line: 2,
+            message: "undefined label 'nowhere'".to_string()
+        }
+    );
+    assert_eq!(error("v0 := 256").message, "value 256 is out of range");
+    assert_eq!(error("v0 |= 3").message, "expected a register, found '3'");
+    assert_eq!(error(": a : a").message, "label 'a' is already defined");
+    assert_eq!(error("end").message, "end without if ... begin");
+    assert_eq!(
+        error("loop v0 += 1").message,
+        "missing end or again at the end of the file"
+    );
+    assert_eq!(error("while v0 == 1").message, "while outside of a loop");
+    assert_eq!(
+        error("jump far\n:org 0x1000\n: far").message,
+        "label 'far' is out of 12 bits range"
+    );
+}
+
+#[test]
+fn block_jumps_past_0xfff() {
+    assert_eq!(
+        error(":org 0x1000\nloop v0 += 1\nagain"),
+        AssembleError {
+            line: 3,
+            message: "jump target 0x1000 is out of 12 bits range".to_string()
+        }
+    );
+    assert_eq!(
+        error(":org 0x0FFC\nif v0 == 1 begin v1 := 1 end").message,
+        "jump target 0x1002 is out of 12 bits range"
+    );
+    assert_eq!(
+        error(":org 0x0FFA\nloop while v0 != 1 again").message,
+        "jump target 0x1000 is out of 12 bits range"
+    );
+
+    // a block ending on the last address of the first 4 KiB is fine
+    assert_eq!(
+        rom(":org 0x0FF8\nif v0 == 1 begin v1 := 1 end")[0xFFA - 0x200..0xFFC - 0x200],
+        [0x1F, 0xFE]
+    );
+}
+
+#[test]
+fn program_past_the_end_of_memory() {
+    assert_eq!(
+        error(":org 0xFFFE\nclear\nclear"),
+        AssembleError {
+            line: 3,
+            message: "the program goes past the end of memory".to_string()
+        }
+    );
+    assert_eq!(
+        error(":org 0xFFFF\n:byte 1\n: after").message,
+        "the program goes past the end of memory"
+    );
+
+    // the last word of memory is fine
+    assert_eq!(rom(":org 0xFFFE clear").len(), 0x10000 - 0x200);
+}
+
+#[test]
+fn recursive_macros() {
+    assert_eq!(
+        error(":macro forever { clear forever }\n\nforever"),
+        AssembleError {
+            line: 3,
+            message: "macro 'forever' expands too deeply, it is probably recursive".to_string()
+        }
+    );
+
+    // macros using other macros are fine
+    assert_eq!(
+        rom(":macro one { v0 += 1 } :macro two { one one } two"),
+        [0x70, 0x01, 0x70, 0x01]
+    );
+}
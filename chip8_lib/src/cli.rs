@@ -0,0 +1,163 @@
+use crate::config::{Config, FaultPolicies, FaultPolicy, DEFAULT_CPU_HZ};
+use crate::platform::Platform;
+use crate::quirks::Quirks;
+use crate::random::Generator;
+use crate::timing::Timing;
+use clap::{Args, ValueEnum};
+
+// Command line values shared by the binaries, converted into their configuration counterparts
+
+// Arguments of the emulator configuration, flattened into the arguments of every binary
+#[derive(Args, Clone, Debug)]
+pub struct ConfigArgs {
+    /// Platform the rom was written for
+    #[arg(long, value_enum, default_value_t = PlatformArg::Chip8)]
+    pub platform: PlatformArg,
+
+    /// Interpreter whose quirks are emulated, defaults to the ones of the platform
+    #[arg(short, long, value_enum)]
+    pub quirks: Option<QuirksProfile>,
+
+    /// Instructions run per second, the timers running at 60 Hz whatever the speed
+    #[arg(long, default_value_t = DEFAULT_CPU_HZ, value_parser = clap::value_parser!(u32).range(1..))]
+    pub cpu_hz: u32,
+
+    /// How emulated time is counted
+    #[arg(long, value_enum, default_value_t = TimingArg::Instructions)]
+    pub timing: TimingArg,
+
+    /// What to do on every fault, defaults to halting on stack faults, logging invalid opcodes and wrapping addresses
+    #[arg(long, value_enum)]
+    pub faults: Option<FaultPolicyArg>,
+
+    /// Generator of the random numbers of cxnn
+    #[arg(long, value_enum, default_value_t = GeneratorArg::Seeded)]
+    pub random: GeneratorArg,
+
+    /// Seed of the random numbers, a random one by default
+    #[arg(long)]
+    pub seed: Option<u64>,
+}
+
+impl ConfigArgs {
+    pub fn config(&self) -> Config {
+        let mut config = Config::new(self.platform.into())
+            .with_cpu_hz(self.cpu_hz)
+            .with_timing(self.timing.into())
+            .with_generator(self.random.into());
+
+        if let Some(quirks) = self.quirks {
+            config = config.with_quirks(quirks.into());
+        }
+
+        if let Some(policy) = self.faults {
+            config = config.with_faults(FaultPolicies::all(policy.into()));
+        }
+
+        if let Some(seed) = self.seed {
+            config = config.with_seed(seed);
+        }
+
+        config
+    }
+}
+
+#[derive(ValueEnum, Clone, Copy, Debug)]
+pub enum PlatformArg {
+    /// COSMAC VIP CHIP-8, 4 KiB of memory
+    Chip8,
+    /// SUPER-CHIP 1.1
+    Schip,
+    /// XO-CHIP, 64 KiB of memory and 4 colours
+    Xochip,
+}
+
+impl From<PlatformArg> for Platform {
+    fn from(platform: PlatformArg) -> Self {
+        match platform {
+            PlatformArg::Chip8 => Platform::Chip8,
+            PlatformArg::Schip => Platform::SuperChip,
+            PlatformArg::Xochip => Platform::XoChip,
+        }
+    }
+}
+
+#[derive(ValueEnum, Clone, Copy, Debug)]
+pub enum QuirksProfile {
+    /// COSMAC VIP CHIP-8
+    Vip,
+    /// HP-48 CHIP-48
+    Chip48,
+    /// HP-48 SUPER-CHIP 1.1
+    Schip,
+    /// Octo XO-CHIP
+    Xochip,
+}
+
+impl From<QuirksProfile> for Quirks {
+    fn from(profile: QuirksProfile) -> Self {
+        match profile {
+            QuirksProfile::Vip => Quirks::cosmac_vip(),
+            QuirksProfile::Chip48 => Quirks::chip48(),
+            QuirksProfile::Schip => Quirks::super_chip(),
+            QuirksProfile::Xochip => Quirks::xo_chip(),
+        }
+    }
+}
+
+#[derive(ValueEnum, Clone, Copy, Debug)]
+pub enum TimingArg {
+    /// Every instruction takes a cycle at --cpu-hz
+    Instructions,
+    /// Every instruction takes its COSMAC VIP machine cycles, and drawing waits for the vertical blank
+    Vip,
+}
+
+impl From<TimingArg> for Timing {
+    fn from(timing: TimingArg) -> Self {
+        match timing {
+            TimingArg::Instructions => Timing::Instructions,
+            TimingArg::Vip => Timing::CosmacVip,
+        }
+    }
+}
+
+#[derive(ValueEnum, Clone, Copy, Debug)]
+pub enum FaultPolicyArg {
+    /// Stop on the faulting instruction and fail
+    Halt,
+    /// Skip the faulting instruction
+    Ignore,
+    /// Print the fault and skip the instruction
+    Log,
+    /// Wrap the stack and addresses around
+    Wrap,
+}
+
+impl From<FaultPolicyArg> for FaultPolicy {
+    fn from(policy: FaultPolicyArg) -> Self {
+        match policy {
+            FaultPolicyArg::Halt => FaultPolicy::Halt,
+            FaultPolicyArg::Ignore => FaultPolicy::Ignore,
+            FaultPolicyArg::Log => FaultPolicy::Log,
+            FaultPolicyArg::Wrap => FaultPolicy::Wrap,
+        }
+    }
+}
+
+#[derive(ValueEnum, Clone, Copy, Debug)]
+pub enum GeneratorArg {
+    /// Seeded pseudo-random generator
+    Seeded,
+    /// Sum of the bytes of the font page, poor numbers but not the ones of a COSMAC VIP
+    PageSum,
+}
+
+impl From<GeneratorArg> for Generator {
+    fn from(generator: GeneratorArg) -> Self {
+        match generator {
+            GeneratorArg::Seeded => Generator::Seeded,
+            GeneratorArg::PageSum => Generator::PageSum,
+        }
+    }
+}
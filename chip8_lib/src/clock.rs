@@ -0,0 +1,120 @@
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::Arc;
+use std::time::{Duration, Instant};
+
+const NANOS_PER_SECOND: u64 = 1_000_000_000;
+
+// Host time, never going backwards
+pub trait Clock: Send {
+    // time since an origin picked by the clock
+    fn now(&self) -> Duration;
+}
+
+// Time of the host, from when the clock was created
+pub struct MonotonicClock {
+    origin: Instant,
+}
+
+impl MonotonicClock {
+    pub fn new() -> Self {
+        Self {
+            origin: Instant::now(),
+        }
+    }
+}
+
+impl Default for MonotonicClock {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl Clock for MonotonicClock {
+    fn now(&self) -> Duration {
+        self.origin.elapsed()
+    }
+}
+
+// Time only moving when told to, for tests
+// clones share the same time, so one can be handed to the emulator and the other advanced
+#[derive(Clone, Default)]
+pub struct ManualClock {
+    nanos: Arc<AtomicU64>,
+}
+
+impl ManualClock {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn advance(&self, duration: Duration) {
+        self.nanos
+            .fetch_add(duration.as_nanos() as u64, Ordering::Relaxed);
+    }
+}
+
+impl Clock for ManualClock {
+    fn now(&self) -> Duration {
+        Duration::from_nanos(self.nanos.load(Ordering::Relaxed))
+    }
+}
+
+// Tells when frames are due at a steady rate, so a late frame doesn't delay the following ones
+pub struct FramePacer {
+    origin: Duration,
+    frames_per_second: u64,
+    // index of the next frame, due at origin + frame / frames_per_second
+    frame: u64,
+    // frames run at most to catch up, the pacer starts over from now when further behind
+    max_catch_up: u32,
+}
+
+impl FramePacer {
+    // the first frame is due right away
+    pub fn new(clock: &impl Clock, frames_per_second: u32) -> Self {
+        Self {
+            origin: clock.now(),
+            frames_per_second: frames_per_second.max(1) as u64,
+            frame: 0,
+            max_catch_up: 4,
+        }
+    }
+
+    pub fn with_max_catch_up(self, max_catch_up: u32) -> Self {
+        Self {
+            max_catch_up: max_catch_up.max(1),
+            ..self
+        }
+    }
+
+    // frames to run now, and marks them as run
+    pub fn frames_due(&mut self, clock: &impl Clock) -> u32 {
+        let now = clock.now();
+        let mut due = 0;
+
+        while due < self.max_catch_up && self.deadline(self.frame) <= now {
+            self.frame += 1;
+            due += 1;
+        }
+
+        // too far behind, like after the host was suspended, the missed frames are dropped
+        if self.deadline(self.frame) <= now {
+            self.origin = now;
+            self.frame = 1;
+        }
+
+        due
+    }
+
+    // time to sleep before the next frame is due
+    pub fn until_next_frame(&self, clock: &impl Clock) -> Duration {
+        self.deadline(self.frame).saturating_sub(clock.now())
+    }
+
+    fn deadline(&self, frame: u64) -> Duration {
+        let seconds = frame / self.frames_per_second;
+        let nanos = frame % self.frames_per_second * NANOS_PER_SECOND / self.frames_per_second;
+
+        self.origin + Duration::from_secs(seconds) + Duration::from_nanos(nanos)
+    }
+}
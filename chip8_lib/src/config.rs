@@ -0,0 +1,128 @@
+use crate::error::CpuFault;
+use crate::platform::Platform;
+use crate::quirks::Quirks;
+use crate::random::Generator;
+use crate::timing::Timing;
+
+// What the CPU does on a fault
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum FaultPolicy {
+    // stops on the faulting instruction, stepping returns the fault
+    Halt,
+    // skips the faulting instruction
+    Ignore,
+    // prints the fault, then skips the instruction like Ignore
+    Log,
+    // the stack and addresses wrap around and the instruction runs, invalid opcodes are skipped
+    // a call on a full stack drops the oldest return address, a return on an empty one uses the last slot
+    Wrap,
+}
+
+// Policy for every kind of fault
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct FaultPolicies {
+    pub stack_overflow: FaultPolicy,
+    pub stack_underflow: FaultPolicy,
+    pub invalid_opcode: FaultPolicy,
+    pub memory_out_of_range: FaultPolicy,
+}
+
+impl FaultPolicies {
+    // the same policy for every fault
+    pub fn all(policy: FaultPolicy) -> Self {
+        Self {
+            stack_overflow: policy,
+            stack_underflow: policy,
+            invalid_opcode: policy,
+            memory_out_of_range: policy,
+        }
+    }
+
+    pub fn policy(&self, fault: &CpuFault) -> FaultPolicy {
+        match fault {
+            CpuFault::StackOverflow { .. } => self.stack_overflow,
+            CpuFault::StackUnderflow { .. } => self.stack_underflow,
+            CpuFault::InvalidOpcode { .. } => self.invalid_opcode,
+            CpuFault::MemoryOutOfRange { .. } => self.memory_out_of_range,
+        }
+    }
+}
+
+// broken stacks halt, invalid opcodes are printed and memory wraps around like it always did
+impl Default for FaultPolicies {
+    fn default() -> Self {
+        Self {
+            stack_overflow: FaultPolicy::Halt,
+            stack_underflow: FaultPolicy::Halt,
+            invalid_opcode: FaultPolicy::Log,
+            memory_out_of_range: FaultPolicy::Wrap,
+        }
+    }
+}
+
+// instructions run per second, 10 per frame at 60 frames per second
+pub const DEFAULT_CPU_HZ: u32 = 600;
+
+// Everything needed to set up an emulator, besides the rom
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct Config {
+    pub platform: Platform,
+    pub quirks: Quirks,
+    // seeds the random numbers of cxnn, a random seed being picked when there is none
+    pub seed: Option<u64>,
+    pub generator: Generator,
+    // instructions run per second of emulated time, the timers always run at 60 Hz
+    pub cpu_hz: u32,
+    pub timing: Timing,
+    pub faults: FaultPolicies,
+}
+
+impl Config {
+    // platform with its default quirks
+    pub fn new(platform: Platform) -> Self {
+        Self {
+            platform,
+            quirks: platform.default_quirks(),
+            seed: None,
+            generator: Generator::Seeded,
+            cpu_hz: DEFAULT_CPU_HZ,
+            timing: Timing::Instructions,
+            faults: FaultPolicies::default(),
+        }
+    }
+
+    pub fn with_quirks(self, quirks: Quirks) -> Self {
+        Self { quirks, ..self }
+    }
+
+    // same random numbers on every run
+    pub fn with_seed(self, seed: u64) -> Self {
+        Self {
+            seed: Some(seed),
+            ..self
+        }
+    }
+
+    pub fn with_generator(self, generator: Generator) -> Self {
+        Self { generator, ..self }
+    }
+
+    // must not be 0
+    pub fn with_cpu_hz(self, cpu_hz: u32) -> Self {
+        Self { cpu_hz, ..self }
+    }
+
+    pub fn with_timing(self, timing: Timing) -> Self {
+        Self { timing, ..self }
+    }
+
+    pub fn with_faults(self, faults: FaultPolicies) -> Self {
+        Self { faults, ..self }
+    }
+}
+
+impl Default for Config {
+    fn default() -> Self {
+        Self::new(Platform::default())
+    }
+}
@@ -1,225 +1,612 @@
+use crate::config::{FaultPolicies, FaultPolicy};
+use crate::error::{CpuFault, StateError};
+use crate::instruction::{decode_for, Instruction};
 use crate::mmu::Mmu;
+use crate::platform::Platform;
+use crate::quirks::{MemoryIncrement, Quirks};
+use crate::random::RandomSource;
 use crate::registers::Registers;
-use rand::Rng;
-
-pub struct Cpu {}
+use crate::state::{StateReader, StateWriter};
+use crate::trace::Tracer;
+
+pub struct Cpu {
+    // opcodes of other platforms are invalid
+    platform: Platform,
+    quirks: Quirks,
+    faults: FaultPolicies,
+    // random numbers of cxnn
+    random: Box<dyn RandomSource>,
+    waiting_for_vblank: bool,
+    // set by fx0a until a key has been pressed and released
+    key_wait: Option<KeyWait>,
+    exited: bool,
+    // set once a fault halted the cpu, nothing runs afterwards
+    fault: Option<CpuFault>,
+    tracer: Option<Tracer>,
+}
+
+// fx0a waiting for a key, keys only change between frames
+#[derive(Clone, Copy)]
+struct KeyWait {
+    // register receiving the key
+    x: u8,
+    // key pressed, waiting for its release
+    key: Option<u8>,
+}
+
+// Flags read from a save state, restored once the whole state has been read
+pub struct CpuState {
+    waiting_for_vblank: bool,
+    key_wait: Option<KeyWait>,
+    exited: bool,
+    fault: Option<CpuFault>,
+    random: u64,
+}
 
 impl Cpu {
-    pub fn tick(&mut self, mmu: &mut Mmu, registers: &mut Registers) {
+    pub fn new(
+        platform: Platform,
+        quirks: Quirks,
+        faults: FaultPolicies,
+        random: Box<dyn RandomSource>,
+    ) -> Self {
+        Self {
+            platform,
+            quirks,
+            faults,
+            random,
+            waiting_for_vblank: false,
+            key_wait: None,
+            exited: false,
+            fault: None,
+            tracer: None,
+        }
+    }
+
+    // true once the interpreter has been exited with 00fd
+    pub fn has_exited(&self) -> bool {
+        self.exited
+    }
+
+    // true once a sprite has been drawn with the display wait quirk, until the next vertical blank
+    pub fn is_waiting_for_vblank(&self) -> bool {
+        self.waiting_for_vblank
+    }
+
+    // idles until the next frame, whatever the quirks
+    pub fn wait_for_vblank(&mut self) {
+        self.waiting_for_vblank = true;
+    }
+
+    // fault that halted the cpu
+    pub fn get_fault(&self) -> Option<CpuFault> {
+        self.fault
+    }
+
+    // true while fx0a waits for a key to be pressed and released, nothing runs meanwhile
+    pub fn is_waiting_for_key(&self) -> bool {
+        self.key_wait.is_some()
+    }
+
+    // starts a frame, once its keys have been latched
+    pub fn vblank(&mut self, mmu: &Mmu, registers: &mut Registers) {
+        self.waiting_for_vblank = false;
+
+        match self.key_wait {
+            // the first key pressed since the wait began is the one waited for, keys held before are ignored
+            Some(KeyWait { x, key: None }) => {
+                if let Some(key) = (0..16).find(|key| mmu.is_key_newly_down(*key)) {
+                    self.key_wait = Some(KeyWait {
+                        x,
+                        key: Some(key as u8),
+                    });
+                }
+            }
+            Some(KeyWait { x, key: Some(key) }) if !mmu.is_key_down(key as usize) => {
+                registers.set_vx(x as usize, key);
+                self.key_wait = None;
+            }
+            _ => {}
+        }
+    }
+
+    pub fn set_random_source(&mut self, random: Box<dyn RandomSource>) {
+        self.random = random;
+    }
+
+    pub fn set_tracer(&mut self, tracer: Option<Tracer>) {
+        self.tracer = tracer;
+    }
+
+    pub fn tracer_mut(&mut self) -> Option<&mut Tracer> {
+        self.tracer.as_mut()
+    }
+
+    pub fn take_tracer(&mut self) -> Option<Tracer> {
+        self.tracer.take()
+    }
+
+    // key wait: a flag, then the register and the key pressed (0xff for none) when waiting
+    pub fn write_state(&self, writer: &mut StateWriter) {
+        writer.write_bool(self.waiting_for_vblank);
+        writer.write_bool(self.exited);
+
+        writer.write_bool(self.key_wait.is_some());
+        if let Some(KeyWait { x, key }) = self.key_wait {
+            writer.write_u8(x);
+            writer.write_u8(key.unwrap_or(0xFF));
+        }
+
+        // a kind, the faulting address, then what the kind needs
+        match self.fault {
+            None => writer.write_u8(0),
+            Some(CpuFault::StackOverflow { address }) => {
+                writer.write_u8(1);
+                writer.write_u16(address);
+            }
+            Some(CpuFault::StackUnderflow { address }) => {
+                writer.write_u8(2);
+                writer.write_u16(address);
+            }
+            Some(CpuFault::InvalidOpcode { address, op_code }) => {
+                writer.write_u8(3);
+                writer.write_u16(address);
+                writer.write_u16(op_code);
+            }
+            Some(CpuFault::MemoryOutOfRange { address, target }) => {
+                writer.write_u8(4);
+                writer.write_u16(address);
+                writer.write_u32(target as u32);
+            }
+        }
+
+        writer.write_u64(self.random.get_state());
+    }
+
+    // quirks are part of the configuration, not of the state, and neither is the random source, only its state
+    pub fn read_state(reader: &mut StateReader) -> Result<CpuState, StateError> {
+        let waiting_for_vblank = reader.read_bool()?;
+        let exited = reader.read_bool()?;
+
+        let key_wait = match reader.read_bool()? {
+            true => {
+                let x = reader.read_u8()?;
+                let key = reader.read_u8()?;
+
+                if x > 0x0F || (key > 0x0F && key != 0xFF) {
+                    return Err(StateError::Corrupted);
+                }
+
+                Some(KeyWait {
+                    x,
+                    key: (key != 0xFF).then_some(key),
+                })
+            }
+            false => None,
+        };
+
+        let fault = match reader.read_u8()? {
+            0 => None,
+            1 => Some(CpuFault::StackOverflow {
+                address: reader.read_u16()?,
+            }),
+            2 => Some(CpuFault::StackUnderflow {
+                address: reader.read_u16()?,
+            }),
+            3 => Some(CpuFault::InvalidOpcode {
+                address: reader.read_u16()?,
+                op_code: reader.read_u16()?,
+            }),
+            4 => Some(CpuFault::MemoryOutOfRange {
+                address: reader.read_u16()?,
+                target: reader.read_u32()? as usize,
+            }),
+            _ => return Err(StateError::Corrupted),
+        };
+
+        Ok(CpuState {
+            waiting_for_vblank,
+            key_wait,
+            exited,
+            fault,
+            random: reader.read_u64()?,
+        })
+    }
+
+    pub fn restore_state(&mut self, state: CpuState) {
+        self.waiting_for_vblank = state.waiting_for_vblank;
+        self.key_wait = state.key_wait;
+        self.exited = state.exited;
+        self.fault = state.fault;
+        self.random.set_state(state.random);
+    }
+
+    // runs the instruction at PC, the fault is returned when its policy halts the cpu
+    pub fn tick(&mut self, mmu: &mut Mmu, registers: &mut Registers) -> Result<(), CpuFault> {
+        if self.exited || self.fault.is_some() {
+            return Ok(());
+        }
+
         let pc = registers.get_pc();
         let op_code = mmu.get_short(pc as usize);
+        let instruction = decode_for(self.platform, op_code);
 
-        // increment pc after reading the opcode
-        registers.set_pc(pc + 2);
-
-        // get the correct instruction to execute
-        let instruction = match op_code & 0xF000 {
-            0x0000 => match op_code & 0x00FF {
-                // CLS
-                0xE0 => instruction_00e0,
-                // RET
-                0xEE => instruction_00ee,
-                // no match
-                _ => instruction_invalid,
-            },
-            // JP addr
-            0x1000 => instruction_1nnn,
-            // CALL addr
-            0x2000 => instruction_2nnn,
-            // SE Vx, byte
-            0x3000 => instruction_3nnn,
-            // SNE Vx, byte
-            0x4000 => instruction_4nnn,
-            // SE Vx, Vy
-            0x5000 => instruction_5xy0,
-            // LD Vx, byte
-            0x6000 => instruction_6xnn,
-            // ADD Vx, byte
-            0x7000 => instruction_7xnn,
-            // Logical and arithmetic instructions
-            0x8000 => match op_code & 0x000F {
-                // LD Vx, Vy
-                0x0 => instruction_8xy0,
-                // OR Vx, Vy
-                0x1 => instruction_8xy1,
-                // AND Vx, Vy
-                0x2 => instruction_8xy2,
-                // XOR Vx, Vy
-                0x3 => instruction_8xy3,
-                // ADD Vx, Vy
-                0x4 => instruction_8xy4,
-                // SUB Vx, Vy
-                0x5 => instruction_8xy5,
-                // SHR Vx, {, Vy}
-                0x6 => instruction_8xy6,
-                // SUBN Vx, Vy
-                0x7 => instruction_8xy7,
-                // SHL Vx {, Vy}
-                0xE => instruction_8xye,
-                // no match
-                _ => instruction_invalid,
-            },
-            // SNE Vx, Vy
-            0x9000 => instruction_9xy0,
-            // LD I, addr
-            0xA000 => instruction_annn,
-            // JP V0, addr
-            0xB000 => instruction_bnnn,
-            // RND Vx, byte
-            0xC000 => instruction_cxnn,
-            // DRW Vx, Vy, nibble
-            0xD000 => instruction_dxyn,
-            0xE000 => match op_code & 0x00FF {
-                // SKP Vx
-                0x9E => instruction_ex9e,
-                // SKNP Vx
-                0xA1 => instruction_exa1,
-                // no match
-                _ => instruction_invalid,
-            },
-            0xF000 => match op_code & 0x00FF {
-                // LD Vx, DT
-                0x07 => instruction_fx07,
-                // LD Vx, K -> Get key (blocking)
-                0x0A => instruction_fx0a,
-                // LD DT, Vx
-                0x15 => instruction_fx15,
-                // LD ST, Vx
-                0x18 => instruction_fx18,
-                // ADD I, Vx
-                0x1E => instruction_fx1e,
-                // LD F, Vx
-                0x29 => instruction_fx29,
-                // LD B, Vx
-                0x33 => instruction_fx33,
-                // LD [I], Vx
-                0x55 => instruction_fx55,
-                // LD Vx, [I]
-                0x65 => instruction_fx65,
-                // no match
-                _ => instruction_invalid,
-            },
-            _ => instruction_invalid,
+        if let Some(tracer) = self.tracer.as_mut() {
+            tracer.before_instruction(registers, mmu);
+        }
+
+        let fault = match instruction {
+            Ok(instruction) => find_fault(&instruction, pc, mmu, registers),
+            Err(_) => Some(CpuFault::InvalidOpcode {
+                address: pc,
+                op_code,
+            }),
         };
 
-        // excute instruction
-        instruction(mmu, registers, op_code);
+        let mut run = true;
+        let mut logged = None;
+
+        if let Some(fault) = fault {
+            match self.faults.policy(&fault) {
+                FaultPolicy::Halt => {
+                    self.fault = Some(fault);
+
+                    // the instructions leading to it are the interesting ones
+                    if let Some(tracer) = self.tracer.as_mut() {
+                        tracer.after_instruction(
+                            pc,
+                            op_code,
+                            instruction.as_ref().ok(),
+                            registers,
+                            mmu,
+                        );
+                        tracer.dump(&fault.to_string());
+                    }
+
+                    return Err(fault);
+                }
+                FaultPolicy::Ignore => run = false,
+                // reported, then skipped like an ignored fault
+                FaultPolicy::Log => {
+                    eprintln!("{}", fault);
+                    run = false;
+                    logged = Some(fault);
+                }
+                FaultPolicy::Wrap => match fault {
+                    CpuFault::StackOverflow { .. } => mmu.discard_oldest_return_address(),
+                    CpuFault::StackUnderflow { .. } => mmu.wrap_stack_pointer(),
+                    CpuFault::InvalidOpcode { .. } => run = false,
+                    // addresses are masked by the mmu
+                    CpuFault::MemoryOutOfRange { .. } => {}
+                },
+            }
+        }
+
+        // increment pc after reading the opcode
+        registers.set_pc(pc.wrapping_add(2));
+
+        match instruction {
+            Ok(instruction) if run => self.execute(instruction, mmu, registers),
+            _ => {}
+        }
+
+        if let Some(tracer) = self.tracer.as_mut() {
+            tracer.after_instruction(pc, op_code, instruction.as_ref().ok(), registers, mmu);
+
+            if let Some(fault) = logged {
+                tracer.dump(&fault.to_string());
+            }
+        }
+
+        Ok(())
+    }
+
+    pub fn execute(&mut self, instruction: Instruction, mmu: &mut Mmu, registers: &mut Registers) {
+        let quirks = &self.quirks;
+
+        match instruction {
+            Instruction::Scd { n } => instruction_00cn(mmu, registers, n),
+            Instruction::Scu { n } => instruction_00dn(mmu, registers, n),
+            Instruction::Cls => instruction_00e0(mmu, registers),
+            Instruction::Ret => instruction_00ee(mmu, registers),
+            Instruction::Scr => instruction_00fb(mmu, registers),
+            Instruction::Scl => instruction_00fc(mmu, registers),
+            Instruction::Exit => self.exited = true,
+            Instruction::Low => instruction_00fe(mmu, registers),
+            Instruction::High => instruction_00ff(mmu, registers),
+            Instruction::Jp { addr } => instruction_1nnn(mmu, registers, addr),
+            Instruction::Call { addr } => instruction_2nnn(mmu, registers, addr),
+            Instruction::SeByte { x, byte } => instruction_3nnn(mmu, registers, x as usize, byte),
+            Instruction::SneByte { x, byte } => instruction_4nnn(mmu, registers, x as usize, byte),
+            Instruction::SeReg { x, y } => instruction_5xy0(mmu, registers, x as usize, y as usize),
+            Instruction::SaveRange { x, y } => {
+                instruction_5xy2(mmu, registers, x as usize, y as usize)
+            }
+            Instruction::LoadRange { x, y } => {
+                instruction_5xy3(mmu, registers, x as usize, y as usize)
+            }
+            Instruction::LdByte { x, byte } => instruction_6xnn(mmu, registers, x as usize, byte),
+            Instruction::AddByte { x, byte } => instruction_7xnn(mmu, registers, x as usize, byte),
+            Instruction::LdReg { x, y } => instruction_8xy0(mmu, registers, x as usize, y as usize),
+            Instruction::Or { x, y } => {
+                instruction_8xy1(mmu, registers, quirks, x as usize, y as usize)
+            }
+            Instruction::And { x, y } => {
+                instruction_8xy2(mmu, registers, quirks, x as usize, y as usize)
+            }
+            Instruction::Xor { x, y } => {
+                instruction_8xy3(mmu, registers, quirks, x as usize, y as usize)
+            }
+            Instruction::AddReg { x, y } => {
+                instruction_8xy4(mmu, registers, x as usize, y as usize)
+            }
+            Instruction::Sub { x, y } => instruction_8xy5(mmu, registers, x as usize, y as usize),
+            Instruction::Shr { x, y } => {
+                instruction_8xy6(mmu, registers, quirks, x as usize, y as usize)
+            }
+            Instruction::Subn { x, y } => instruction_8xy7(mmu, registers, x as usize, y as usize),
+            Instruction::Shl { x, y } => {
+                instruction_8xye(mmu, registers, quirks, x as usize, y as usize)
+            }
+            Instruction::SneReg { x, y } => {
+                instruction_9xy0(mmu, registers, x as usize, y as usize)
+            }
+            Instruction::LdI { addr } => instruction_annn(mmu, registers, addr),
+            Instruction::JpV0 { addr } => instruction_bnnn(mmu, registers, quirks, addr),
+            Instruction::Rnd { x, byte } => {
+                instruction_cxnn(mmu, registers, self.random.as_mut(), x as usize, byte)
+            }
+            Instruction::Drw { x, y, n } => {
+                instruction_dxyn(mmu, registers, quirks, x as usize, y as usize, n);
+
+                if quirks.display_wait {
+                    self.waiting_for_vblank = true;
+                }
+            }
+            Instruction::Skp { x } => instruction_ex9e(mmu, registers, x as usize),
+            Instruction::Sknp { x } => instruction_exa1(mmu, registers, x as usize),
+            Instruction::LdLongI => instruction_f000(mmu, registers),
+            Instruction::Plane { n } => instruction_fn01(mmu, registers, n),
+            Instruction::Audio => instruction_f002(mmu, registers),
+            Instruction::LdVxDt { x } => instruction_fx07(mmu, registers, x as usize),
+            // the key is stored once released, in a later frame
+            Instruction::LdVxK { x } => self.key_wait = Some(KeyWait { x, key: None }),
+            Instruction::LdDtVx { x } => instruction_fx15(mmu, registers, x as usize),
+            Instruction::LdStVx { x } => instruction_fx18(mmu, registers, x as usize),
+            Instruction::AddI { x } => instruction_fx1e(mmu, registers, x as usize),
+            Instruction::LdF { x } => instruction_fx29(mmu, registers, x as usize),
+            Instruction::LdHf { x } => instruction_fx30(mmu, registers, x as usize),
+            Instruction::LdB { x } => instruction_fx33(mmu, registers, x as usize),
+            Instruction::Pitch { x } => instruction_fx3a(mmu, registers, x as usize),
+            Instruction::LdIVx { x } => instruction_fx55(mmu, registers, quirks, x as usize),
+            Instruction::LdVxI { x } => instruction_fx65(mmu, registers, quirks, x as usize),
+            Instruction::LdRVx { x } => instruction_fx75(mmu, registers, x as usize),
+            Instruction::LdVxR { x } => instruction_fx85(mmu, registers, x as usize),
+        }
+    }
+}
+
+// XO-CHIP "LD I, long addr" is 4 bytes long, and has to be skipped entirely
+fn skip_next_instruction(mmu: &Mmu, registers: &mut Registers) {
+    let pc = registers.get_pc();
+
+    if mmu.get_short(pc as usize) == 0xF000 {
+        registers.set_pc(pc.wrapping_add(4));
+    } else {
+        registers.set_pc(pc.wrapping_add(2));
     }
 }
 
-fn instruction_invalid(_mmu: &mut Mmu, registers: &mut Registers, op_code: u16) {
-    println!(
-        "invalid opcode: {:#04x} at {:#04x}",
-        op_code,
-        registers.get_pc() - 2
-    );
+// fault the instruction at pc would cause, stack faults being checked before memory ones
+fn find_fault(
+    instruction: &Instruction,
+    pc: u16,
+    mmu: &Mmu,
+    registers: &Registers,
+) -> Option<CpuFault> {
+    match instruction {
+        Instruction::Call { .. } if mmu.is_stack_full() => {
+            return Some(CpuFault::StackOverflow { address: pc })
+        }
+        Instruction::Ret if mmu.get_stack_pointer() == 0 => {
+            return Some(CpuFault::StackUnderflow { address: pc })
+        }
+        _ => {}
+    }
+
+    let i = registers.get_i() as usize;
+
+    // end of the memory the instruction reads or writes, f000 taking its address after the opcode
+    let end = match *instruction {
+        Instruction::LdLongI => pc as usize + 4,
+        Instruction::Drw { n, .. } => {
+            let bytes = match n {
+                0 => 32,
+                n => n as usize,
+            };
+
+            i + bytes * mmu.get_selected_planes().count_ones() as usize
+        }
+        Instruction::SaveRange { x, y } | Instruction::LoadRange { x, y } => {
+            i + x.abs_diff(y) as usize + 1
+        }
+        Instruction::Audio => i + 16,
+        Instruction::LdB { .. } => i + 3,
+        Instruction::LdIVx { x } | Instruction::LdVxI { x } => i + x as usize + 1,
+        _ => pc as usize + 2,
+    }
+    .max(pc as usize + 2);
+
+    let memory_size = mmu.get_address_mask() + 1;
+
+    (end > memory_size).then_some(CpuFault::MemoryOutOfRange {
+        address: pc,
+        target: end - 1,
+    })
 }
 
-fn instruction_00e0(mmu: &mut Mmu, _registers: &mut Registers, _op_code: u16) {
-    mmu.set_screen(vec![0; 32 * 64]);
+fn instruction_00cn(mmu: &mut Mmu, _registers: &mut Registers, n: u8) {
+    mmu.scroll(0, n as isize);
 }
 
-fn instruction_00ee(mmu: &mut Mmu, registers: &mut Registers, _op_code: u16) {
-    registers.set_pc(mmu.pop_stack() & 0x0FFF);
+fn instruction_00dn(mmu: &mut Mmu, _registers: &mut Registers, n: u8) {
+    mmu.scroll(0, -(n as isize));
 }
 
-fn instruction_1nnn(_mmu: &mut Mmu, registers: &mut Registers, op_code: u16) {
-    registers.set_pc(op_code & 0x0FFF);
+fn instruction_00e0(mmu: &mut Mmu, _registers: &mut Registers) {
+    mmu.clear_screen();
 }
 
-fn instruction_2nnn(mmu: &mut Mmu, registers: &mut Registers, op_code: u16) {
-    mmu.push_on_stack(registers.get_pc());
-    registers.set_pc(op_code & 0x0FFF);
+fn instruction_00ee(mmu: &mut Mmu, registers: &mut Registers) {
+    let address = mmu.pop_stack() as usize & mmu.get_address_mask();
+
+    registers.set_pc(address as u16);
 }
 
-fn instruction_3nnn(_mmu: &mut Mmu, registers: &mut Registers, op_code: u16) {
-    let index = ((op_code & 0x0F00) >> 8) as usize;
-    let value = (op_code & 0x00FF) as u8;
+fn instruction_00fb(mmu: &mut Mmu, _registers: &mut Registers) {
+    mmu.scroll(4, 0);
+}
 
-    if registers.get_vx(index) == value {
-        let pc = registers.get_pc();
-        registers.set_pc(pc + 2);
-    }
+fn instruction_00fc(mmu: &mut Mmu, _registers: &mut Registers) {
+    mmu.scroll(-4, 0);
 }
 
-fn instruction_4nnn(_mmu: &mut Mmu, registers: &mut Registers, op_code: u16) {
-    let index = ((op_code & 0x0F00) >> 8) as usize;
-    let value = (op_code & 0x00FF) as u8;
+fn instruction_00fe(mmu: &mut Mmu, _registers: &mut Registers) {
+    mmu.set_high_resolution(false);
+}
 
-    if registers.get_vx(index) != value {
-        let pc = registers.get_pc();
-        registers.set_pc(pc + 2);
+fn instruction_00ff(mmu: &mut Mmu, _registers: &mut Registers) {
+    mmu.set_high_resolution(true);
+}
+
+fn instruction_1nnn(_mmu: &mut Mmu, registers: &mut Registers, address: u16) {
+    registers.set_pc(address);
+}
+
+fn instruction_2nnn(mmu: &mut Mmu, registers: &mut Registers, address: u16) {
+    mmu.push_on_stack(registers.get_pc());
+    registers.set_pc(address);
+}
+
+fn instruction_3nnn(mmu: &mut Mmu, registers: &mut Registers, x_index: usize, value: u8) {
+    if registers.get_vx(x_index) == value {
+        skip_next_instruction(mmu, registers);
     }
 }
 
-fn instruction_5xy0(_mmu: &mut Mmu, registers: &mut Registers, op_code: u16) {
-    let x_index = ((op_code & 0x0F00) >> 8) as usize;
-    let y_index = ((op_code & 0x00F0) >> 4) as usize;
+fn instruction_4nnn(mmu: &mut Mmu, registers: &mut Registers, x_index: usize, value: u8) {
+    if registers.get_vx(x_index) != value {
+        skip_next_instruction(mmu, registers);
+    }
+}
 
+fn instruction_5xy0(mmu: &mut Mmu, registers: &mut Registers, x_index: usize, y_index: usize) {
     if registers.get_vx(x_index) == registers.get_vx(y_index) {
-        let pc = registers.get_pc();
-        registers.set_pc(pc + 2);
+        skip_next_instruction(mmu, registers);
     }
 }
 
-fn instruction_6xnn(_mmu: &mut Mmu, registers: &mut Registers, op_code: u16) {
-    let index = ((op_code & 0x0F00) >> 8) as usize;
-    let value = (op_code & 0x00FF) as u8;
+fn instruction_5xy2(mmu: &mut Mmu, registers: &mut Registers, x_index: usize, y_index: usize) {
+    let i = registers.get_i() as usize;
+
+    // registers are saved in reverse order if x > y
+    for offset in 0..=x_index.abs_diff(y_index) {
+        let index = if x_index <= y_index {
+            x_index + offset
+        } else {
+            x_index - offset
+        };
 
-    registers.set_vx(index, value);
+        mmu.set_byte(i + offset, registers.get_vx(index));
+    }
 }
 
-fn instruction_7xnn(_mmu: &mut Mmu, registers: &mut Registers, op_code: u16) {
-    let index = ((op_code & 0x0F00) >> 8) as usize;
-    let operand = (op_code & 0x00FF) as u8;
+fn instruction_5xy3(mmu: &mut Mmu, registers: &mut Registers, x_index: usize, y_index: usize) {
+    let i = registers.get_i() as usize;
+
+    // registers are loaded in reverse order if x > y
+    for offset in 0..=x_index.abs_diff(y_index) {
+        let index = if x_index <= y_index {
+            x_index + offset
+        } else {
+            x_index - offset
+        };
 
-    let original_value = registers.get_vx(index);
+        registers.set_vx(index, mmu.get_byte(i + offset));
+    }
+}
 
-    registers.set_vx(index, original_value.overflowing_add(operand).0);
+fn instruction_6xnn(_mmu: &mut Mmu, registers: &mut Registers, x_index: usize, value: u8) {
+    registers.set_vx(x_index, value);
 }
 
-fn instruction_8xy0(_mmu: &mut Mmu, registers: &mut Registers, op_code: u16) {
-    let x_index = ((op_code & 0x0F00) >> 8) as usize;
-    let y_index = ((op_code & 0x00F0) >> 4) as usize;
+fn instruction_7xnn(_mmu: &mut Mmu, registers: &mut Registers, x_index: usize, operand: u8) {
+    let original_value = registers.get_vx(x_index);
 
+    registers.set_vx(x_index, original_value.overflowing_add(operand).0);
+}
+
+fn instruction_8xy0(_mmu: &mut Mmu, registers: &mut Registers, x_index: usize, y_index: usize) {
     registers.set_vx(x_index, registers.get_vx(y_index));
 }
 
-fn instruction_8xy1(_mmu: &mut Mmu, registers: &mut Registers, op_code: u16) {
-    let x_index = ((op_code & 0x0F00) >> 8) as usize;
+fn instruction_8xy1(
+    _mmu: &mut Mmu,
+    registers: &mut Registers,
+    quirks: &Quirks,
+    x_index: usize,
+    y_index: usize,
+) {
     let x_value = registers.get_vx(x_index);
 
-    let y_index = ((op_code & 0x00F0) >> 4) as usize;
     let y_value = registers.get_vx(y_index);
 
-    registers.set_vx(x_index, (x_value | y_value) as u8);
+    registers.set_vx(x_index, x_value | y_value);
+
+    if quirks.vf_reset {
+        registers.set_vx(0xF, 0);
+    }
 }
 
-fn instruction_8xy2(_mmu: &mut Mmu, registers: &mut Registers, op_code: u16) {
-    let x_index = ((op_code & 0x0F00) >> 8) as usize;
+fn instruction_8xy2(
+    _mmu: &mut Mmu,
+    registers: &mut Registers,
+    quirks: &Quirks,
+    x_index: usize,
+    y_index: usize,
+) {
     let x_value = registers.get_vx(x_index);
 
-    let y_index = ((op_code & 0x00F0) >> 4) as usize;
     let y_value = registers.get_vx(y_index);
 
-    registers.set_vx(x_index, (x_value & y_value) as u8);
+    registers.set_vx(x_index, x_value & y_value);
+
+    if quirks.vf_reset {
+        registers.set_vx(0xF, 0);
+    }
 }
 
-fn instruction_8xy3(_mmu: &mut Mmu, registers: &mut Registers, op_code: u16) {
-    let x_index = ((op_code & 0x0F00) >> 8) as usize;
+fn instruction_8xy3(
+    _mmu: &mut Mmu,
+    registers: &mut Registers,
+    quirks: &Quirks,
+    x_index: usize,
+    y_index: usize,
+) {
     let x_value = registers.get_vx(x_index);
 
-    let y_index = ((op_code & 0x00F0) >> 4) as usize;
     let y_value = registers.get_vx(y_index);
 
-    registers.set_vx(x_index, (x_value ^ y_value) as u8);
+    registers.set_vx(x_index, x_value ^ y_value);
+
+    if quirks.vf_reset {
+        registers.set_vx(0xF, 0);
+    }
 }
 
-fn instruction_8xy4(_mmu: &mut Mmu, registers: &mut Registers, op_code: u16) {
-    let x_index = ((op_code & 0x0F00) >> 8) as usize;
+fn instruction_8xy4(_mmu: &mut Mmu, registers: &mut Registers, x_index: usize, y_index: usize) {
     let x_value = registers.get_vx(x_index);
 
-    let y_index = ((op_code & 0x00F0) >> 4) as usize;
     let y_value = registers.get_vx(y_index);
 
     let result = x_value.overflowing_add(y_value);
@@ -229,11 +616,9 @@ fn instruction_8xy4(_mmu: &mut Mmu, registers: &mut Registers, op_code: u16) {
     registers.set_vx(0xF, result.1 as u8);
 }
 
-fn instruction_8xy5(_mmu: &mut Mmu, registers: &mut Registers, op_code: u16) {
-    let x_index = ((op_code & 0x0F00) >> 8) as usize;
+fn instruction_8xy5(_mmu: &mut Mmu, registers: &mut Registers, x_index: usize, y_index: usize) {
     let x_value = registers.get_vx(x_index);
 
-    let y_index = ((op_code & 0x00F0) >> 4) as usize;
     let y_value = registers.get_vx(y_index);
 
     let result = x_value.overflowing_sub(y_value);
@@ -243,19 +628,22 @@ fn instruction_8xy5(_mmu: &mut Mmu, registers: &mut Registers, op_code: u16) {
     registers.set_vx(0xF, !result.1 as u8);
 }
 
-fn instruction_8xy6(_mmu: &mut Mmu, registers: &mut Registers, op_code: u16) {
-    let index = ((op_code & 0x0F00) >> 8) as usize;
-    let value = registers.get_vx(index);
+fn instruction_8xy6(
+    _mmu: &mut Mmu,
+    registers: &mut Registers,
+    quirks: &Quirks,
+    x_index: usize,
+    y_index: usize,
+) {
+    let value = registers.get_vx(if quirks.shifting { x_index } else { y_index });
 
-    registers.set_vx(index, (value >> 1) as u8);
-    registers.set_vx(0xF, (value & 0b00000001) as u8);
+    registers.set_vx(x_index, value >> 1);
+    registers.set_vx(0xF, value & 0b00000001);
 }
 
-fn instruction_8xy7(_mmu: &mut Mmu, registers: &mut Registers, op_code: u16) {
-    let x_index = ((op_code & 0x0F00) >> 8) as usize;
+fn instruction_8xy7(_mmu: &mut Mmu, registers: &mut Registers, x_index: usize, y_index: usize) {
     let x_value = registers.get_vx(x_index);
 
-    let y_index = ((op_code & 0x00F0) >> 4) as usize;
     let y_value = registers.get_vx(y_index);
 
     let result = y_value.overflowing_sub(x_value);
@@ -265,141 +653,189 @@ fn instruction_8xy7(_mmu: &mut Mmu, registers: &mut Registers, op_code: u16) {
     registers.set_vx(0xF, !result.1 as u8);
 }
 
-fn instruction_8xye(_mmu: &mut Mmu, registers: &mut Registers, op_code: u16) {
-    let index = ((op_code & 0x0F00) >> 8) as usize;
-    let value = registers.get_vx(index);
+fn instruction_8xye(
+    _mmu: &mut Mmu,
+    registers: &mut Registers,
+    quirks: &Quirks,
+    x_index: usize,
+    y_index: usize,
+) {
+    let value = registers.get_vx(if quirks.shifting { x_index } else { y_index });
 
-    registers.set_vx(index, (value << 1) as u8);
-    registers.set_vx(0xF, ((value & 0b10000000) >> 7) as u8);
+    registers.set_vx(x_index, value << 1);
+    registers.set_vx(0xF, (value & 0b10000000) >> 7);
 }
 
-fn instruction_9xy0(_mmu: &mut Mmu, registers: &mut Registers, op_code: u16) {
-    let x_index = ((op_code & 0x0F00) >> 8) as usize;
-    let y_index = ((op_code & 0x00F0) >> 4) as usize;
-
+fn instruction_9xy0(mmu: &mut Mmu, registers: &mut Registers, x_index: usize, y_index: usize) {
     if registers.get_vx(x_index) != registers.get_vx(y_index) {
-        let pc = registers.get_pc();
-        registers.set_pc(pc + 2);
+        skip_next_instruction(mmu, registers);
     }
 }
 
-fn instruction_annn(_mmu: &mut Mmu, registers: &mut Registers, op_code: u16) {
-    let value = (op_code & 0x0FFF) as u16;
-
-    registers.set_i(value);
+fn instruction_annn(_mmu: &mut Mmu, registers: &mut Registers, address: u16) {
+    registers.set_i(address);
 }
 
-fn instruction_bnnn(_mmu: &mut Mmu, registers: &mut Registers, op_code: u16) {
-    let value = (op_code & 0x0FFF) as u16;
+fn instruction_bnnn(mmu: &mut Mmu, registers: &mut Registers, quirks: &Quirks, address: u16) {
+    let index = if quirks.jumping {
+        ((address & 0x0F00) >> 8) as usize
+    } else {
+        0
+    };
 
-    registers.set_pc(value + registers.get_vx(0) as u16);
-}
+    let target = (address as usize + registers.get_vx(index) as usize) & mmu.get_address_mask();
 
-fn instruction_cxnn(_mmu: &mut Mmu, registers: &mut Registers, op_code: u16) {
-    let index = ((op_code & 0x0F00) >> 8) as usize;
-    let modulo = (op_code & 0x00FF) as u8;
+    registers.set_pc(target as u16);
+}
 
-    let rng_value = rand::thread_rng().gen_range(1..=0xFF);
-    registers.set_vx(index, (rng_value % modulo) as u8)
+fn instruction_cxnn(
+    mmu: &mut Mmu,
+    registers: &mut Registers,
+    random: &mut dyn RandomSource,
+    x_index: usize,
+    mask: u8,
+) {
+    let rng_value = random.next_byte(mmu.get_memory());
+    registers.set_vx(x_index, rng_value & mask)
 }
 
-fn instruction_dxyn(mmu: &mut Mmu, registers: &mut Registers, op_code: u16) {
-    let x_index = ((op_code & 0x0F00) >> 8) as usize;
-    let x_value = (registers.get_vx(x_index) % 64) as u16;
+fn instruction_dxyn(
+    mmu: &mut Mmu,
+    registers: &mut Registers,
+    quirks: &Quirks,
+    x_index: usize,
+    y_index: usize,
+    n: u8,
+) {
+    let width = mmu.get_screen_width() as u16;
+    let height = mmu.get_screen_height() as u16;
 
-    let y_index = ((op_code & 0x00F0) >> 4) as usize;
-    let y_value = (registers.get_vx(y_index) % 32) as u16;
+    let x_value = registers.get_vx(x_index) as u16 % width;
+    let y_value = registers.get_vx(y_index) as u16 % height;
 
-    let n = op_code & 0x000F;
+    // dxy0 draws a 16x16 sprite, stored as 2 bytes per row
+    let (rows, columns) = match n {
+        0 => (16, 16),
+        n => (n as u16, 8),
+    };
+    let bytes_per_row = columns / 8;
 
     // reset carry flag
     registers.set_vx(0xF, 0);
 
-    for j in 0..n {
-        let pixel = mmu.get_byte((registers.get_i() + j) as usize);
+    // with both XO-CHIP planes selected, the sprite for plane 2 follows the one for plane 1
+    let mut address = registers.get_i() as usize;
 
-        for i in 0..8 {
-            if (pixel & (0x80 >> i)) != 0 {
-                let index = (x_value + i + ((y_value + j) * 64)) as usize;
-                let value = mmu.get_screen_at(index);
+    for plane in [1, 2] {
+        if mmu.get_selected_planes() & plane == 0 {
+            continue;
+        }
+
+        for j in 0..rows {
+            if quirks.clipping && y_value + j >= height {
+                break;
+            }
 
-                if value != 0 {
-                    registers.set_vx(0xF, 1);
+            for i in 0..columns {
+                if quirks.clipping && x_value + i >= width {
+                    break;
                 }
 
-                mmu.set_screen_at(index, value ^ 1);
+                let pixel = mmu.get_byte(address + (j * bytes_per_row + i / 8) as usize);
+
+                if (pixel & (0x80 >> (i % 8))) != 0 {
+                    let index =
+                        (((x_value + i) % width) + ((y_value + j) % height) * width) as usize;
+                    let value = mmu.get_screen_at(index);
+
+                    if value & plane != 0 {
+                        registers.set_vx(0xF, 1);
+                    }
+
+                    mmu.set_screen_at(index, value ^ plane);
+                }
             }
         }
+
+        address += (rows * bytes_per_row) as usize;
     }
 }
 
-fn instruction_ex9e(mmu: &mut Mmu, registers: &mut Registers, op_code: u16) {
-    let index = ((op_code & 0x0F00) >> 8) as usize;
-    let key = registers.get_vx(index) as usize;
+fn instruction_ex9e(mmu: &mut Mmu, registers: &mut Registers, x_index: usize) {
+    let key = registers.get_vx(x_index) as usize;
 
     if mmu.is_key_down(key) {
-        let pc = registers.get_pc();
-        registers.set_pc(pc + 2);
+        skip_next_instruction(mmu, registers);
     }
 }
 
-fn instruction_exa1(mmu: &mut Mmu, registers: &mut Registers, op_code: u16) {
-    let index = ((op_code & 0x0F00) >> 8) as usize;
-    let key = registers.get_vx(index) as usize;
+fn instruction_exa1(mmu: &mut Mmu, registers: &mut Registers, x_index: usize) {
+    let key = registers.get_vx(x_index) as usize;
 
     if !mmu.is_key_down(key) {
-        let pc = registers.get_pc();
-        registers.set_pc(pc + 2);
+        skip_next_instruction(mmu, registers);
     }
 }
 
-fn instruction_fx07(mmu: &mut Mmu, registers: &mut Registers, op_code: u16) {
-    let index = ((op_code & 0x0F00) >> 8) as usize;
+fn instruction_f000(mmu: &mut Mmu, registers: &mut Registers) {
+    let pc = registers.get_pc();
 
-    registers.set_vx(index, mmu.get_delay_timer());
+    // the address is stored in the 2 bytes following the opcode
+    registers.set_i(mmu.get_short(pc as usize));
+    registers.set_pc(pc.wrapping_add(2));
 }
 
-fn instruction_fx0a(mmu: &mut Mmu, registers: &mut Registers, op_code: u16) {
-    let index = ((op_code & 0x0F00) >> 8) as usize;
-    let key = registers.get_vx(index) as usize;
+fn instruction_fn01(mmu: &mut Mmu, _registers: &mut Registers, planes: u8) {
+    mmu.set_selected_planes(planes);
+}
 
-    if !mmu.is_key_down(key) {
-        let pc = registers.get_pc();
-        registers.set_pc(pc - 2);
+fn instruction_f002(mmu: &mut Mmu, registers: &mut Registers) {
+    let i = registers.get_i() as usize;
+    let mut pattern = [0; 16];
+
+    for (index, byte) in pattern.iter_mut().enumerate() {
+        *byte = mmu.get_byte(i + index);
     }
-}
 
-fn instruction_fx15(mmu: &mut Mmu, registers: &mut Registers, op_code: u16) {
-    let index = ((op_code & 0x0F00) >> 8) as usize;
+    mmu.set_audio_pattern(pattern);
+}
 
-    mmu.set_delay_timer(registers.get_vx(index));
+fn instruction_fx07(mmu: &mut Mmu, registers: &mut Registers, x_index: usize) {
+    registers.set_vx(x_index, mmu.get_delay_timer());
 }
 
-fn instruction_fx18(mmu: &mut Mmu, registers: &mut Registers, op_code: u16) {
-    let index = ((op_code & 0x0F00) >> 8) as usize;
+fn instruction_fx15(mmu: &mut Mmu, registers: &mut Registers, x_index: usize) {
+    mmu.set_delay_timer(registers.get_vx(x_index));
+}
 
-    mmu.set_sound_timer(registers.get_vx(index));
+fn instruction_fx18(mmu: &mut Mmu, registers: &mut Registers, x_index: usize) {
+    mmu.set_sound_timer(registers.get_vx(x_index));
 }
 
-fn instruction_fx1e(_mmu: &mut Mmu, registers: &mut Registers, op_code: u16) {
-    let index = ((op_code & 0x0F00) >> 8) as usize;
-    let result = registers.get_i() + (registers.get_vx(index) as u16);
-    let overflow = (result > 0x0FFF) as u8;
+fn instruction_fx1e(mmu: &mut Mmu, registers: &mut Registers, x_index: usize) {
+    let result = registers.get_i() as usize + registers.get_vx(x_index) as usize;
+    let overflow = (result > mmu.get_address_mask()) as u8;
 
-    registers.set_i(result & 0x0FFF);
+    registers.set_i((result & mmu.get_address_mask()) as u16);
     registers.set_vx(0xF, overflow);
 }
 
-fn instruction_fx29(_mmu: &mut Mmu, registers: &mut Registers, op_code: u16) {
-    let index = ((op_code & 0x0F00) >> 8) as usize;
-
+fn instruction_fx29(_mmu: &mut Mmu, registers: &mut Registers, x_index: usize) {
     // 0x50 is the memory location we used for the font, 5 is the size of a font in bytes
-    registers.set_i(0x50 + (registers.get_vx(index) as u16));
+    registers.set_i(0x50 + (registers.get_vx(x_index) as u16 & 0x0F) * 5);
 }
 
-fn instruction_fx33(mmu: &mut Mmu, registers: &mut Registers, op_code: u16) {
-    let index = ((op_code & 0x0F00) >> 8) as usize;
-    let value = registers.get_vx(index);
+fn instruction_fx30(_mmu: &mut Mmu, registers: &mut Registers, x_index: usize) {
+    // 0xA0 is the memory location we used for the big font, 10 is the size of a big font in bytes
+    registers.set_i(0xA0 + (registers.get_vx(x_index) as u16 & 0x0F) * 10);
+}
+
+fn instruction_fx3a(mmu: &mut Mmu, registers: &mut Registers, x_index: usize) {
+    mmu.set_pitch(registers.get_vx(x_index));
+}
+
+fn instruction_fx33(mmu: &mut Mmu, registers: &mut Registers, x_index: usize) {
+    let value = registers.get_vx(x_index);
 
     let units: u8 = value % 10;
     let tens: u8 = ((value - units) % 100) / 10;
@@ -412,21 +848,43 @@ fn instruction_fx33(mmu: &mut Mmu, registers: &mut Registers, op_code: u16) {
     mmu.set_byte(i + 2, units);
 }
 
-fn instruction_fx55(mmu: &mut Mmu, registers: &mut Registers, op_code: u16) {
-    let index = ((op_code & 0x0F00) >> 8) as usize;
-
+fn instruction_fx55(mmu: &mut Mmu, registers: &mut Registers, quirks: &Quirks, x_index: usize) {
     let register_i_value = registers.get_i() as usize;
 
-    for i in 0..=index {
+    for i in 0..=x_index {
         mmu.set_byte(register_i_value + i, registers.get_vx(i));
     }
+
+    registers.set_i(memory_increment(quirks, register_i_value, x_index));
+}
+
+fn instruction_fx65(mmu: &mut Mmu, registers: &mut Registers, quirks: &Quirks, x_index: usize) {
+    let i = registers.get_i() as usize;
+
+    for idx in 0..=x_index {
+        registers.set_vx(idx, mmu.get_byte(i + idx));
+    }
+
+    registers.set_i(memory_increment(quirks, i, x_index));
 }
 
-fn instruction_fx65(mmu: &mut Mmu, registers: &mut Registers, op_code: u16) {
-    let index = ((op_code & 0x0F00) >> 8) as u16;
-    let i = registers.get_i();
+// I once fx55 or fx65 went through the registers up to x
+fn memory_increment(quirks: &Quirks, i: usize, x_index: usize) -> u16 {
+    match quirks.memory_increment {
+        MemoryIncrement::None => i as u16,
+        MemoryIncrement::X => (i + x_index) as u16,
+        MemoryIncrement::XPlusOne => (i + x_index + 1) as u16,
+    }
+}
+
+fn instruction_fx75(mmu: &mut Mmu, registers: &mut Registers, x_index: usize) {
+    for i in 0..=x_index {
+        mmu.set_rpl_flag(i, registers.get_vx(i));
+    }
+}
 
-    for idx in 0..=index {
-        registers.set_vx(idx as usize, mmu.get_byte((i + idx) as usize));
+fn instruction_fx85(mmu: &mut Mmu, registers: &mut Registers, x_index: usize) {
+    for i in 0..=x_index {
+        registers.set_vx(i, mmu.get_rpl_flag(i));
     }
 }
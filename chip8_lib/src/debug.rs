@@ -0,0 +1,432 @@
+use crate::error::{ConditionError, CpuFault};
+use crate::mmu::Mmu;
+use crate::registers::Registers;
+use std::fmt;
+use std::ops::RangeInclusive;
+use std::str::FromStr;
+use std::time::Duration;
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Access {
+    Read,
+    Write,
+    // the instruction at the address is about to run
+    Execute,
+}
+
+// Stops execution when an address of the range is accessed
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct Watchpoint {
+    pub range: RangeInclusive<u16>,
+    pub read: bool,
+    pub write: bool,
+    pub execute: bool,
+}
+
+impl Watchpoint {
+    pub fn read(range: RangeInclusive<u16>) -> Self {
+        Self {
+            range,
+            read: true,
+            write: false,
+            execute: false,
+        }
+    }
+
+    pub fn write(range: RangeInclusive<u16>) -> Self {
+        Self {
+            range,
+            read: false,
+            write: true,
+            execute: false,
+        }
+    }
+
+    pub fn read_write(range: RangeInclusive<u16>) -> Self {
+        Self {
+            range,
+            read: true,
+            write: true,
+            execute: false,
+        }
+    }
+
+    pub fn execute(range: RangeInclusive<u16>) -> Self {
+        Self {
+            range,
+            read: false,
+            write: false,
+            execute: true,
+        }
+    }
+
+    pub fn matches(&self, address: u16, access: Access) -> bool {
+        let watched = match access {
+            Access::Read => self.read,
+            Access::Write => self.write,
+            Access::Execute => self.execute,
+        };
+
+        watched && self.range.contains(&address)
+    }
+}
+
+// Why execution stopped before the end of the frame
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum StopReason {
+    // the instruction at the address is about to run
+    Breakpoint {
+        address: u16,
+    },
+    // execute watchpoints stop before the instruction, read and write ones right after it
+    Watchpoint {
+        address: u16,
+        access: Access,
+        value: u8,
+    },
+    // the cpu halted on the faulting instruction
+    Fault(CpuFault),
+}
+
+impl fmt::Display for StopReason {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            StopReason::Breakpoint { address } => write!(f, "breakpoint at {:#05x}", address),
+            StopReason::Fault(fault) => write!(f, "{}", fault),
+            StopReason::Watchpoint {
+                address,
+                access,
+                value,
+            } => {
+                let access = match access {
+                    Access::Read => "read",
+                    Access::Write => "write",
+                    Access::Execute => "execute",
+                };
+
+                write!(
+                    f,
+                    "{} watchpoint at {:#05x} ({:#04x})",
+                    access, address, value
+                )
+            }
+        }
+    }
+}
+
+pub struct FrameReport {
+    // time spent running the frame
+    pub elapsed: Duration,
+    // set when the frame stopped early, the next step resumes it
+    pub stop_reason: Option<StopReason>,
+}
+
+// Boolean expression over the machine state, for conditional breakpoints
+// Operands: V0-VF, I, PC, SP, DT, ST, [address] for a byte of memory, and numbers (0x prefix for hexadecimal)
+// Operators by precedence: ||, &&, == != < <= > >=, ( )
+// e.g. "V3 == 0x10 && I > 0x300"
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct Condition {
+    expression: Expression,
+}
+
+#[derive(Clone, Debug, PartialEq, Eq)]
+enum Expression {
+    Number(u32),
+    Register(usize),
+    I,
+    Pc,
+    Sp,
+    DelayTimer,
+    SoundTimer,
+    Memory(Box<Expression>),
+    Compare(Box<Expression>, Comparison, Box<Expression>),
+    And(Box<Expression>, Box<Expression>),
+    Or(Box<Expression>, Box<Expression>),
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+enum Comparison {
+    Equal,
+    NotEqual,
+    Less,
+    LessOrEqual,
+    Greater,
+    GreaterOrEqual,
+}
+
+impl Condition {
+    pub fn parse(text: &str) -> Result<Self, ConditionError> {
+        let tokens = tokenize(text)?;
+        let mut parser = Parser {
+            tokens,
+            position: 0,
+            length: text.len(),
+        };
+
+        let expression = parser.parse_or()?;
+
+        match parser.tokens.get(parser.position) {
+            None => Ok(Self { expression }),
+            Some((column, token)) => Err(ConditionError {
+                column: *column,
+                message: format!("unexpected {}", token),
+            }),
+        }
+    }
+
+    pub fn evaluate(&self, registers: &Registers, mmu: &Mmu) -> bool {
+        evaluate(&self.expression, registers, mmu) != 0
+    }
+}
+
+impl FromStr for Condition {
+    type Err = ConditionError;
+
+    fn from_str(text: &str) -> Result<Self, Self::Err> {
+        Self::parse(text)
+    }
+}
+
+fn evaluate(expression: &Expression, registers: &Registers, mmu: &Mmu) -> u32 {
+    match expression {
+        Expression::Number(value) => *value,
+        Expression::Register(index) => registers.get_vx(*index) as u32,
+        Expression::I => registers.get_i() as u32,
+        Expression::Pc => registers.get_pc() as u32,
+        Expression::Sp => mmu.get_stack_pointer() as u32,
+        Expression::DelayTimer => mmu.get_delay_timer() as u32,
+        Expression::SoundTimer => mmu.get_sound_timer() as u32,
+        // peeks, so that evaluating a condition never triggers a read watchpoint
+        Expression::Memory(address) => {
+            mmu.peek_byte(evaluate(address, registers, mmu) as usize) as u32
+        }
+        Expression::Compare(left, comparison, right) => {
+            let left = evaluate(left, registers, mmu);
+            let right = evaluate(right, registers, mmu);
+
+            let result = match comparison {
+                Comparison::Equal => left == right,
+                Comparison::NotEqual => left != right,
+                Comparison::Less => left < right,
+                Comparison::LessOrEqual => left <= right,
+                Comparison::Greater => left > right,
+                Comparison::GreaterOrEqual => left >= right,
+            };
+
+            result as u32
+        }
+        Expression::And(left, right) => {
+            (evaluate(left, registers, mmu) != 0 && evaluate(right, registers, mmu) != 0) as u32
+        }
+        Expression::Or(left, right) => {
+            (evaluate(left, registers, mmu) != 0 || evaluate(right, registers, mmu) != 0) as u32
+        }
+    }
+}
+
+#[derive(Clone, Debug, PartialEq, Eq)]
+enum Token {
+    Number(u32),
+    Identifier(String),
+    Operator(&'static str),
+}
+
+impl fmt::Display for Token {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            Token::Number(value) => write!(f, "{}", value),
+            Token::Identifier(name) => write!(f, "{}", name),
+            Token::Operator(operator) => write!(f, "{}", operator),
+        }
+    }
+}
+
+// longest operators first, so that <= isn't read as <
+const OPERATORS: [&str; 12] = [
+    "==", "!=", "<=", ">=", "&&", "||", "<", ">", "(", ")", "[", "]",
+];
+
+// tokens with the column they start at, from 1
+fn tokenize(text: &str) -> Result<Vec<(usize, Token)>, ConditionError> {
+    let mut tokens = Vec::new();
+    let mut index = 0;
+
+    while index < text.len() {
+        let rest = &text[index..];
+        let character = rest.chars().next().unwrap_or_default();
+        let column = index + 1;
+
+        if character.is_whitespace() {
+            index += character.len_utf8();
+        } else if let Some(operator) = OPERATORS
+            .iter()
+            .find(|operator| rest.starts_with(**operator))
+        {
+            tokens.push((column, Token::Operator(operator)));
+            index += operator.len();
+        } else if character.is_ascii_alphanumeric() {
+            let length = rest
+                .find(|character: char| !character.is_ascii_alphanumeric())
+                .unwrap_or(rest.len());
+            let word = &rest[..length];
+
+            let token = if character.is_ascii_digit() {
+                let value = match word.strip_prefix("0x").or(word.strip_prefix("0X")) {
+                    Some(digits) => u32::from_str_radix(digits, 16),
+                    None => word.parse(),
+                };
+
+                Token::Number(value.map_err(|_| ConditionError {
+                    column,
+                    message: format!("invalid number {}", word),
+                })?)
+            } else {
+                Token::Identifier(word.to_ascii_uppercase())
+            };
+
+            tokens.push((column, token));
+            index += length;
+        } else {
+            return Err(ConditionError {
+                column,
+                message: format!("unexpected {}", character),
+            });
+        }
+    }
+
+    Ok(tokens)
+}
+
+struct Parser {
+    tokens: Vec<(usize, Token)>,
+    position: usize,
+    // length of the condition, to report errors at its end
+    length: usize,
+}
+
+impl Parser {
+    fn parse_or(&mut self) -> Result<Expression, ConditionError> {
+        let mut expression = self.parse_and()?;
+
+        while self.accept("||") {
+            expression = Expression::Or(Box::new(expression), Box::new(self.parse_and()?));
+        }
+
+        Ok(expression)
+    }
+
+    fn parse_and(&mut self) -> Result<Expression, ConditionError> {
+        let mut expression = self.parse_comparison()?;
+
+        while self.accept("&&") {
+            expression = Expression::And(Box::new(expression), Box::new(self.parse_comparison()?));
+        }
+
+        Ok(expression)
+    }
+
+    fn parse_comparison(&mut self) -> Result<Expression, ConditionError> {
+        let left = self.parse_operand()?;
+
+        let comparison = match self.tokens.get(self.position) {
+            Some((_, Token::Operator("=="))) => Comparison::Equal,
+            Some((_, Token::Operator("!="))) => Comparison::NotEqual,
+            Some((_, Token::Operator("<"))) => Comparison::Less,
+            Some((_, Token::Operator("<="))) => Comparison::LessOrEqual,
+            Some((_, Token::Operator(">"))) => Comparison::Greater,
+            Some((_, Token::Operator(">="))) => Comparison::GreaterOrEqual,
+            _ => return Ok(left),
+        };
+        self.position += 1;
+
+        let right = self.parse_operand()?;
+
+        Ok(Expression::Compare(
+            Box::new(left),
+            comparison,
+            Box::new(right),
+        ))
+    }
+
+    fn parse_operand(&mut self) -> Result<Expression, ConditionError> {
+        let (column, token) = match self.tokens.get(self.position) {
+            Some((column, token)) => (*column, token.clone()),
+            None => {
+                return Err(ConditionError {
+                    column: self.length + 1,
+                    message: "unexpected end of condition".to_string(),
+                })
+            }
+        };
+        self.position += 1;
+
+        match token {
+            Token::Number(value) => Ok(Expression::Number(value)),
+            Token::Operator("(") => {
+                let expression = self.parse_or()?;
+                self.expect(")")?;
+
+                Ok(expression)
+            }
+            Token::Operator("[") => {
+                let address = self.parse_or()?;
+                self.expect("]")?;
+
+                Ok(Expression::Memory(Box::new(address)))
+            }
+            Token::Identifier(name) => match name.as_str() {
+                "I" => Ok(Expression::I),
+                "PC" => Ok(Expression::Pc),
+                "SP" => Ok(Expression::Sp),
+                "DT" => Ok(Expression::DelayTimer),
+                "ST" => Ok(Expression::SoundTimer),
+                _ => match name.strip_prefix('V') {
+                    Some(digit) if digit.len() == 1 => {
+                        let index =
+                            usize::from_str_radix(digit, 16).map_err(|_| ConditionError {
+                                column,
+                                message: format!("unknown register {}", name),
+                            })?;
+
+                        Ok(Expression::Register(index))
+                    }
+                    _ => Err(ConditionError {
+                        column,
+                        message: format!("unknown register {}", name),
+                    }),
+                },
+            },
+            token => Err(ConditionError {
+                column,
+                message: format!("unexpected {}", token),
+            }),
+        }
+    }
+
+    fn accept(&mut self, operator: &str) -> bool {
+        match self.tokens.get(self.position) {
+            Some((_, Token::Operator(found))) if *found == operator => {
+                self.position += 1;
+                true
+            }
+            _ => false,
+        }
+    }
+
+    fn expect(&mut self, operator: &str) -> Result<(), ConditionError> {
+        if self.accept(operator) {
+            return Ok(());
+        }
+
+        let column = match self.tokens.get(self.position) {
+            Some((column, _)) => *column,
+            None => self.length + 1,
+        };
+
+        Err(ConditionError {
+            column,
+            message: format!("expected {}", operator),
+        })
+    }
+}
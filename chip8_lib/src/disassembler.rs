@@ -0,0 +1,216 @@
+use crate::instruction::{decode, Instruction};
+use std::collections::{BTreeMap, BTreeSet};
+use std::fmt;
+
+// roms are loaded at 0x200
+const ORIGIN: u16 = 0x200;
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq, PartialOrd, Ord)]
+pub enum LabelKind {
+    // target of a CALL
+    Subroutine,
+    // target of a JP
+    Jump,
+    // target of a LD I, most likely sprites or data
+    Data,
+}
+
+// Result of a recursive descent over a rom, following jumps, calls and skips from 0x200
+// Bytes never reached as an instruction are considered to be data
+pub struct Disassembly {
+    rom: Vec<u8>,
+    // instructions by address, the long load of I is stored with its address
+    instructions: BTreeMap<u16, (Instruction, Option<u16>)>,
+    labels: BTreeMap<u16, LabelKind>,
+}
+
+pub fn disassemble(rom: &[u8]) -> Disassembly {
+    let end = ORIGIN as usize + rom.len();
+    let word_at = |address: u16| -> Option<u16> {
+        let index = address.checked_sub(ORIGIN)? as usize;
+
+        if index + 1 < rom.len() {
+            Some(((rom[index] as u16) << 8) | rom[index + 1] as u16)
+        } else {
+            None
+        }
+    };
+
+    let mut instructions = BTreeMap::new();
+    let mut labels = BTreeMap::new();
+    let mut pending = vec![ORIGIN];
+
+    while let Some(mut address) = pending.pop() {
+        while (address as usize) < end && !instructions.contains_key(&address) {
+            let instruction = match word_at(address).map(decode) {
+                Some(Ok(instruction)) => instruction,
+                _ => break,
+            };
+            let long_address = match instruction {
+                Instruction::LdLongI => match address.checked_add(2).and_then(word_at) {
+                    Some(long_address) => Some(long_address),
+                    None => break,
+                },
+                _ => None,
+            };
+            // none past the end of the address space, for roms filling the 64 KiB of XO-CHIP
+            let next = address.checked_add(instruction.size());
+
+            instructions.insert(address, (instruction, long_address));
+
+            match instruction {
+                Instruction::Jp { addr } => {
+                    labels.entry(addr).or_insert(LabelKind::Jump);
+                    pending.push(addr);
+                    break;
+                }
+                Instruction::Call { addr } => {
+                    labels.insert(addr, LabelKind::Subroutine);
+                    pending.push(addr);
+                }
+                Instruction::LdI { addr } => {
+                    labels.entry(addr).or_insert(LabelKind::Data);
+                }
+                Instruction::LdLongI => {
+                    if let Some(long_address) = long_address {
+                        labels.entry(long_address).or_insert(LabelKind::Data);
+                    }
+                }
+                // the target depends on V0, only the base address is known
+                Instruction::JpV0 { addr } => {
+                    labels.entry(addr).or_insert(LabelKind::Jump);
+                    break;
+                }
+                Instruction::Ret | Instruction::Exit => break,
+                // both the next instruction and the one after can be executed
+                Instruction::SeByte { .. }
+                | Instruction::SneByte { .. }
+                | Instruction::SeReg { .. }
+                | Instruction::SneReg { .. }
+                | Instruction::Skp { .. }
+                | Instruction::Sknp { .. } => {
+                    let skipped = match next.and_then(word_at) {
+                        Some(0xF000) => 4,
+                        _ => 2,
+                    };
+
+                    if let Some(target) = next.and_then(|next| next.checked_add(skipped)) {
+                        pending.push(target);
+                    }
+                }
+                _ => {}
+            }
+
+            match next {
+                Some(next) => address = next,
+                None => break,
+            }
+        }
+    }
+
+    // labels are printed on the line starting at their address, targets outside of the rom or in the
+    // middle of an instruction keep their numeric address
+    let lines = line_addresses(rom.len(), &instructions);
+    labels.retain(|address, _| lines.contains(address));
+
+    Disassembly {
+        rom: rom.to_vec(),
+        instructions,
+        labels,
+    }
+}
+
+// addresses the lines of the disassembly start at, an instruction or a byte of data each
+fn line_addresses(
+    rom_size: usize,
+    instructions: &BTreeMap<u16, (Instruction, Option<u16>)>,
+) -> BTreeSet<u16> {
+    let end = ORIGIN as usize + rom_size;
+    let mut lines = BTreeSet::new();
+    let mut address = ORIGIN as usize;
+
+    while address < end {
+        lines.insert(address as u16);
+        address += instructions
+            .get(&(address as u16))
+            .map_or(1, |(instruction, _)| instruction.size() as usize);
+    }
+
+    lines
+}
+
+impl Disassembly {
+    pub fn instruction_at(&self, address: u16) -> Option<Instruction> {
+        self.instructions
+            .get(&address)
+            .map(|(instruction, _)| *instruction)
+    }
+
+    pub fn labels(&self) -> &BTreeMap<u16, LabelKind> {
+        &self.labels
+    }
+
+    pub fn label(&self, address: u16) -> Option<String> {
+        self.labels.get(&address).map(|kind| match kind {
+            LabelKind::Subroutine => format!("sub_{:03x}", address),
+            LabelKind::Jump => format!("label_{:03x}", address),
+            LabelKind::Data => format!("data_{:03x}", address),
+        })
+    }
+
+    fn format_address(&self, address: u16) -> String {
+        self.label(address)
+            .unwrap_or_else(|| format!("{:#05x}", address))
+    }
+}
+
+impl fmt::Display for Disassembly {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        let end = ORIGIN as usize + self.rom.len();
+        let mut address = ORIGIN as usize;
+
+        // addresses are usize, the last line of a rom filling the XO-CHIP memory ending at 0x10000
+        while address < end {
+            if let Some(label) = self.label(address as u16) {
+                writeln!(f, "{}:", label)?;
+            }
+
+            let index = address - ORIGIN as usize;
+
+            match self.instructions.get(&(address as u16)) {
+                Some((instruction, long_address)) => {
+                    let size = instruction.size() as usize;
+                    let raw: String = self.rom[index..index + size]
+                        .iter()
+                        .map(|byte| format!("{:02x}", byte))
+                        .collect();
+                    let text = match long_address {
+                        Some(long_address) => {
+                            format!("LD I, {}", self.format_address(*long_address))
+                        }
+                        None => instruction.format_with(|addr| self.format_address(addr)),
+                    };
+
+                    writeln!(f, "    {:#05x}  {:<8}  {}", address, raw, text)?;
+                    address += size;
+                }
+                None => {
+                    // data is shown with a preview of the sprite row it would draw
+                    let byte = self.rom[index];
+                    let preview: String = (0..8)
+                        .map(|bit| if byte & (0x80 >> bit) != 0 { '#' } else { '.' })
+                        .collect();
+
+                    writeln!(
+                        f,
+                        "    {:#05x}  {:02x}        DB {:#04x}  ; {}",
+                        address, byte, byte, preview
+                    )?;
+                    address += 1;
+                }
+            }
+        }
+
+        Ok(())
+    }
+}
@@ -1,56 +1,608 @@
+use crate::clock::{Clock, MonotonicClock};
+use crate::config::Config;
 use crate::cpu::Cpu;
+use crate::debug::{Access, Condition, FrameReport, StopReason, Watchpoint};
+use crate::error::{CpuFault, LoadError, StateError};
+use crate::instruction::{decode, decode_for, Instruction};
 use crate::interfaces::{Controller, Renderer, Synthetizer};
 use crate::mmu::Mmu;
+use crate::random::{random_seed, RandomSource};
 use crate::registers::Registers;
+use crate::state::{rom_hash, StateReader, StateWriter, MAGIC, VERSION};
+use crate::timing::{self, Timing, VIP_DISPLAY_CYCLES};
+use crate::trace::Tracer;
+use std::collections::BTreeMap;
 use std::fs;
-use std::time::{Duration, SystemTime, SystemTimeError};
+use std::mem;
+use std::path::Path;
+use std::time::Duration;
+
+const NANOS_PER_SECOND: u128 = 1_000_000_000;
 
 pub struct Emulator {
+    // with the seed that was picked when the configuration had none
+    config: Config,
     cpu: Cpu,
     mmu: Mmu,
     registers: Registers,
+    // identifies the rom in save states
+    rom_hash: u32,
+    // false once a frame has ended, until the next one latches the keys
+    in_frame: bool,
+    // emulated time since the emulator was created, in instructions at the configured speed
+    cycle_count: u64,
+    // frames completed since the emulator was created
+    // frame n ends once cycle_count reaches n * cpu_hz / 60, rounded up, so timers run at exactly 60 Hz
+    frame_count: u64,
+    // part of a cycle left over by run_for, in nanoseconds times cpu_hz
+    time_remainder: u128,
+    // cycles the last run_cycles ran past its target, as instructions aren't split, taken from the next one
+    cycle_overshoot: u64,
+    // cycles of the rows of the sprite drawn last with the VIP timing, taken from the start of the next frame
+    sprite_cycles: u64,
+    breakpoints: BTreeMap<u16, Option<Condition>>,
+    watchpoints: Vec<Watchpoint>,
+    // address execution stopped at before running it, which runs when resuming
+    stopped_at: Option<u16>,
+    // measures the host time runs take
+    clock: Box<dyn Clock>,
 }
 
 impl Emulator {
-    pub fn new(rom_path: &String) -> Self {
-        let result = load_file_to_vector(rom_path);
+    pub fn from_bytes(rom: &[u8], config: Config) -> Result<Self, LoadError> {
+        // roms are loaded at 0x200
+        let max_size = config.platform.memory_size() - 0x200;
 
-        if result.is_ok() {
-            let rom = result.unwrap();
+        if rom.is_empty() {
+            return Err(LoadError::EmptyRom);
+        }
 
-            Self {
-                cpu: Cpu {},
-                mmu: Mmu::new(&rom),
-                registers: Registers::new(),
-            }
-        } else {
-            panic!("Could not load file: {}", rom_path);
+        if rom.len() > max_size {
+            return Err(LoadError::RomTooLarge {
+                size: rom.len(),
+                max_size,
+            });
+        }
+
+        if config.cpu_hz == 0 {
+            return Err(LoadError::ZeroCpuSpeed);
         }
+
+        let seed = config.seed.unwrap_or_else(random_seed);
+
+        Ok(Self {
+            config: config.with_seed(seed),
+            cpu: Cpu::new(
+                config.platform,
+                config.quirks,
+                config.faults,
+                config.generator.source(seed),
+            ),
+            mmu: Mmu::new(rom, config.platform.memory_size()),
+            registers: Registers::new(),
+            rom_hash: rom_hash(rom),
+            in_frame: false,
+            cycle_count: 0,
+            frame_count: 0,
+            time_remainder: 0,
+            cycle_overshoot: 0,
+            sprite_cycles: 0,
+            breakpoints: BTreeMap::new(),
+            watchpoints: Vec::new(),
+            stopped_at: None,
+            clock: Box::new(MonotonicClock::new()),
+        })
+    }
+
+    pub fn from_file(rom_path: impl AsRef<Path>, config: Config) -> Result<Self, LoadError> {
+        Self::from_bytes(&fs::read(rom_path)?, config)
+    }
+
+    // replaces the generator of the config, movies can't be replayed with other sources
+    pub fn with_random_source(mut self, random: impl RandomSource + 'static) -> Self {
+        self.cpu.set_random_source(Box::new(random));
+        self
+    }
+
+    // measures the elapsed time of frame reports with another clock
+    pub fn with_clock(mut self, clock: impl Clock + 'static) -> Self {
+        self.clock = Box::new(clock);
+        self
+    }
+
+    // fault that halted the cpu, nothing runs anymore
+    pub fn get_fault(&self) -> Option<CpuFault> {
+        self.cpu.get_fault()
+    }
+
+    // true once the rom has exited the interpreter (SUPER-CHIP 00fd)
+    pub fn has_exited(&self) -> bool {
+        self.cpu.has_exited()
+    }
+
+    // true when the current instruction jumps to itself, which is how most roms end
+    pub fn is_looping(&self) -> bool {
+        let pc = self.registers.get_pc();
+
+        decode(self.mmu.get_short(pc as usize)) == Ok(Instruction::Jp { addr: pc })
+    }
+
+    pub fn config(&self) -> &Config {
+        &self.config
     }
 
+    // identifies the rom in save states and movies
+    pub fn get_rom_hash(&self) -> u32 {
+        self.rom_hash
+    }
+
+    pub fn get_frame_count(&self) -> u64 {
+        self.frame_count
+    }
+
+    pub fn get_cycle_count(&self) -> u64 {
+        self.cycle_count
+    }
+
+    pub fn save_state(&self) -> Vec<u8> {
+        let mut writer = StateWriter::new();
+
+        writer.write_bytes(&MAGIC);
+        writer.write_u8(VERSION);
+        writer.write_u32(self.rom_hash);
+
+        self.registers.write_state(&mut writer);
+        self.mmu.write_state(&mut writer);
+        self.cpu.write_state(&mut writer);
+
+        // below a second of nanoseconds times cpu_hz, which fits
+        writer.write_bool(self.in_frame);
+        writer.write_u64(self.cycle_count);
+        writer.write_u64(self.frame_count);
+        writer.write_u64(self.time_remainder as u64);
+        writer.write_u64(self.cycle_overshoot);
+        writer.write_u64(self.sprite_cycles);
+
+        writer.into_bytes()
+    }
+
+    // the emulator is left untouched if the state can't be loaded
+    pub fn load_state(&mut self, state: &[u8]) -> Result<(), StateError> {
+        let mut reader = StateReader::new(state);
+
+        if reader.read_bytes(MAGIC.len())? != MAGIC {
+            return Err(StateError::InvalidMagic);
+        }
+
+        let version = reader.read_u8()?;
+        if version != VERSION {
+            return Err(StateError::UnsupportedVersion(version));
+        }
+
+        if reader.read_u32()? != self.rom_hash {
+            return Err(StateError::RomMismatch);
+        }
+
+        let registers = Registers::read_state(&mut reader)?;
+        let mmu = Mmu::read_state(&mut reader)?;
+        let cpu = Cpu::read_state(&mut reader)?;
+        let in_frame = reader.read_bool()?;
+        let cycle_count = reader.read_u64()?;
+        let frame_count = reader.read_u64()?;
+        let time_remainder = reader.read_u64()? as u128;
+        let cycle_overshoot = reader.read_u64()?;
+        let sprite_cycles = reader.read_u64()?;
+        reader.finish()?;
+
+        if mmu.get_address_mask() != self.mmu.get_address_mask() {
+            return Err(StateError::PlatformMismatch);
+        }
+
+        self.registers = registers;
+        self.mmu = mmu;
+        self.cpu.restore_state(cpu);
+        self.in_frame = in_frame;
+        self.cycle_count = cycle_count;
+        self.frame_count = frame_count;
+        self.time_remainder = time_remainder;
+        self.cycle_overshoot = cycle_overshoot;
+        self.sprite_cycles = sprite_cycles;
+        self.stopped_at = None;
+
+        self.mmu
+            .set_recording_accesses(!self.watchpoints.is_empty());
+
+        Ok(())
+    }
+
+    // draws the current screen, without running anything
+    pub fn render(&self, renderer: &mut impl Renderer) {
+        renderer.render(
+            self.mmu.get_screen(),
+            self.mmu.get_screen_width(),
+            self.mmu.get_screen_height(),
+        );
+    }
+
+    pub fn registers(&self) -> &Registers {
+        &self.registers
+    }
+
+    pub fn mmu(&self) -> &Mmu {
+        &self.mmu
+    }
+
+    // changes memory from outside of the rom, like a debugger would
+    pub fn poke_byte(&mut self, address: usize, value: u8) {
+        self.mmu.poke_byte(address, value);
+    }
+
+    // stops before running the instruction at the address, when the condition holds if there is one
+    pub fn add_breakpoint(&mut self, address: u16, condition: Option<Condition>) {
+        self.breakpoints.insert(address, condition);
+    }
+
+    pub fn remove_breakpoint(&mut self, address: u16) -> bool {
+        self.breakpoints.remove(&address).is_some()
+    }
+
+    pub fn breakpoints(&self) -> &BTreeMap<u16, Option<Condition>> {
+        &self.breakpoints
+    }
+
+    pub fn add_watchpoint(&mut self, watchpoint: Watchpoint) {
+        self.watchpoints.push(watchpoint);
+        self.mmu.set_recording_accesses(true);
+    }
+
+    pub fn remove_watchpoint(&mut self, watchpoint: &Watchpoint) -> bool {
+        let count = self.watchpoints.len();
+
+        self.watchpoints.retain(|watched| watched != watchpoint);
+        self.mmu
+            .set_recording_accesses(!self.watchpoints.is_empty());
+
+        self.watchpoints.len() != count
+    }
+
+    pub fn watchpoints(&self) -> &[Watchpoint] {
+        &self.watchpoints
+    }
+
+    // breakpoint or execute watchpoint on the instruction about to run
+    pub fn check_breakpoints(&self) -> Option<StopReason> {
+        let pc = self.registers.get_pc();
+
+        let breakpoint = match self.breakpoints.get(&pc) {
+            Some(Some(condition)) => condition.evaluate(&self.registers, &self.mmu),
+            Some(None) => true,
+            None => false,
+        };
+
+        if breakpoint {
+            return Some(StopReason::Breakpoint { address: pc });
+        }
+
+        self.watchpoints
+            .iter()
+            .find(|watchpoint| watchpoint.matches(pc, Access::Execute))
+            .map(|_| StopReason::Watchpoint {
+                address: pc,
+                access: Access::Execute,
+                value: self.mmu.peek_byte(pc as usize),
+            })
+    }
+
+    // logs every instruction run from now on
+    pub fn set_tracer(&mut self, tracer: Tracer) {
+        self.cpu.set_tracer(Some(tracer));
+    }
+
+    pub fn tracer_mut(&mut self) -> Option<&mut Tracer> {
+        self.cpu.tracer_mut()
+    }
+
+    // stops tracing, finish has to be called on the tracer to flush it
+    pub fn take_tracer(&mut self) -> Option<Tracer> {
+        self.cpu.take_tracer()
+    }
+
+    // true once a sprite has been drawn with the display wait quirk, nothing runs until the frame ends
+    pub fn is_waiting_for_vblank(&self) -> bool {
+        self.cpu.is_waiting_for_vblank()
+    }
+
+    // true while fx0a waits for a key to be pressed and released, front-ends can show a hint meanwhile
+    pub fn is_waiting_for_key(&self) -> bool {
+        self.cpu.is_waiting_for_key()
+    }
+
+    // runs a single instruction, ignoring breakpoints, and ends the frame once its time is up
+    // returns the read or write watchpoint the instruction triggered
+    pub fn step_instruction(
+        &mut self,
+        controller: &impl Controller,
+        renderer: &mut impl Renderer,
+        synthetizer: &mut impl Synthetizer,
+    ) -> Option<StopReason> {
+        self.step(u64::MAX, controller, renderer, synthetizer)
+    }
+
+    // runs until the end of the frame, or until a breakpoint or watchpoint stops it
+    // a stopped frame is resumed by the next call
     pub fn step_frame(
         &mut self,
-        instructions_per_frame: u32,
         controller: &impl Controller,
         renderer: &mut impl Renderer,
         synthetizer: &mut impl Synthetizer,
-    ) -> Result<Duration, SystemTimeError> {
-        let start_time = SystemTime::now();
+    ) -> FrameReport {
+        let frame_count = self.frame_count;
+
+        self.run(
+            u64::MAX,
+            |emulator| emulator.frame_count != frame_count,
+            controller,
+            renderer,
+            synthetizer,
+        )
+    }
+
+    // runs the given number of cycles of emulated time, ending frames on the way as their time is up
+    // cycles a breakpoint or watchpoint didn't run are dropped, execution resumes with the next call
+    pub fn run_cycles(
+        &mut self,
+        cycles: u64,
+        controller: &impl Controller,
+        renderer: &mut impl Renderer,
+        synthetizer: &mut impl Synthetizer,
+    ) -> FrameReport {
+        let target = (self.cycle_count - self.cycle_overshoot).saturating_add(cycles);
+
+        let report = self.run(
+            target,
+            |emulator| emulator.cycle_count >= target,
+            controller,
+            renderer,
+            synthetizer,
+        );
+
+        self.cycle_overshoot = match report.stop_reason {
+            Some(_) => 0,
+            None => self.cycle_count - target,
+        };
+
+        report
+    }
+
+    // runs for the given emulated time, the part of a cycle left over is carried to the next call
+    pub fn run_for(
+        &mut self,
+        duration: Duration,
+        controller: &impl Controller,
+        renderer: &mut impl Renderer,
+        synthetizer: &mut impl Synthetizer,
+    ) -> FrameReport {
+        let time = duration.as_nanos() * self.cycles_per_second() as u128 + self.time_remainder;
+        let cycles = time / NANOS_PER_SECOND;
+        self.time_remainder = time % NANOS_PER_SECOND;
+
+        self.run_cycles(
+            cycles.try_into().unwrap_or(u64::MAX),
+            controller,
+            renderer,
+            synthetizer,
+        )
+    }
+
+    // runs instructions until done, a breakpoint or a watchpoint, without going past the cycle limit
+    fn run(
+        &mut self,
+        limit: u64,
+        done: impl Fn(&Self) -> bool,
+        controller: &impl Controller,
+        renderer: &mut impl Renderer,
+        synthetizer: &mut impl Synthetizer,
+    ) -> FrameReport {
+        let start_time = self.clock.now();
+
+        while !done(self) {
+            let pc = self.registers.get_pc();
+
+            // the instruction execution stopped at runs when resuming, and none runs while idle
+            if !self.is_idle() && self.stopped_at.take() != Some(pc) {
+                if let Some(stop_reason) = self.check_breakpoints() {
+                    self.stopped_at = Some(pc);
+
+                    return self.stop_frame(start_time, stop_reason, renderer);
+                }
+            }
+
+            if let Some(stop_reason) = self.step(limit, controller, renderer, synthetizer) {
+                return self.stop_frame(start_time, stop_reason, renderer);
+            }
+        }
+
+        FrameReport {
+            elapsed: self.clock.now().saturating_sub(start_time),
+            stop_reason: None,
+        }
+    }
+
+    // runs an instruction, or idles until the end of the frame or the cycle limit when nothing can run
+    fn step(
+        &mut self,
+        limit: u64,
+        controller: &impl Controller,
+        renderer: &mut impl Renderer,
+        synthetizer: &mut impl Synthetizer,
+    ) -> Option<StopReason> {
+        if !self.in_frame {
+            self.begin_frame(controller);
+        }
+
+        let mut stop_reason = None;
+
+        if !self.is_idle() {
+            // accesses made by the front-end between instructions don't count
+            self.mmu.take_accesses();
+            self.stopped_at = None;
+
+            stop_reason = match self.run_instruction() {
+                Ok(cycles) => {
+                    self.cycle_count += cycles;
+
+                    self.check_watchpoints()
+                }
+                // the cpu stays on the faulting instruction, which takes no time
+                Err(fault) => Some(StopReason::Fault(fault)),
+            };
+        }
+
+        // the rest of the frame is idle once a sprite is drawn if the display wait quirk is on
+        if self.is_idle() {
+            let frame_end = self.frame_start(self.frame_count + 1);
+
+            self.cycle_count = self.cycle_count.max(frame_end.min(limit));
+        }
+
+        // a single instruction can last several frames below 60 Hz
+        while self.cycle_count >= self.frame_start(self.frame_count + 1) {
+            self.end_frame(renderer, synthetizer);
+        }
+
+        stop_reason
+    }
+
+    // ticks the cpu, returning the cycles the instruction took, or the fault that halted it
+    fn run_instruction(&mut self) -> Result<u64, CpuFault> {
+        if self.config.timing == Timing::Instructions {
+            self.cpu.tick(&mut self.mmu, &mut self.registers)?;
+
+            return Ok(1);
+        }
+
+        let pc = self.registers.get_pc();
+        let instruction = decode_for(self.config.platform, self.mmu.get_short(pc as usize));
+
+        // costs depend on the registers before the instruction changes them
+        let cycles = instruction.map_or(1, |instruction| {
+            timing::vip_cycles(&instruction, &self.registers)
+        });
+        let sprite_cycles = instruction.map_or(0, |instruction| {
+            timing::vip_sprite_cycles(&instruction, &self.registers)
+        });
+
+        self.cpu.tick(&mut self.mmu, &mut self.registers)?;
+
+        Ok(match instruction {
+            // the interpreter waits for the vertical blank, then draws the rows in the next frame
+            Ok(Instruction::Drw { .. }) => {
+                self.cpu.wait_for_vblank();
+                self.sprite_cycles = sprite_cycles;
+
+                cycles
+            }
+            Ok(
+                Instruction::SeByte { .. }
+                | Instruction::SneByte { .. }
+                | Instruction::SeReg { .. }
+                | Instruction::SneReg { .. }
+                | Instruction::Skp { .. }
+                | Instruction::Sknp { .. },
+            ) if self.registers.get_pc() != pc.wrapping_add(2) => cycles + timing::SKIP_CYCLES,
+            _ => cycles,
+        })
+    }
+
+    // nothing runs until the next frame
+    fn is_idle(&self) -> bool {
+        (self.in_frame && self.cpu.is_waiting_for_vblank())
+            || self.cpu.is_waiting_for_key()
+            || self.cpu.has_exited()
+            || self.cpu.get_fault().is_some()
+    }
+
+    // instructions per second, or machine cycles per second with the VIP timing
+    fn cycles_per_second(&self) -> u64 {
+        self.config.timing.cycles_per_second(self.config.cpu_hz) as u64
+    }
+
+    // cycle the frame starts at
+    fn frame_start(&self, frame: u64) -> u64 {
+        (frame * self.cycles_per_second()).div_ceil(60)
+    }
+
+    // renders the screen as it is when stopping in the middle of a frame, and dumps the trace
+    fn stop_frame(
+        &mut self,
+        start_time: Duration,
+        stop_reason: StopReason,
+        renderer: &mut impl Renderer,
+    ) -> FrameReport {
+        self.render(renderer);
+
+        // the cpu already dumped the trace of its faults
+        if let (Some(tracer), false) = (
+            self.cpu.tracer_mut(),
+            matches!(stop_reason, StopReason::Fault(_)),
+        ) {
+            tracer.dump(&stop_reason.to_string());
+        }
+
+        FrameReport {
+            elapsed: self.clock.now().saturating_sub(start_time),
+            stop_reason: Some(stop_reason),
+        }
+    }
+
+    // first read or write of the last instruction matching a watchpoint
+    fn check_watchpoints(&self) -> Option<StopReason> {
+        if self.watchpoints.is_empty() {
+            return None;
+        }
+
+        self.mmu
+            .take_accesses()
+            .into_iter()
+            .find(|(address, access, _)| {
+                self.watchpoints
+                    .iter()
+                    .any(|watchpoint| watchpoint.matches(*address, *access))
+            })
+            .map(|(address, access, value)| StopReason::Watchpoint {
+                address,
+                access,
+                value,
+            })
+    }
+
+    // latches the keys and starts a new frame
+    fn begin_frame(&mut self, controller: &impl Controller) {
+        self.mmu.latch_keys();
 
-        // handle input
         for i in 0..16 {
             self.mmu.set_key_down(i, controller.is_key_down(i));
         }
 
-        // run frame
-        for _ in 0..instructions_per_frame {
-            self.cpu.tick(&mut self.mmu, &mut self.registers);
+        self.cpu.vblank(&self.mmu, &mut self.registers);
+        self.in_frame = true;
+
+        if self.config.timing == Timing::CosmacVip {
+            self.cycle_count += VIP_DISPLAY_CYCLES + mem::take(&mut self.sprite_cycles);
         }
+    }
 
-        // render screen
-        renderer.render(self.mmu.get_screen());
+    // renders the screen, handles sound and decrements the timers
+    fn end_frame(&mut self, renderer: &mut impl Renderer, synthetizer: &mut impl Synthetizer) {
+        self.in_frame = false;
+        self.frame_count += 1;
+        self.render(renderer);
+
+        // handle sound, using the XO-CHIP audio pattern once one has been loaded
+        if let Some(pattern) = self.mmu.get_audio_pattern() {
+            synthetizer.set_pattern(pattern, self.mmu.get_pitch());
+        }
 
-        // handle sound
         if self.mmu.get_sound_timer() > 0 {
             synthetizer.play();
         } else {
@@ -60,13 +612,5 @@ impl Emulator {
         // decrement timers
         self.mmu.decrement_delay_timer();
         self.mmu.decrement_sound_timer();
-
-        SystemTime::now().duration_since(start_time)
     }
 }
-
-fn load_file_to_vector(path: &String) -> Result<Vec<u8>, Box<dyn std::error::Error>> {
-    let data: Vec<u8> = fs::read(path)?;
-
-    Ok(data)
-}
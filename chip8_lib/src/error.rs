@@ -0,0 +1,174 @@
+use std::error::Error;
+use std::fmt;
+use std::io;
+
+#[derive(Debug)]
+pub enum LoadError {
+    Io(io::Error),
+    // roms are loaded at 0x200, and have to fit in the remaining memory
+    RomTooLarge { size: usize, max_size: usize },
+    EmptyRom,
+    // the configuration asked for 0 instructions per second
+    ZeroCpuSpeed,
+}
+
+impl fmt::Display for LoadError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            LoadError::Io(error) => write!(f, "could not read the rom: {}", error),
+            LoadError::RomTooLarge { size, max_size } => write!(
+                f,
+                "rom is too large: {} bytes, at most {} bytes fit in memory",
+                size, max_size
+            ),
+            LoadError::EmptyRom => write!(f, "rom is empty"),
+            LoadError::ZeroCpuSpeed => write!(f, "cpu speed must be at least 1 Hz"),
+        }
+    }
+}
+
+impl Error for LoadError {
+    fn source(&self) -> Option<&(dyn Error + 'static)> {
+        match self {
+            LoadError::Io(error) => Some(error),
+            _ => None,
+        }
+    }
+}
+
+impl From<io::Error> for LoadError {
+    fn from(error: io::Error) -> Self {
+        LoadError::Io(error)
+    }
+}
+
+#[derive(Debug, PartialEq, Eq)]
+pub enum StateError {
+    InvalidMagic,
+    UnsupportedVersion(u8),
+    // the state was saved while running another rom
+    RomMismatch,
+    // the state was saved on a platform with a different memory size
+    PlatformMismatch,
+    Truncated,
+    Corrupted,
+}
+
+impl fmt::Display for StateError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            StateError::InvalidMagic => write!(f, "not a save state"),
+            StateError::UnsupportedVersion(version) => {
+                write!(f, "unsupported save state version: {}", version)
+            }
+            StateError::RomMismatch => write!(f, "save state belongs to another rom"),
+            StateError::PlatformMismatch => write!(f, "save state belongs to another platform"),
+            StateError::Truncated => write!(f, "save state is truncated"),
+            StateError::Corrupted => write!(f, "save state is corrupted"),
+        }
+    }
+}
+
+impl Error for StateError {}
+
+#[derive(Debug, PartialEq, Eq)]
+pub enum MovieError {
+    InvalidMagic,
+    UnsupportedVersion(u8),
+    // the movie was recorded while running another rom
+    RomMismatch,
+    Truncated,
+    Corrupted,
+}
+
+impl fmt::Display for MovieError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            MovieError::InvalidMagic => write!(f, "not a movie"),
+            MovieError::UnsupportedVersion(version) => {
+                write!(f, "unsupported movie version: {}", version)
+            }
+            MovieError::RomMismatch => write!(f, "movie was recorded with another rom"),
+            MovieError::Truncated => write!(f, "movie is truncated"),
+            MovieError::Corrupted => write!(f, "movie is corrupted"),
+        }
+    }
+}
+
+impl Error for MovieError {}
+
+// movies are read with the reader of the save states
+impl From<StateError> for MovieError {
+    fn from(error: StateError) -> Self {
+        match error {
+            StateError::Truncated => MovieError::Truncated,
+            _ => MovieError::Corrupted,
+        }
+    }
+}
+
+// Something a rom did that the CPU can't run, addresses being the one of the faulting instruction
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum CpuFault {
+    // a call with the 16 levels of the stack in use
+    StackOverflow { address: u16 },
+    // a return with an empty stack
+    StackUnderflow { address: u16 },
+    InvalidOpcode { address: u16, op_code: u16 },
+    // the instruction reads or writes memory up to target, past the end of memory
+    MemoryOutOfRange { address: u16, target: usize },
+}
+
+impl fmt::Display for CpuFault {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            CpuFault::StackOverflow { address } => write!(f, "stack overflow at {:#06x}", address),
+            CpuFault::StackUnderflow { address } => {
+                write!(f, "stack underflow at {:#06x}", address)
+            }
+            CpuFault::InvalidOpcode { address, op_code } => {
+                write!(f, "invalid opcode: {:#06x} at {:#06x}", op_code, address)
+            }
+            CpuFault::MemoryOutOfRange { address, target } => write!(
+                f,
+                "memory out of range at {:#06x}: {:#06x} is past the end of memory",
+                address, target
+            ),
+        }
+    }
+}
+
+impl Error for CpuFault {}
+
+#[derive(Debug, PartialEq, Eq)]
+pub struct ConditionError {
+    // column of the condition, from 1
+    pub column: usize,
+    pub message: String,
+}
+
+impl fmt::Display for ConditionError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "column {}: {}", self.column, self.message)
+    }
+}
+
+impl Error for ConditionError {}
+
+// opcode patterns are 4 hex digits, any other character matching every digit
+#[derive(Debug, PartialEq, Eq)]
+pub struct OpcodeFilterError {
+    pub pattern: String,
+}
+
+impl fmt::Display for OpcodeFilterError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(
+            f,
+            "invalid opcode pattern '{}', expected 4 characters such as 8xy4",
+            self.pattern
+        )
+    }
+}
+
+impl Error for OpcodeFilterError {}
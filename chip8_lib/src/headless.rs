@@ -0,0 +1,67 @@
+use crate::interfaces::{Controller, Renderer, Synthetizer};
+
+// Implementations of the interfaces for running without a display, keyboard or sound
+
+// no key is ever pressed
+pub struct NullController;
+
+impl Controller for NullController {
+    fn is_key_down(&self, _key_index: usize) -> bool {
+        false
+    }
+}
+
+// sound is discarded
+pub struct NullSynthetizer;
+
+impl Synthetizer for NullSynthetizer {
+    fn play(&mut self) {}
+
+    fn stop(&mut self) {}
+
+    fn set_pattern(&mut self, _pattern: &[u8; 16], _pitch: u8) {}
+}
+
+// keeps a copy of the last rendered screen
+pub struct CaptureRenderer {
+    screen: Vec<u8>,
+    width: usize,
+    height: usize,
+}
+
+impl CaptureRenderer {
+    pub fn new() -> Self {
+        Self {
+            screen: vec![0; 64 * 32],
+            width: 64,
+            height: 32,
+        }
+    }
+
+    pub fn get_screen(&self) -> &[u8] {
+        &self.screen
+    }
+
+    pub fn get_width(&self) -> usize {
+        self.width
+    }
+
+    pub fn get_height(&self) -> usize {
+        self.height
+    }
+}
+
+impl Default for CaptureRenderer {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl Renderer for CaptureRenderer {
+    fn render(&mut self, screen: &[u8], width: usize, height: usize) {
+        self.screen.clear();
+        self.screen.extend_from_slice(screen);
+        self.width = width;
+        self.height = height;
+    }
+}
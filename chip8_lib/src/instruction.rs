@@ -0,0 +1,356 @@
+use crate::platform::Platform;
+use std::error::Error;
+use std::fmt;
+
+// Decoded op code, named after the mnemonics of Cowgod's reference (and its SUPER-CHIP / XO-CHIP extensions)
+// x and y are register indexes, n a nibble, byte an 8 bits value and addr a 12 bits address
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Instruction {
+    // 00CN - SCD nibble
+    Scd { n: u8 },
+    // 00DN - SCU nibble
+    Scu { n: u8 },
+    // 00E0 - CLS
+    Cls,
+    // 00EE - RET
+    Ret,
+    // 00FB - SCR
+    Scr,
+    // 00FC - SCL
+    Scl,
+    // 00FD - EXIT
+    Exit,
+    // 00FE - LOW
+    Low,
+    // 00FF - HIGH
+    High,
+    // 1NNN - JP addr
+    Jp { addr: u16 },
+    // 2NNN - CALL addr
+    Call { addr: u16 },
+    // 3XNN - SE Vx, byte
+    SeByte { x: u8, byte: u8 },
+    // 4XNN - SNE Vx, byte
+    SneByte { x: u8, byte: u8 },
+    // 5XY0 - SE Vx, Vy
+    SeReg { x: u8, y: u8 },
+    // 5XY2 - LD [I], Vx-Vy
+    SaveRange { x: u8, y: u8 },
+    // 5XY3 - LD Vx-Vy, [I]
+    LoadRange { x: u8, y: u8 },
+    // 6XNN - LD Vx, byte
+    LdByte { x: u8, byte: u8 },
+    // 7XNN - ADD Vx, byte
+    AddByte { x: u8, byte: u8 },
+    // 8XY0 - LD Vx, Vy
+    LdReg { x: u8, y: u8 },
+    // 8XY1 - OR Vx, Vy
+    Or { x: u8, y: u8 },
+    // 8XY2 - AND Vx, Vy
+    And { x: u8, y: u8 },
+    // 8XY3 - XOR Vx, Vy
+    Xor { x: u8, y: u8 },
+    // 8XY4 - ADD Vx, Vy
+    AddReg { x: u8, y: u8 },
+    // 8XY5 - SUB Vx, Vy
+    Sub { x: u8, y: u8 },
+    // 8XY6 - SHR Vx {, Vy}
+    Shr { x: u8, y: u8 },
+    // 8XY7 - SUBN Vx, Vy
+    Subn { x: u8, y: u8 },
+    // 8XYE - SHL Vx {, Vy}
+    Shl { x: u8, y: u8 },
+    // 9XY0 - SNE Vx, Vy
+    SneReg { x: u8, y: u8 },
+    // ANNN - LD I, addr
+    LdI { addr: u16 },
+    // BNNN - JP V0, addr
+    JpV0 { addr: u16 },
+    // CXNN - RND Vx, byte
+    Rnd { x: u8, byte: u8 },
+    // DXYN - DRW Vx, Vy, nibble
+    Drw { x: u8, y: u8, n: u8 },
+    // EX9E - SKP Vx
+    Skp { x: u8 },
+    // EXA1 - SKNP Vx
+    Sknp { x: u8 },
+    // F000 NNNN - LD I, long addr, the 16 bits address is the word following the op code
+    LdLongI,
+    // FN01 - PLANE n
+    Plane { n: u8 },
+    // F002 - AUDIO
+    Audio,
+    // FX07 - LD Vx, DT
+    LdVxDt { x: u8 },
+    // FX0A - LD Vx, K
+    LdVxK { x: u8 },
+    // FX15 - LD DT, Vx
+    LdDtVx { x: u8 },
+    // FX18 - LD ST, Vx
+    LdStVx { x: u8 },
+    // FX1E - ADD I, Vx
+    AddI { x: u8 },
+    // FX29 - LD F, Vx
+    LdF { x: u8 },
+    // FX30 - LD HF, Vx
+    LdHf { x: u8 },
+    // FX33 - LD B, Vx
+    LdB { x: u8 },
+    // FX3A - PITCH Vx
+    Pitch { x: u8 },
+    // FX55 - LD [I], Vx
+    LdIVx { x: u8 },
+    // FX65 - LD Vx, [I]
+    LdVxI { x: u8 },
+    // FX75 - LD R, Vx
+    LdRVx { x: u8 },
+    // FX85 - LD Vx, R
+    LdVxR { x: u8 },
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct DecodeError {
+    pub op_code: u16,
+}
+
+impl fmt::Display for DecodeError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "invalid opcode: {:#06x}", self.op_code)
+    }
+}
+
+impl Error for DecodeError {}
+
+pub fn decode(op_code: u16) -> Result<Instruction, DecodeError> {
+    let x = ((op_code & 0x0F00) >> 8) as u8;
+    let y = ((op_code & 0x00F0) >> 4) as u8;
+    let n = (op_code & 0x000F) as u8;
+    let byte = (op_code & 0x00FF) as u8;
+    let addr = op_code & 0x0FFF;
+
+    let instruction = match op_code & 0xF000 {
+        0x0000 => match op_code & 0x0FFF {
+            0x0C0..=0x0CF => Instruction::Scd { n },
+            0x0D0..=0x0DF => Instruction::Scu { n },
+            0x0E0 => Instruction::Cls,
+            0x0EE => Instruction::Ret,
+            0x0FB => Instruction::Scr,
+            0x0FC => Instruction::Scl,
+            0x0FD => Instruction::Exit,
+            0x0FE => Instruction::Low,
+            0x0FF => Instruction::High,
+            _ => return Err(DecodeError { op_code }),
+        },
+        0x1000 => Instruction::Jp { addr },
+        0x2000 => Instruction::Call { addr },
+        0x3000 => Instruction::SeByte { x, byte },
+        0x4000 => Instruction::SneByte { x, byte },
+        0x5000 => match n {
+            0x0 => Instruction::SeReg { x, y },
+            0x2 => Instruction::SaveRange { x, y },
+            0x3 => Instruction::LoadRange { x, y },
+            _ => return Err(DecodeError { op_code }),
+        },
+        0x6000 => Instruction::LdByte { x, byte },
+        0x7000 => Instruction::AddByte { x, byte },
+        0x8000 => match n {
+            0x0 => Instruction::LdReg { x, y },
+            0x1 => Instruction::Or { x, y },
+            0x2 => Instruction::And { x, y },
+            0x3 => Instruction::Xor { x, y },
+            0x4 => Instruction::AddReg { x, y },
+            0x5 => Instruction::Sub { x, y },
+            0x6 => Instruction::Shr { x, y },
+            0x7 => Instruction::Subn { x, y },
+            0xE => Instruction::Shl { x, y },
+            _ => return Err(DecodeError { op_code }),
+        },
+        0x9000 => match n {
+            0x0 => Instruction::SneReg { x, y },
+            _ => return Err(DecodeError { op_code }),
+        },
+        0xA000 => Instruction::LdI { addr },
+        0xB000 => Instruction::JpV0 { addr },
+        0xC000 => Instruction::Rnd { x, byte },
+        0xD000 => Instruction::Drw { x, y, n },
+        0xE000 => match byte {
+            0x9E => Instruction::Skp { x },
+            0xA1 => Instruction::Sknp { x },
+            _ => return Err(DecodeError { op_code }),
+        },
+        0xF000 => match byte {
+            0x00 if x == 0 => Instruction::LdLongI,
+            0x01 => Instruction::Plane { n: x },
+            0x02 if x == 0 => Instruction::Audio,
+            0x07 => Instruction::LdVxDt { x },
+            0x0A => Instruction::LdVxK { x },
+            0x15 => Instruction::LdDtVx { x },
+            0x18 => Instruction::LdStVx { x },
+            0x1E => Instruction::AddI { x },
+            0x29 => Instruction::LdF { x },
+            0x30 => Instruction::LdHf { x },
+            0x33 => Instruction::LdB { x },
+            0x3A => Instruction::Pitch { x },
+            0x55 => Instruction::LdIVx { x },
+            0x65 => Instruction::LdVxI { x },
+            0x75 => Instruction::LdRVx { x },
+            0x85 => Instruction::LdVxR { x },
+            _ => return Err(DecodeError { op_code }),
+        },
+        _ => return Err(DecodeError { op_code }),
+    };
+
+    Ok(instruction)
+}
+
+// same as decode, the instructions the platform doesn't have being invalid
+pub fn decode_for(platform: Platform, op_code: u16) -> Result<Instruction, DecodeError> {
+    decode(op_code).and_then(|instruction| match platform.supports(&instruction) {
+        true => Ok(instruction),
+        false => Err(DecodeError { op_code }),
+    })
+}
+
+pub fn encode(instruction: &Instruction) -> u16 {
+    let xy = |prefix: u16, x: u8, y: u8, suffix: u16| {
+        prefix | ((x as u16 & 0xF) << 8) | ((y as u16 & 0xF) << 4) | suffix
+    };
+    let xnn = |prefix: u16, x: u8, byte: u8| prefix | ((x as u16 & 0xF) << 8) | byte as u16;
+    let fx = |x: u8, suffix: u16| 0xF000 | ((x as u16 & 0xF) << 8) | suffix;
+
+    match *instruction {
+        Instruction::Scd { n } => 0x00C0 | (n as u16 & 0xF),
+        Instruction::Scu { n } => 0x00D0 | (n as u16 & 0xF),
+        Instruction::Cls => 0x00E0,
+        Instruction::Ret => 0x00EE,
+        Instruction::Scr => 0x00FB,
+        Instruction::Scl => 0x00FC,
+        Instruction::Exit => 0x00FD,
+        Instruction::Low => 0x00FE,
+        Instruction::High => 0x00FF,
+        Instruction::Jp { addr } => 0x1000 | (addr & 0x0FFF),
+        Instruction::Call { addr } => 0x2000 | (addr & 0x0FFF),
+        Instruction::SeByte { x, byte } => xnn(0x3000, x, byte),
+        Instruction::SneByte { x, byte } => xnn(0x4000, x, byte),
+        Instruction::SeReg { x, y } => xy(0x5000, x, y, 0x0),
+        Instruction::SaveRange { x, y } => xy(0x5000, x, y, 0x2),
+        Instruction::LoadRange { x, y } => xy(0x5000, x, y, 0x3),
+        Instruction::LdByte { x, byte } => xnn(0x6000, x, byte),
+        Instruction::AddByte { x, byte } => xnn(0x7000, x, byte),
+        Instruction::LdReg { x, y } => xy(0x8000, x, y, 0x0),
+        Instruction::Or { x, y } => xy(0x8000, x, y, 0x1),
+        Instruction::And { x, y } => xy(0x8000, x, y, 0x2),
+        Instruction::Xor { x, y } => xy(0x8000, x, y, 0x3),
+        Instruction::AddReg { x, y } => xy(0x8000, x, y, 0x4),
+        Instruction::Sub { x, y } => xy(0x8000, x, y, 0x5),
+        Instruction::Shr { x, y } => xy(0x8000, x, y, 0x6),
+        Instruction::Subn { x, y } => xy(0x8000, x, y, 0x7),
+        Instruction::Shl { x, y } => xy(0x8000, x, y, 0xE),
+        Instruction::SneReg { x, y } => xy(0x9000, x, y, 0x0),
+        Instruction::LdI { addr } => 0xA000 | (addr & 0x0FFF),
+        Instruction::JpV0 { addr } => 0xB000 | (addr & 0x0FFF),
+        Instruction::Rnd { x, byte } => xnn(0xC000, x, byte),
+        Instruction::Drw { x, y, n } => xy(0xD000, x, y, n as u16 & 0xF),
+        Instruction::Skp { x } => xnn(0xE000, x, 0x9E),
+        Instruction::Sknp { x } => xnn(0xE000, x, 0xA1),
+        Instruction::LdLongI => 0xF000,
+        Instruction::Plane { n } => fx(n, 0x01),
+        Instruction::Audio => 0xF002,
+        Instruction::LdVxDt { x } => fx(x, 0x07),
+        Instruction::LdVxK { x } => fx(x, 0x0A),
+        Instruction::LdDtVx { x } => fx(x, 0x15),
+        Instruction::LdStVx { x } => fx(x, 0x18),
+        Instruction::AddI { x } => fx(x, 0x1E),
+        Instruction::LdF { x } => fx(x, 0x29),
+        Instruction::LdHf { x } => fx(x, 0x30),
+        Instruction::LdB { x } => fx(x, 0x33),
+        Instruction::Pitch { x } => fx(x, 0x3A),
+        Instruction::LdIVx { x } => fx(x, 0x55),
+        Instruction::LdVxI { x } => fx(x, 0x65),
+        Instruction::LdRVx { x } => fx(x, 0x75),
+        Instruction::LdVxR { x } => fx(x, 0x85),
+    }
+}
+
+impl Instruction {
+    // size in memory, LD I, long addr being followed by its address
+    pub fn size(&self) -> u16 {
+        match self {
+            Instruction::LdLongI => 4,
+            _ => 2,
+        }
+    }
+
+    // address used as an operand by JP, CALL, LD I and JP V0
+    pub fn address(&self) -> Option<u16> {
+        match *self {
+            Instruction::Jp { addr }
+            | Instruction::Call { addr }
+            | Instruction::LdI { addr }
+            | Instruction::JpV0 { addr } => Some(addr),
+            _ => None,
+        }
+    }
+
+    // same as Display, but with the address operand formatted by the given function (to use labels for example)
+    pub fn format_with(&self, format_address: impl Fn(u16) -> String) -> String {
+        match *self {
+            Instruction::Scd { n } => format!("SCD {}", n),
+            Instruction::Scu { n } => format!("SCU {}", n),
+            Instruction::Cls => "CLS".to_string(),
+            Instruction::Ret => "RET".to_string(),
+            Instruction::Scr => "SCR".to_string(),
+            Instruction::Scl => "SCL".to_string(),
+            Instruction::Exit => "EXIT".to_string(),
+            Instruction::Low => "LOW".to_string(),
+            Instruction::High => "HIGH".to_string(),
+            Instruction::Jp { addr } => format!("JP {}", format_address(addr)),
+            Instruction::Call { addr } => format!("CALL {}", format_address(addr)),
+            Instruction::SeByte { x, byte } => format!("SE V{:X}, {:#04x}", x, byte),
+            Instruction::SneByte { x, byte } => format!("SNE V{:X}, {:#04x}", x, byte),
+            Instruction::SeReg { x, y } => format!("SE V{:X}, V{:X}", x, y),
+            Instruction::SaveRange { x, y } => format!("LD [I], V{:X}-V{:X}", x, y),
+            Instruction::LoadRange { x, y } => format!("LD V{:X}-V{:X}, [I]", x, y),
+            Instruction::LdByte { x, byte } => format!("LD V{:X}, {:#04x}", x, byte),
+            Instruction::AddByte { x, byte } => format!("ADD V{:X}, {:#04x}", x, byte),
+            Instruction::LdReg { x, y } => format!("LD V{:X}, V{:X}", x, y),
+            Instruction::Or { x, y } => format!("OR V{:X}, V{:X}", x, y),
+            Instruction::And { x, y } => format!("AND V{:X}, V{:X}", x, y),
+            Instruction::Xor { x, y } => format!("XOR V{:X}, V{:X}", x, y),
+            Instruction::AddReg { x, y } => format!("ADD V{:X}, V{:X}", x, y),
+            Instruction::Sub { x, y } => format!("SUB V{:X}, V{:X}", x, y),
+            Instruction::Shr { x, y } => format!("SHR V{:X}, V{:X}", x, y),
+            Instruction::Subn { x, y } => format!("SUBN V{:X}, V{:X}", x, y),
+            Instruction::Shl { x, y } => format!("SHL V{:X}, V{:X}", x, y),
+            Instruction::SneReg { x, y } => format!("SNE V{:X}, V{:X}", x, y),
+            Instruction::LdI { addr } => format!("LD I, {}", format_address(addr)),
+            Instruction::JpV0 { addr } => format!("JP V0, {}", format_address(addr)),
+            Instruction::Rnd { x, byte } => format!("RND V{:X}, {:#04x}", x, byte),
+            Instruction::Drw { x, y, n } => format!("DRW V{:X}, V{:X}, {}", x, y, n),
+            Instruction::Skp { x } => format!("SKP V{:X}", x),
+            Instruction::Sknp { x } => format!("SKNP V{:X}", x),
+            Instruction::LdLongI => "LD I, long".to_string(),
+            Instruction::Plane { n } => format!("PLANE {}", n),
+            Instruction::Audio => "AUDIO".to_string(),
+            Instruction::LdVxDt { x } => format!("LD V{:X}, DT", x),
+            Instruction::LdVxK { x } => format!("LD V{:X}, K", x),
+            Instruction::LdDtVx { x } => format!("LD DT, V{:X}", x),
+            Instruction::LdStVx { x } => format!("LD ST, V{:X}", x),
+            Instruction::AddI { x } => format!("ADD I, V{:X}", x),
+            Instruction::LdF { x } => format!("LD F, V{:X}", x),
+            Instruction::LdHf { x } => format!("LD HF, V{:X}", x),
+            Instruction::LdB { x } => format!("LD B, V{:X}", x),
+            Instruction::Pitch { x } => format!("PITCH V{:X}", x),
+            Instruction::LdIVx { x } => format!("LD [I], V{:X}", x),
+            Instruction::LdVxI { x } => format!("LD V{:X}, [I]", x),
+            Instruction::LdRVx { x } => format!("LD R, V{:X}", x),
+            Instruction::LdVxR { x } => format!("LD V{:X}, R", x),
+        }
+    }
+}
+
+impl fmt::Display for Instruction {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "{}", self.format_with(|addr| format!("{:#05x}", addr)))
+    }
+}
@@ -3,10 +3,14 @@ pub trait Controller {
 }
 
 pub trait Renderer {
-    fn render(&mut self, screen: &Vec<u8>);
+    // screen holds one byte per pixel, row by row, and is 64x32 or 128x64 depending on the resolution
+    // each pixel is a plane index from 0 to 3 (bit 0 for plane 1, bit 1 for plane 2), only 0 and 1 outside of XO-CHIP
+    fn render(&mut self, screen: &[u8], width: usize, height: usize);
 }
 
 pub trait Synthetizer {
     fn play(&mut self);
     fn stop(&mut self);
+    // XO-CHIP 1-bit audio pattern, played at 4000 * 2 ^ ((pitch - 64) / 48) samples per second
+    fn set_pattern(&mut self, pattern: &[u8; 16], pitch: u8);
 }
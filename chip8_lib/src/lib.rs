@@ -1,5 +1,24 @@
+#[cfg(feature = "clap")]
+pub mod cli;
+pub mod clock;
+pub mod config;
 mod cpu;
+pub mod debug;
+pub mod disassembler;
 pub mod emulator;
+pub mod error;
+pub mod headless;
+pub mod instruction;
 pub mod interfaces;
-mod mmu;
-mod registers;
+pub mod mmu;
+pub mod movie;
+pub mod platform;
+pub mod quirks;
+pub mod random;
+pub mod registers;
+pub mod rewind;
+mod state;
+#[cfg(feature = "test-util")]
+pub mod test_util;
+pub mod timing;
+pub mod trace;
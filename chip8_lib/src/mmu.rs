@@ -1,24 +1,71 @@
+use crate::debug::Access;
+use crate::error::StateError;
+use crate::state::{StateReader, StateWriter};
+use std::cell::RefCell;
+
+// SUPER-CHIP 8x10 font for the hexadecimal digits, one row per digit
+const BIG_FONT: [u8; 160] = [
+    0xFF, 0xFF, 0xC3, 0xC3, 0xC3, 0xC3, 0xC3, 0xC3, 0xFF, 0xFF, // 0
+    0x18, 0x78, 0x78, 0x18, 0x18, 0x18, 0x18, 0x18, 0xFF, 0xFF, // 1
+    0xFF, 0xFF, 0x03, 0x03, 0xFF, 0xFF, 0xC0, 0xC0, 0xFF, 0xFF, // 2
+    0xFF, 0xFF, 0x03, 0x03, 0xFF, 0xFF, 0x03, 0x03, 0xFF, 0xFF, // 3
+    0xC3, 0xC3, 0xC3, 0xC3, 0xFF, 0xFF, 0x03, 0x03, 0x03, 0x03, // 4
+    0xFF, 0xFF, 0xC0, 0xC0, 0xFF, 0xFF, 0x03, 0x03, 0xFF, 0xFF, // 5
+    0xFF, 0xFF, 0xC0, 0xC0, 0xFF, 0xFF, 0xC3, 0xC3, 0xFF, 0xFF, // 6
+    0xFF, 0xFF, 0x03, 0x03, 0x06, 0x0C, 0x18, 0x18, 0x18, 0x18, // 7
+    0xFF, 0xFF, 0xC3, 0xC3, 0xFF, 0xFF, 0xC3, 0xC3, 0xFF, 0xFF, // 8
+    0xFF, 0xFF, 0xC3, 0xC3, 0xFF, 0xFF, 0x03, 0x03, 0xFF, 0xFF, // 9
+    0x7E, 0xFF, 0xC3, 0xC3, 0xC3, 0xFF, 0xFF, 0xC3, 0xC3, 0xC3, // A
+    0xFC, 0xFC, 0xC3, 0xC3, 0xFC, 0xFC, 0xC3, 0xC3, 0xFC, 0xFC, // B
+    0x3C, 0xFF, 0xC3, 0xC0, 0xC0, 0xC0, 0xC0, 0xC3, 0xFF, 0x3C, // C
+    0xFC, 0xFE, 0xC3, 0xC3, 0xC3, 0xC3, 0xC3, 0xC3, 0xFE, 0xFC, // D
+    0xFF, 0xFF, 0xC0, 0xC0, 0xFF, 0xFF, 0xC0, 0xC0, 0xFF, 0xFF, // E
+    0xFF, 0xFF, 0xC0, 0xC0, 0xFF, 0xFF, 0xC0, 0xC0, 0xC0, 0xC0, // F
+];
+
 pub struct Mmu {
     key_pressed: Vec<bool>,
+    // keys held during the previous frame, to tell the keys pressed since from the ones held
+    previous_keys: Vec<bool>,
 
+    // 4 KiB, or 64 KiB for XO-CHIP
     memory: Vec<u8>,
+    address_mask: usize,
+
+    // 64x32 in low resolution, 128x64 in high resolution
+    // each pixel holds one bit per plane, bit 0 being plane 1 and bit 1 plane 2
     screen: Vec<u8>,
+    screen_width: usize,
+    screen_height: usize,
+
+    // XO-CHIP planes affected by drawing, clearing and scrolling
+    selected_planes: u8,
+
+    // XO-CHIP audio pattern buffer (128 1-bit samples) and pitch register
+    audio_pattern: Option<[u8; 16]>,
+    pitch: u8,
+
+    // SUPER-CHIP persistent user flags
+    rpl_flags: Vec<u8>,
 
     delay_timer: u8,
     sound_timer: u8,
 
     stack: Vec<u16>,
     stack_pointer: usize,
+
+    // reads and writes done by the instructions, kept only while watchpoints are set
+    recording_accesses: bool,
+    accesses: RefCell<Vec<(u16, Access, u8)>>,
 }
 
 impl Mmu {
-    pub fn new(rom: &Vec<u8>) -> Self {
-        let mut temp: Vec<u8> = vec![0; 4096];
+    // memory_size has to be a power of 2
+    pub fn new(rom: &[u8], memory_size: usize) -> Self {
+        let mut temp: Vec<u8> = vec![0; memory_size];
 
         // copy rom content into memory (starting at 0x200)
-        for index in 0..rom.len() {
-            temp[0x200 + index] = rom[index];
-        }
+        temp[0x200..0x200 + rom.len()].copy_from_slice(rom);
 
         // Setup font in memory - conventionally stored in [0x50 - 0x9F]
         // 0
@@ -118,37 +165,167 @@ impl Mmu {
         temp[0x9E] = 0x80;
         temp[0x9F] = 0x80;
 
+        // Setup big font in memory, right after the regular one - [0xA0 - 0x13F]
+        temp[0xA0..0xA0 + BIG_FONT.len()].copy_from_slice(&BIG_FONT);
+
         Self {
             key_pressed: vec![false; 16],
+            previous_keys: vec![false; 16],
             memory: temp.clone(),
+            address_mask: memory_size - 1,
             screen: vec![0; 32 * 64],
+            screen_width: 64,
+            screen_height: 32,
+            selected_planes: 1,
+            audio_pattern: None,
+            pitch: 64,
+            rpl_flags: vec![0; 16],
             delay_timer: 0,
             sound_timer: 0,
             stack: vec![0; 16],
             stack_pointer: 0,
+            recording_accesses: false,
+            accesses: RefCell::new(Vec::new()),
         }
     }
 
+    pub fn get_address_mask(&self) -> usize {
+        self.address_mask
+    }
+
     pub fn get_byte(&self, address: usize) -> u8 {
-        self.memory[address & 0x0FFF]
+        let value = self.peek_byte(address);
+
+        self.record_access(address, Access::Read, value);
+
+        value
     }
 
     pub fn set_byte(&mut self, address: usize, value: u8) {
-        self.memory[address & 0x0FFF] = value;
+        self.memory[address & self.address_mask] = value;
+
+        self.record_access(address, Access::Write, value);
+    }
+
+    // reads without being seen by the watchpoints
+    pub fn peek_byte(&self, address: usize) -> u8 {
+        self.memory[address & self.address_mask]
+    }
+
+    // writes without being seen by the watchpoints
+    pub fn poke_byte(&mut self, address: usize, value: u8) {
+        self.memory[address & self.address_mask] = value;
+    }
+
+    // the whole address space, without being seen by the watchpoints
+    pub fn get_memory(&self) -> &[u8] {
+        &self.memory
+    }
+
+    pub fn set_recording_accesses(&mut self, value: bool) {
+        self.recording_accesses = value;
+        self.accesses.borrow_mut().clear();
+    }
+
+    // accesses since the last call, as address, access and value read or written
+    pub fn take_accesses(&self) -> Vec<(u16, Access, u8)> {
+        self.accesses.take()
+    }
+
+    fn record_access(&self, address: usize, access: Access, value: u8) {
+        if self.recording_accesses {
+            let address = (address & self.address_mask) as u16;
+
+            self.accesses.borrow_mut().push((address, access, value));
+        }
     }
 
     pub fn get_short(&self, address: usize) -> u16 {
-        let masked_address: usize = address & 0x0FFF;
+        let masked_address: usize = address & self.address_mask;
 
-        ((self.memory[masked_address] as u16) << 8) | self.memory[masked_address + 1] as u16
+        // the second byte wraps around too, when the first is the last one of memory
+        ((self.memory[masked_address] as u16) << 8)
+            | self.memory[(masked_address + 1) & self.address_mask] as u16
     }
 
     pub fn get_screen(&self) -> &Vec<u8> {
         &self.screen
     }
 
-    pub fn set_screen(&mut self, new_screen: Vec<u8>) {
-        self.screen = new_screen;
+    pub fn get_screen_width(&self) -> usize {
+        self.screen_width
+    }
+
+    pub fn get_screen_height(&self) -> usize {
+        self.screen_height
+    }
+
+    // switching resolution clears the screen
+    pub fn set_high_resolution(&mut self, value: bool) {
+        if value {
+            self.screen_width = 128;
+            self.screen_height = 64;
+        } else {
+            self.screen_width = 64;
+            self.screen_height = 32;
+        }
+
+        self.screen = vec![0; self.screen_width * self.screen_height];
+    }
+
+    // clears the selected planes only
+    pub fn clear_screen(&mut self) {
+        let planes = self.selected_planes;
+
+        self.screen.iter_mut().for_each(|pixel| *pixel &= !planes);
+    }
+
+    // moves the selected planes by the given amount of pixels, positive being right and down
+    pub fn scroll(&mut self, columns: isize, lines: isize) {
+        let width = self.screen_width as isize;
+        let height = self.screen_height as isize;
+        let planes = self.selected_planes;
+        let previous = self.screen.clone();
+
+        for y in 0..height {
+            for x in 0..width {
+                let source_x = x - columns;
+                let source_y = y - lines;
+
+                let moved = if (0..width).contains(&source_x) && (0..height).contains(&source_y) {
+                    previous[(source_x + source_y * width) as usize] & planes
+                } else {
+                    0
+                };
+
+                let index = (x + y * width) as usize;
+                self.screen[index] = (previous[index] & !planes) | moved;
+            }
+        }
+    }
+
+    pub fn get_selected_planes(&self) -> u8 {
+        self.selected_planes
+    }
+
+    pub fn set_selected_planes(&mut self, planes: u8) {
+        self.selected_planes = planes & 0x3;
+    }
+
+    pub fn get_audio_pattern(&self) -> Option<&[u8; 16]> {
+        self.audio_pattern.as_ref()
+    }
+
+    pub fn set_audio_pattern(&mut self, pattern: [u8; 16]) {
+        self.audio_pattern = Some(pattern);
+    }
+
+    pub fn get_pitch(&self) -> u8 {
+        self.pitch
+    }
+
+    pub fn set_pitch(&mut self, value: u8) {
+        self.pitch = value;
     }
 
     pub fn get_screen_at(&self, index: usize) -> u8 {
@@ -162,16 +339,44 @@ impl Mmu {
     pub fn push_on_stack(&mut self, value: u16) {
         if self.stack_pointer < 16 {
             self.stack[self.stack_pointer] = value;
-            self.stack_pointer = self.stack_pointer + 1;
+            self.stack_pointer += 1;
+        }
+    }
+
+    pub fn is_stack_full(&self) -> bool {
+        self.stack_pointer == self.stack.len()
+    }
+
+    // makes room for a call on a full stack
+    pub fn discard_oldest_return_address(&mut self) {
+        if self.stack_pointer > 0 {
+            self.stack.rotate_left(1);
+            self.stack_pointer -= 1;
+        }
+    }
+
+    // lets a return on an empty stack use the last slot
+    pub fn wrap_stack_pointer(&mut self) {
+        if self.stack_pointer == 0 {
+            self.stack_pointer = self.stack.len();
         }
     }
 
+    pub fn get_stack_pointer(&self) -> usize {
+        self.stack_pointer
+    }
+
+    // return addresses, the last one being on top
+    pub fn get_stack(&self) -> &[u16] {
+        &self.stack[..self.stack_pointer]
+    }
+
     pub fn pop_stack(&mut self) -> u16 {
         let mut value: u16 = 0;
 
         if self.stack_pointer > 0 {
             value = self.stack[self.stack_pointer - 1];
-            self.stack_pointer = self.stack_pointer - 1;
+            self.stack_pointer -= 1;
         }
 
         value
@@ -205,6 +410,14 @@ impl Mmu {
         }
     }
 
+    pub fn get_rpl_flag(&self, index: usize) -> u8 {
+        self.rpl_flags[index & 0x0F]
+    }
+
+    pub fn set_rpl_flag(&mut self, index: usize, value: u8) {
+        self.rpl_flags[index & 0x0F] = value;
+    }
+
     pub fn is_key_down(&self, index: usize) -> bool {
         self.key_pressed[index & 0x0F]
     }
@@ -212,4 +425,115 @@ impl Mmu {
     pub fn set_key_down(&mut self, index: usize, value: bool) {
         self.key_pressed[index & 0x0F] = value;
     }
+
+    // held now but not during the previous frame
+    pub fn is_key_newly_down(&self, index: usize) -> bool {
+        self.key_pressed[index & 0x0F] && !self.previous_keys[index & 0x0F]
+    }
+
+    // to call when a frame starts, before setting its keys
+    pub fn latch_keys(&mut self) {
+        self.previous_keys.clone_from(&self.key_pressed);
+    }
+
+    pub fn write_state(&self, writer: &mut StateWriter) {
+        writer.write_u32(self.memory.len() as u32);
+        writer.write_bytes(&self.memory);
+
+        writer.write_u16(self.screen_width as u16);
+        writer.write_u16(self.screen_height as u16);
+        writer.write_bytes(&self.screen);
+        writer.write_u8(self.selected_planes);
+
+        match &self.audio_pattern {
+            Some(pattern) => {
+                writer.write_bool(true);
+                writer.write_bytes(pattern);
+            }
+            None => writer.write_bool(false),
+        }
+        writer.write_u8(self.pitch);
+
+        writer.write_bytes(&self.rpl_flags);
+        writer.write_u8(self.delay_timer);
+        writer.write_u8(self.sound_timer);
+
+        self.stack.iter().for_each(|value| writer.write_u16(*value));
+        writer.write_u8(self.stack_pointer as u8);
+
+        self.key_pressed
+            .iter()
+            .chain(&self.previous_keys)
+            .for_each(|pressed| writer.write_bool(*pressed));
+    }
+
+    pub fn read_state(reader: &mut StateReader) -> Result<Self, StateError> {
+        let memory_size = reader.read_u32()? as usize;
+
+        if !memory_size.is_power_of_two() || memory_size < 0x200 {
+            return Err(StateError::Corrupted);
+        }
+
+        let memory = reader.read_bytes(memory_size)?.to_vec();
+
+        let screen_width = reader.read_u16()? as usize;
+        let screen_height = reader.read_u16()? as usize;
+
+        if !matches!((screen_width, screen_height), (64, 32) | (128, 64)) {
+            return Err(StateError::Corrupted);
+        }
+
+        let screen = reader.read_bytes(screen_width * screen_height)?.to_vec();
+        let selected_planes = reader.read_u8()? & 0x3;
+
+        let audio_pattern = if reader.read_bool()? {
+            let mut pattern = [0; 16];
+            pattern.copy_from_slice(reader.read_bytes(16)?);
+
+            Some(pattern)
+        } else {
+            None
+        };
+        let pitch = reader.read_u8()?;
+
+        let rpl_flags = reader.read_bytes(16)?.to_vec();
+        let delay_timer = reader.read_u8()?;
+        let sound_timer = reader.read_u8()?;
+
+        let stack = (0..16)
+            .map(|_| reader.read_u16())
+            .collect::<Result<Vec<u16>, StateError>>()?;
+        let stack_pointer = reader.read_u8()? as usize;
+
+        if stack_pointer > 16 {
+            return Err(StateError::Corrupted);
+        }
+
+        let key_pressed = (0..16)
+            .map(|_| reader.read_bool())
+            .collect::<Result<Vec<bool>, StateError>>()?;
+        let previous_keys = (0..16)
+            .map(|_| reader.read_bool())
+            .collect::<Result<Vec<bool>, StateError>>()?;
+
+        Ok(Self {
+            key_pressed,
+            previous_keys,
+            memory,
+            address_mask: memory_size - 1,
+            screen,
+            screen_width,
+            screen_height,
+            selected_planes,
+            audio_pattern,
+            pitch,
+            rpl_flags,
+            delay_timer,
+            sound_timer,
+            stack,
+            stack_pointer,
+            recording_accesses: false,
+            accesses: RefCell::new(Vec::new()),
+        })
+    }
 }
@@ -0,0 +1,244 @@
+use crate::config::{Config, FaultPolicies, FaultPolicy};
+use crate::emulator::Emulator;
+use crate::error::MovieError;
+use crate::interfaces::Controller;
+use crate::platform::Platform;
+use crate::quirks::{MemoryIncrement, Quirks};
+use crate::random::Generator;
+use crate::state::{rom_hash, StateReader, StateWriter};
+use crate::timing::Timing;
+
+// Movie layout, all values big endian:
+// header: magic, version, rom hash
+// config: platform, quirks (a byte each), seed, random generator, cpu speed, timing, fault policies (a byte each)
+// frames: count, then the keys held during each frame, bit n for key n
+const MAGIC: [u8; 4] = *b"C8MV";
+const VERSION: u8 = 6;
+
+// Keys held during every frame of a run, which is replayed exactly from the same rom, config, seed and generator
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct Movie {
+    config: Config,
+    rom_hash: u32,
+    frames: Vec<u16>,
+}
+
+impl Movie {
+    // recording has to start with the emulator, before its first frame
+    pub fn new(emulator: &Emulator) -> Self {
+        Self {
+            config: *emulator.config(),
+            rom_hash: emulator.get_rom_hash(),
+            frames: Vec::new(),
+        }
+    }
+
+    // keys of the next frame, to call once per frame before running it
+    pub fn record_frame(&mut self, controller: &impl Controller) {
+        let keys = (0..16)
+            .filter(|key| controller.is_key_down(*key))
+            .fold(0, |keys, key| keys | 1 << key);
+
+        self.frames.push(keys);
+    }
+
+    // configuration to create the emulator playing the movie with, seed included
+    pub fn config(&self) -> &Config {
+        &self.config
+    }
+
+    pub fn frames(&self) -> &[u16] {
+        &self.frames
+    }
+
+    pub fn check_rom(&self, rom: &[u8]) -> Result<(), MovieError> {
+        if rom_hash(rom) == self.rom_hash {
+            Ok(())
+        } else {
+            Err(MovieError::RomMismatch)
+        }
+    }
+
+    pub fn to_bytes(&self) -> Vec<u8> {
+        let mut writer = StateWriter::new();
+        let quirks = &self.config.quirks;
+
+        writer.write_bytes(&MAGIC);
+        writer.write_u8(VERSION);
+        writer.write_u32(self.rom_hash);
+
+        writer.write_u8(match self.config.platform {
+            Platform::Chip8 => 0,
+            Platform::SuperChip => 1,
+            Platform::XoChip => 2,
+        });
+
+        writer.write_bool(quirks.vf_reset);
+        writer.write_u8(match quirks.memory_increment {
+            MemoryIncrement::None => 0,
+            MemoryIncrement::X => 1,
+            MemoryIncrement::XPlusOne => 2,
+        });
+
+        for quirk in [
+            quirks.display_wait,
+            quirks.clipping,
+            quirks.shifting,
+            quirks.jumping,
+        ] {
+            writer.write_bool(quirk);
+        }
+
+        writer.write_u64(self.config.seed.unwrap_or_default());
+        writer.write_u8(match self.config.generator {
+            Generator::Seeded => 0,
+            Generator::PageSum => 1,
+        });
+        writer.write_u32(self.config.cpu_hz);
+        writer.write_u8(match self.config.timing {
+            Timing::Instructions => 0,
+            Timing::CosmacVip => 1,
+        });
+
+        let faults = &self.config.faults;
+        for policy in [
+            faults.stack_overflow,
+            faults.stack_underflow,
+            faults.invalid_opcode,
+            faults.memory_out_of_range,
+        ] {
+            writer.write_u8(match policy {
+                FaultPolicy::Halt => 0,
+                FaultPolicy::Ignore => 1,
+                FaultPolicy::Log => 2,
+                FaultPolicy::Wrap => 3,
+            });
+        }
+
+        writer.write_u32(self.frames.len() as u32);
+        for keys in &self.frames {
+            writer.write_u16(*keys);
+        }
+
+        writer.into_bytes()
+    }
+
+    pub fn from_bytes(bytes: &[u8]) -> Result<Self, MovieError> {
+        let mut reader = StateReader::new(bytes);
+
+        if reader.read_bytes(MAGIC.len())? != MAGIC {
+            return Err(MovieError::InvalidMagic);
+        }
+
+        let version = reader.read_u8()?;
+        if version != VERSION {
+            return Err(MovieError::UnsupportedVersion(version));
+        }
+
+        let rom_hash = reader.read_u32()?;
+
+        let platform = match reader.read_u8()? {
+            0 => Platform::Chip8,
+            1 => Platform::SuperChip,
+            2 => Platform::XoChip,
+            _ => return Err(MovieError::Corrupted),
+        };
+
+        let quirks = Quirks {
+            vf_reset: reader.read_bool()?,
+            memory_increment: match reader.read_u8()? {
+                0 => MemoryIncrement::None,
+                1 => MemoryIncrement::X,
+                2 => MemoryIncrement::XPlusOne,
+                _ => return Err(MovieError::Corrupted),
+            },
+            display_wait: reader.read_bool()?,
+            clipping: reader.read_bool()?,
+            shifting: reader.read_bool()?,
+            jumping: reader.read_bool()?,
+        };
+
+        let config = Config::new(platform)
+            .with_quirks(quirks)
+            .with_seed(reader.read_u64()?)
+            .with_generator(match reader.read_u8()? {
+                0 => Generator::Seeded,
+                1 => Generator::PageSum,
+                _ => return Err(MovieError::Corrupted),
+            })
+            .with_cpu_hz(reader.read_u32()?)
+            .with_timing(match reader.read_u8()? {
+                0 => Timing::Instructions,
+                1 => Timing::CosmacVip,
+                _ => return Err(MovieError::Corrupted),
+            })
+            .with_faults(FaultPolicies {
+                stack_overflow: read_policy(&mut reader)?,
+                stack_underflow: read_policy(&mut reader)?,
+                invalid_opcode: read_policy(&mut reader)?,
+                memory_out_of_range: read_policy(&mut reader)?,
+            });
+
+        if config.cpu_hz == 0 {
+            return Err(MovieError::Corrupted);
+        }
+
+        let frame_count = reader.read_u32()? as usize;
+        let frames = (0..frame_count)
+            .map(|_| reader.read_u16())
+            .collect::<Result<Vec<u16>, _>>()?;
+
+        reader.finish()?;
+
+        Ok(Self {
+            config,
+            rom_hash,
+            frames,
+        })
+    }
+}
+
+// Controller replaying the keys of a movie, frame after frame
+pub struct Playback {
+    movie: Movie,
+    frame: usize,
+}
+
+impl Playback {
+    pub fn new(movie: Movie) -> Self {
+        Self { movie, frame: 0 }
+    }
+
+    // to call once per frame after running it
+    pub fn next_frame(&mut self) {
+        self.frame += 1;
+    }
+
+    pub fn get_frame(&self) -> usize {
+        self.frame
+    }
+
+    // no key is held once every frame has been played
+    pub fn is_finished(&self) -> bool {
+        self.frame >= self.movie.frames.len()
+    }
+}
+
+impl Controller for Playback {
+    fn is_key_down(&self, key_index: usize) -> bool {
+        self.movie
+            .frames
+            .get(self.frame)
+            .is_some_and(|keys| keys >> (key_index & 0xF) & 1 == 1)
+    }
+}
+
+fn read_policy(reader: &mut StateReader) -> Result<FaultPolicy, MovieError> {
+    match reader.read_u8()? {
+        0 => Ok(FaultPolicy::Halt),
+        1 => Ok(FaultPolicy::Ignore),
+        2 => Ok(FaultPolicy::Log),
+        3 => Ok(FaultPolicy::Wrap),
+        _ => Err(MovieError::Corrupted),
+    }
+}
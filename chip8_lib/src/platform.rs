@@ -0,0 +1,55 @@
+use crate::instruction::Instruction;
+use crate::quirks::Quirks;
+
+// Interpreter the emulator behaves like, which decides the memory size and the default quirks
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum Platform {
+    // COSMAC VIP CHIP-8, 4 KiB of memory
+    #[default]
+    Chip8,
+    // HP-48 SUPER-CHIP 1.1, 4 KiB of memory
+    SuperChip,
+    // Octo XO-CHIP, 64 KiB of memory and two bitplanes
+    XoChip,
+}
+
+impl Platform {
+    pub fn memory_size(&self) -> usize {
+        match self {
+            Platform::Chip8 | Platform::SuperChip => 0x1000,
+            Platform::XoChip => 0x10000,
+        }
+    }
+
+    pub fn default_quirks(&self) -> Quirks {
+        match self {
+            Platform::Chip8 => Quirks::cosmac_vip(),
+            Platform::SuperChip => Quirks::super_chip(),
+            Platform::XoChip => Quirks::xo_chip(),
+        }
+    }
+
+    // SUPER-CHIP adds the scrolling, resolution, exit, big font and rpl flag instructions to CHIP-8,
+    // XO-CHIP everything else
+    pub fn supports(&self, instruction: &Instruction) -> bool {
+        match instruction {
+            Instruction::Scd { .. }
+            | Instruction::Scr
+            | Instruction::Scl
+            | Instruction::Exit
+            | Instruction::Low
+            | Instruction::High
+            | Instruction::LdHf { .. }
+            | Instruction::LdRVx { .. }
+            | Instruction::LdVxR { .. } => *self != Platform::Chip8,
+            Instruction::Scu { .. }
+            | Instruction::SaveRange { .. }
+            | Instruction::LoadRange { .. }
+            | Instruction::LdLongI
+            | Instruction::Plane { .. }
+            | Instruction::Audio
+            | Instruction::Pitch { .. } => *self == Platform::XoChip,
+            _ => true,
+        }
+    }
+}
@@ -0,0 +1,84 @@
+// Behaviour of the ambiguous opcodes, which differs between the interpreters CHIP-8 ROMs were written for.
+// See https://github.com/Timendus/chip8-test-suite#quirks-test for a description of each quirk.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct Quirks {
+    // 8xy1, 8xy2 and 8xy3 reset VF to 0
+    pub vf_reset: bool,
+    // where fx55 and fx65 leave I
+    pub memory_increment: MemoryIncrement,
+    // dxyn waits for the vertical blank, limiting drawing to one sprite per frame
+    pub display_wait: bool,
+    // sprites are clipped at the edges of the screen instead of wrapping around
+    pub clipping: bool,
+    // 8xy6 and 8xye shift Vx in place instead of shifting Vy into Vx
+    pub shifting: bool,
+    // bnnn jumps to xnn + Vx instead of nnn + V0
+    pub jumping: bool,
+}
+
+// Where fx55 and fx65 leave I, once the registers are read or written
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum MemoryIncrement {
+    // I is left unchanged
+    None,
+    // I points at the last register, I += X
+    X,
+    // I points right after the last register, I += X + 1
+    XPlusOne,
+}
+
+impl Quirks {
+    // original interpreter of the RCA COSMAC VIP
+    pub fn cosmac_vip() -> Self {
+        Self {
+            vf_reset: true,
+            memory_increment: MemoryIncrement::XPlusOne,
+            display_wait: true,
+            clipping: true,
+            shifting: false,
+            jumping: false,
+        }
+    }
+
+    // CHIP-48 interpreter of the HP-48 calculators
+    pub fn chip48() -> Self {
+        Self {
+            vf_reset: false,
+            memory_increment: MemoryIncrement::X,
+            display_wait: false,
+            clipping: true,
+            shifting: true,
+            jumping: true,
+        }
+    }
+
+    // SUPER-CHIP 1.1 interpreter of the HP-48 calculators
+    pub fn super_chip() -> Self {
+        Self {
+            vf_reset: false,
+            memory_increment: MemoryIncrement::None,
+            display_wait: false,
+            clipping: true,
+            shifting: true,
+            jumping: true,
+        }
+    }
+
+    // XO-CHIP as implemented by Octo
+    pub fn xo_chip() -> Self {
+        Self {
+            vf_reset: false,
+            memory_increment: MemoryIncrement::XPlusOne,
+            display_wait: false,
+            clipping: false,
+            shifting: false,
+            jumping: false,
+        }
+    }
+}
+
+impl Default for Quirks {
+    fn default() -> Self {
+        Self::cosmac_vip()
+    }
+}
@@ -0,0 +1,147 @@
+use std::collections::hash_map::RandomState;
+use std::hash::{BuildHasher, Hasher};
+
+// Source of the random bytes of cxnn
+pub trait RandomSource: Send {
+    // memory is the whole address space, for generators reading it like the COSMAC VIP one
+    fn next_byte(&mut self, memory: &[u8]) -> u8;
+
+    // state kept in save states, so that a loaded state draws the same numbers again
+    fn get_state(&self) -> u64;
+
+    fn set_state(&mut self, state: u64);
+}
+
+// seed for runs that don't need to be reproduced, from the random keys of the standard library
+pub fn random_seed() -> u64 {
+    RandomState::new().build_hasher().finish()
+}
+
+// xorshift64* pseudo-random generator, the same seed giving the same bytes
+pub struct SeededRandom {
+    state: u64,
+}
+
+impl SeededRandom {
+    pub fn new(seed: u64) -> Self {
+        // splitmix64 of the seed, as xorshift needs a state other than 0 and close seeds would start alike
+        let mut state = seed.wrapping_add(0x9E3779B97F4A7C15);
+        state = (state ^ (state >> 30)).wrapping_mul(0xBF58476D1CE4E5B9);
+        state = (state ^ (state >> 27)).wrapping_mul(0x94D049BB133111EB);
+        state ^= state >> 31;
+
+        Self {
+            state: state.max(1),
+        }
+    }
+}
+
+impl RandomSource for SeededRandom {
+    fn next_byte(&mut self, _memory: &[u8]) -> u8 {
+        self.state ^= self.state >> 12;
+        self.state ^= self.state << 25;
+        self.state ^= self.state >> 27;
+
+        (self.state.wrapping_mul(0x2545F4914F6CDD1D) >> 56) as u8
+    }
+
+    fn get_state(&self) -> u64 {
+        self.state
+    }
+
+    // 0 would stick the generator to 0
+    fn set_state(&mut self, state: u64) {
+        self.state = state.max(1);
+    }
+}
+
+// Returns the given bytes in a loop, to know what cxnn will draw in tests
+pub struct FixedSequence {
+    bytes: Vec<u8>,
+    position: usize,
+}
+
+impl FixedSequence {
+    // an empty sequence only returns 0
+    pub fn new(bytes: impl Into<Vec<u8>>) -> Self {
+        Self {
+            bytes: bytes.into(),
+            position: 0,
+        }
+    }
+}
+
+impl RandomSource for FixedSequence {
+    fn next_byte(&mut self, _memory: &[u8]) -> u8 {
+        if self.bytes.is_empty() {
+            return 0;
+        }
+
+        let byte = self.bytes[self.position];
+        self.position = (self.position + 1) % self.bytes.len();
+
+        byte
+    }
+
+    fn get_state(&self) -> u64 {
+        self.position as u64
+    }
+
+    fn set_state(&mut self, state: u64) {
+        self.position = state as usize % self.bytes.len().max(1);
+    }
+}
+
+// Sums the bytes of the first page of memory walked by a counter, the page holding the fonts. This is not
+// the COSMAC VIP generator, which sums bytes of the interpreter code that isn't in memory here, so its
+// numbers can't be reproduced: these are only poor and repeat after a while too
+pub struct PageSum {
+    counter: u8,
+    sum: u8,
+}
+
+impl PageSum {
+    pub fn new(seed: u16) -> Self {
+        let [counter, sum] = seed.to_le_bytes();
+
+        Self { counter, sum }
+    }
+}
+
+impl RandomSource for PageSum {
+    fn next_byte(&mut self, memory: &[u8]) -> u8 {
+        self.counter = self.counter.wrapping_add(1);
+        self.sum = self
+            .sum
+            .wrapping_add(memory[self.counter as usize])
+            .wrapping_add(self.counter);
+
+        self.sum
+    }
+
+    fn get_state(&self) -> u64 {
+        u16::from_le_bytes([self.counter, self.sum]) as u64
+    }
+
+    fn set_state(&mut self, state: u64) {
+        [self.counter, self.sum] = (state as u16).to_le_bytes();
+    }
+}
+
+// Generator picked by a config, seeded from it
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum Generator {
+    #[default]
+    Seeded,
+    // only the low 16 bits of the seed are used
+    PageSum,
+}
+
+impl Generator {
+    pub fn source(&self, seed: u64) -> Box<dyn RandomSource> {
+        match self {
+            Generator::Seeded => Box::new(SeededRandom::new(seed)),
+            Generator::PageSum => Box::new(PageSum::new(seed as u16)),
+        }
+    }
+}
@@ -1,3 +1,6 @@
+use crate::error::StateError;
+use crate::state::{StateReader, StateWriter};
+
 pub struct Registers {
     vx: Vec<u8>,
 
@@ -38,4 +41,24 @@ impl Registers {
     pub fn set_pc(&mut self, value: u16) {
         self.pc = value;
     }
+
+    pub fn write_state(&self, writer: &mut StateWriter) {
+        writer.write_bytes(&self.vx);
+        writer.write_u16(self.i);
+        writer.write_u16(self.pc);
+    }
+
+    pub fn read_state(reader: &mut StateReader) -> Result<Self, StateError> {
+        Ok(Self {
+            vx: reader.read_bytes(16)?.to_vec(),
+            i: reader.read_u16()?,
+            pc: reader.read_u16()?,
+        })
+    }
+}
+
+impl Default for Registers {
+    fn default() -> Self {
+        Self::new()
+    }
 }
@@ -0,0 +1,193 @@
+use crate::emulator::Emulator;
+use std::collections::VecDeque;
+
+// deltas start with the kind of change they hold
+const FULL: u8 = 0;
+const RUNS: u8 = 1;
+
+// a run header is an u32 offset and an u16 length, runs closer than that are merged
+const RUN_HEADER_SIZE: usize = 6;
+
+// Recent emulator states, to step backward in time
+// The newest snapshot is kept whole, older ones as deltas turning a snapshot into the previous one,
+// so the oldest snapshots can be dropped once the memory budget is exceeded
+pub struct Rewind {
+    // frames between two snapshots
+    interval: u32,
+    // bytes the snapshots are allowed to use
+    budget: usize,
+    frames_since_snapshot: u32,
+    latest: Option<Vec<u8>>,
+    deltas: VecDeque<Vec<u8>>,
+    memory_used: usize,
+}
+
+impl Rewind {
+    pub fn new(interval: u32, budget: usize) -> Self {
+        Self {
+            interval: interval.max(1),
+            budget,
+            frames_since_snapshot: 0,
+            latest: None,
+            deltas: VecDeque::new(),
+            memory_used: 0,
+        }
+    }
+
+    // to be called after every frame, takes a snapshot every interval frames
+    pub fn record(&mut self, emulator: &Emulator) {
+        self.frames_since_snapshot += 1;
+
+        if self.frames_since_snapshot < self.interval {
+            return;
+        }
+
+        self.frames_since_snapshot = 0;
+
+        let state = emulator.save_state();
+
+        if let Some(previous) = self.latest.take() {
+            let delta = diff(&state, &previous);
+
+            self.memory_used += delta.len();
+            self.memory_used -= previous.len();
+            self.deltas.push_back(delta);
+        }
+
+        self.memory_used += state.len();
+        self.latest = Some(state);
+
+        // forget the oldest snapshots
+        while self.memory_used > self.budget {
+            match self.deltas.pop_front() {
+                Some(delta) => self.memory_used -= delta.len(),
+                None => break,
+            }
+        }
+    }
+
+    // restores the newest snapshot different from the current state and forgets it, false once there is
+    // nothing left to rewind
+    pub fn rewind(&mut self, emulator: &mut Emulator) -> bool {
+        // a snapshot taken on this frame is the current state, restoring it would do nothing
+        if self.latest.as_ref() == Some(&emulator.save_state()) {
+            self.pop_latest();
+        }
+
+        match self.pop_latest() {
+            // snapshots come from this emulator, loading them can't fail
+            Some(state) => emulator.load_state(&state).is_ok(),
+            None => false,
+        }
+    }
+
+    // forgets the newest snapshot, the previous one becoming the newest
+    fn pop_latest(&mut self) -> Option<Vec<u8>> {
+        let state = self.latest.take()?;
+
+        self.memory_used -= state.len();
+        self.frames_since_snapshot = 0;
+
+        if let Some(delta) = self.deltas.pop_back() {
+            let previous = patch(&state, &delta);
+
+            self.memory_used -= delta.len();
+            self.memory_used += previous.len();
+            self.latest = Some(previous);
+        }
+
+        Some(state)
+    }
+
+    pub fn clear(&mut self) {
+        self.frames_since_snapshot = 0;
+        self.latest = None;
+        self.deltas.clear();
+        self.memory_used = 0;
+    }
+
+    // number of snapshots that can be rewound
+    pub fn len(&self) -> usize {
+        self.latest.iter().count() + self.deltas.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.latest.is_none()
+    }
+
+    pub fn memory_used(&self) -> usize {
+        self.memory_used
+    }
+}
+
+// delta turning state into previous
+fn diff(state: &[u8], previous: &[u8]) -> Vec<u8> {
+    // the screen is reallocated when switching resolution, so states don't always have the same size
+    if state.len() != previous.len() {
+        let mut delta = vec![FULL];
+        delta.extend_from_slice(previous);
+
+        return delta;
+    }
+
+    let mut delta = vec![RUNS];
+    let mut index = 0;
+
+    while index < state.len() {
+        if state[index] == previous[index] {
+            index += 1;
+            continue;
+        }
+
+        // extend the run until enough identical bytes follow to pay for a new run header
+        let start = index;
+        let mut end = index + 1;
+        let mut same = 0;
+
+        while end < state.len() && same <= RUN_HEADER_SIZE {
+            if state[end] == previous[end] {
+                same += 1;
+            } else {
+                same = 0;
+            }
+
+            end += 1;
+        }
+
+        let end = end - same;
+        let length = (end - start).min(u16::MAX as usize);
+
+        delta.extend_from_slice(&(start as u32).to_be_bytes());
+        delta.extend_from_slice(&(length as u16).to_be_bytes());
+        delta.extend_from_slice(&previous[start..start + length]);
+
+        index = start + length;
+    }
+
+    delta
+}
+
+fn patch(state: &[u8], delta: &[u8]) -> Vec<u8> {
+    if delta[0] == FULL {
+        return delta[1..].to_vec();
+    }
+
+    let mut previous = state.to_vec();
+    let mut index = 1;
+
+    while index < delta.len() {
+        let start = u32::from_be_bytes([
+            delta[index],
+            delta[index + 1],
+            delta[index + 2],
+            delta[index + 3],
+        ]) as usize;
+        let length = u16::from_be_bytes([delta[index + 4], delta[index + 5]]) as usize;
+        index += RUN_HEADER_SIZE;
+
+        previous[start..start + length].copy_from_slice(&delta[index..index + length]);
+        index += length;
+    }
+
+    previous
+}
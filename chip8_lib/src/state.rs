@@ -0,0 +1,118 @@
+use crate::error::StateError;
+
+// Save state layout, all values big endian:
+// header: magic, version, rom hash
+// registers: V0-VF, I, PC
+// mmu: memory, screen, planes, audio, rpl flags, timers, stack, keys, keys of the previous frame
+// cpu: vblank and exit flags, key wait, fault, random source state
+// scheduler: frame flag, cycle and frame counts, time remainder, cycle overshoot, sprite cycles
+pub const MAGIC: [u8; 4] = *b"C8SS";
+pub const VERSION: u8 = 3;
+
+pub struct StateWriter {
+    bytes: Vec<u8>,
+}
+
+impl StateWriter {
+    pub fn new() -> Self {
+        Self { bytes: Vec::new() }
+    }
+
+    pub fn write_u8(&mut self, value: u8) {
+        self.bytes.push(value);
+    }
+
+    pub fn write_bool(&mut self, value: bool) {
+        self.bytes.push(value as u8);
+    }
+
+    pub fn write_u16(&mut self, value: u16) {
+        self.bytes.extend_from_slice(&value.to_be_bytes());
+    }
+
+    pub fn write_u32(&mut self, value: u32) {
+        self.bytes.extend_from_slice(&value.to_be_bytes());
+    }
+
+    pub fn write_u64(&mut self, value: u64) {
+        self.bytes.extend_from_slice(&value.to_be_bytes());
+    }
+
+    pub fn write_bytes(&mut self, bytes: &[u8]) {
+        self.bytes.extend_from_slice(bytes);
+    }
+
+    pub fn into_bytes(self) -> Vec<u8> {
+        self.bytes
+    }
+}
+
+pub struct StateReader<'a> {
+    bytes: &'a [u8],
+    position: usize,
+}
+
+impl<'a> StateReader<'a> {
+    pub fn new(bytes: &'a [u8]) -> Self {
+        Self { bytes, position: 0 }
+    }
+
+    pub fn read_u8(&mut self) -> Result<u8, StateError> {
+        Ok(self.read_bytes(1)?[0])
+    }
+
+    pub fn read_bool(&mut self) -> Result<bool, StateError> {
+        match self.read_u8()? {
+            0 => Ok(false),
+            1 => Ok(true),
+            _ => Err(StateError::Corrupted),
+        }
+    }
+
+    pub fn read_u16(&mut self) -> Result<u16, StateError> {
+        let bytes = self.read_bytes(2)?;
+
+        Ok(u16::from_be_bytes([bytes[0], bytes[1]]))
+    }
+
+    pub fn read_u32(&mut self) -> Result<u32, StateError> {
+        let bytes = self.read_bytes(4)?;
+
+        Ok(u32::from_be_bytes([bytes[0], bytes[1], bytes[2], bytes[3]]))
+    }
+
+    pub fn read_u64(&mut self) -> Result<u64, StateError> {
+        let bytes = self.read_bytes(8)?;
+
+        Ok(u64::from_be_bytes(bytes.try_into().unwrap()))
+    }
+
+    pub fn read_bytes(&mut self, length: usize) -> Result<&'a [u8], StateError> {
+        let end = self.position + length;
+
+        if end > self.bytes.len() {
+            return Err(StateError::Truncated);
+        }
+
+        let bytes = &self.bytes[self.position..end];
+        self.position = end;
+
+        Ok(bytes)
+    }
+
+    // the whole state has to be consumed, anything left means a different layout
+    pub fn finish(self) -> Result<(), StateError> {
+        if self.position == self.bytes.len() {
+            Ok(())
+        } else {
+            Err(StateError::Corrupted)
+        }
+    }
+}
+
+// 32 bit FNV-1a, identifies the rom a state or a movie was recorded from
+pub fn rom_hash(rom: &[u8]) -> u32 {
+    rom.iter().fold(0x811C9DC5, |hash, byte| {
+        (hash ^ *byte as u32).wrapping_mul(0x01000193)
+    })
+}
@@ -0,0 +1,264 @@
+use crate::config::FaultPolicies;
+use crate::cpu::Cpu;
+use crate::error::CpuFault;
+use crate::mmu::Mmu;
+use crate::platform::Platform;
+use crate::quirks::Quirks;
+use crate::random::{FixedSequence, SeededRandom};
+use crate::registers::Registers;
+
+// random numbers are the same on every run
+const SEED: u64 = 0;
+
+// A machine with an empty rom, put in a given state to run single opcodes on it
+// Opcodes are written at PC and executed like the CPU would, PC being incremented before running them
+pub struct TestMachine {
+    // to create the cpu again when one of them changes
+    platform: Platform,
+    quirks: Quirks,
+    faults: FaultPolicies,
+    cpu: Cpu,
+    mmu: Mmu,
+    registers: Registers,
+    fault: Option<CpuFault>,
+}
+
+impl TestMachine {
+    // CHIP-8 with the COSMAC VIP quirks
+    pub fn new() -> Self {
+        Self::for_platform(Platform::Chip8)
+    }
+
+    pub fn for_platform(platform: Platform) -> Self {
+        Self {
+            platform,
+            quirks: platform.default_quirks(),
+            faults: FaultPolicies::default(),
+            cpu: Cpu::new(
+                platform,
+                platform.default_quirks(),
+                FaultPolicies::default(),
+                Box::new(SeededRandom::new(SEED)),
+            ),
+            mmu: Mmu::new(&[], platform.memory_size()),
+            registers: Registers::new(),
+            fault: None,
+        }
+    }
+
+    pub fn with_quirks(mut self, quirks: Quirks) -> Self {
+        self.quirks = quirks;
+        self.cpu = Cpu::new(
+            self.platform,
+            quirks,
+            self.faults,
+            Box::new(SeededRandom::new(SEED)),
+        );
+        self
+    }
+
+    pub fn with_faults(mut self, faults: FaultPolicies) -> Self {
+        self.faults = faults;
+        self.cpu = Cpu::new(
+            self.platform,
+            self.quirks,
+            faults,
+            Box::new(SeededRandom::new(SEED)),
+        );
+        self
+    }
+
+    // bytes cxnn draws, in a loop
+    pub fn with_random_bytes(mut self, bytes: &[u8]) -> Self {
+        self.cpu
+            .set_random_source(Box::new(FixedSequence::new(bytes)));
+        self
+    }
+
+    // sets V0 and the following registers to the given values
+    pub fn with_registers(mut self, values: &[u8]) -> Self {
+        for (index, value) in values.iter().enumerate() {
+            self.registers.set_vx(index, *value);
+        }
+        self
+    }
+
+    pub fn with_register(mut self, index: usize, value: u8) -> Self {
+        self.registers.set_vx(index, value);
+        self
+    }
+
+    pub fn with_i(mut self, value: u16) -> Self {
+        self.registers.set_i(value);
+        self
+    }
+
+    pub fn with_pc(mut self, value: u16) -> Self {
+        self.registers.set_pc(value);
+        self
+    }
+
+    pub fn with_memory(mut self, address: usize, bytes: &[u8]) -> Self {
+        for (offset, byte) in bytes.iter().enumerate() {
+            self.mmu.set_byte(address + offset, *byte);
+        }
+        self
+    }
+
+    // pushes the given return addresses, the last one being on top
+    pub fn with_stack(mut self, addresses: &[u16]) -> Self {
+        for address in addresses {
+            self.mmu.push_on_stack(*address);
+        }
+        self
+    }
+
+    pub fn with_keys_down(mut self, keys: &[usize]) -> Self {
+        for key in keys {
+            self.mmu.set_key_down(*key, true);
+        }
+        self
+    }
+
+    pub fn with_delay_timer(mut self, value: u8) -> Self {
+        self.mmu.set_delay_timer(value);
+        self
+    }
+
+    pub fn with_sound_timer(mut self, value: u8) -> Self {
+        self.mmu.set_sound_timer(value);
+        self
+    }
+
+    pub fn with_rpl_flags(mut self, values: &[u8]) -> Self {
+        for (index, value) in values.iter().enumerate() {
+            self.mmu.set_rpl_flag(index, *value);
+        }
+        self
+    }
+
+    pub fn with_high_resolution(mut self) -> Self {
+        self.mmu.set_high_resolution(true);
+        self
+    }
+
+    pub fn with_selected_planes(mut self, planes: u8) -> Self {
+        self.mmu.set_selected_planes(planes);
+        self
+    }
+
+    // starts a new frame with only the given keys held, which is when a key wait sees them
+    pub fn next_frame(mut self, keys_down: &[usize]) -> Self {
+        self.mmu.latch_keys();
+
+        for key in 0..16 {
+            self.mmu.set_key_down(key, keys_down.contains(&key));
+        }
+
+        self.cpu.vblank(&self.mmu, &mut self.registers);
+        self
+    }
+
+    pub fn is_waiting_for_key(&self) -> bool {
+        self.cpu.is_waiting_for_key()
+    }
+
+    // writes the opcode at PC and runs it
+    pub fn execute(mut self, op_code: u16) -> Self {
+        let pc = self.registers.get_pc() as usize;
+
+        self.mmu.set_byte(pc, (op_code >> 8) as u8);
+        self.mmu.set_byte(pc + 1, op_code as u8);
+        self.fault = self.cpu.tick(&mut self.mmu, &mut self.registers).err();
+        self
+    }
+
+    // fault the last opcode halted on
+    pub fn fault(&self) -> Option<CpuFault> {
+        self.fault
+    }
+
+    pub fn vx(&self, index: usize) -> u8 {
+        self.registers.get_vx(index)
+    }
+
+    pub fn i(&self) -> u16 {
+        self.registers.get_i()
+    }
+
+    pub fn pc(&self) -> u16 {
+        self.registers.get_pc()
+    }
+
+    pub fn byte(&self, address: usize) -> u8 {
+        self.mmu.get_byte(address)
+    }
+
+    pub fn stack_pointer(&self) -> usize {
+        self.mmu.get_stack_pointer()
+    }
+
+    pub fn delay_timer(&self) -> u8 {
+        self.mmu.get_delay_timer()
+    }
+
+    pub fn sound_timer(&self) -> u8 {
+        self.mmu.get_sound_timer()
+    }
+
+    pub fn rpl_flag(&self, index: usize) -> u8 {
+        self.mmu.get_rpl_flag(index)
+    }
+
+    pub fn selected_planes(&self) -> u8 {
+        self.mmu.get_selected_planes()
+    }
+
+    pub fn audio_pattern(&self) -> Option<&[u8; 16]> {
+        self.mmu.get_audio_pattern()
+    }
+
+    pub fn pitch(&self) -> u8 {
+        self.mmu.get_pitch()
+    }
+
+    // planes lit at the given position, 0 if outside of the screen
+    pub fn pixel(&self, x: usize, y: usize) -> u8 {
+        if x >= self.screen_width() || y >= self.screen_height() {
+            return 0;
+        }
+
+        self.mmu.get_screen_at(x + y * self.screen_width())
+    }
+
+    // number of pixels lit on any plane
+    pub fn lit_pixels(&self) -> usize {
+        self.mmu
+            .get_screen()
+            .iter()
+            .filter(|pixel| **pixel != 0)
+            .count()
+    }
+
+    pub fn screen_width(&self) -> usize {
+        self.mmu.get_screen_width()
+    }
+
+    pub fn screen_height(&self) -> usize {
+        self.mmu.get_screen_height()
+    }
+
+    pub fn has_exited(&self) -> bool {
+        self.cpu.has_exited()
+    }
+
+    pub fn is_waiting_for_vblank(&self) -> bool {
+        self.cpu.is_waiting_for_vblank()
+    }
+}
+
+impl Default for TestMachine {
+    fn default() -> Self {
+        Self::new()
+    }
+}
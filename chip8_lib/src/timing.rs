@@ -0,0 +1,99 @@
+use crate::instruction::Instruction;
+use crate::registers::Registers;
+
+// the VIP runs its 1802 at 1.76064 MHz, 8 clock cycles making a machine cycle
+pub const VIP_CYCLES_PER_SECOND: u32 = 1_760_640 / 8;
+
+// the 1861 display reads the screen by DMA at the start of every frame, stealing a cycle per byte
+pub(crate) const VIP_DISPLAY_CYCLES: u64 = 128 * 8;
+
+// the interpreter loop fetching and decoding every instruction
+const FETCH_CYCLES: u64 = 40;
+
+// extra cycles of a skip that is taken
+pub(crate) const SKIP_CYCLES: u64 = 4;
+
+// How emulated time is counted
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum Timing {
+    // every instruction takes a cycle at the configured cpu speed
+    #[default]
+    Instructions,
+    // every instruction takes the machine cycles it takes on a COSMAC VIP, and dxyn always waits for the end
+    // of the frame, its rows taking their cycles at the start of the next one
+    CosmacVip,
+}
+
+impl Timing {
+    // cycles of emulated time per second, the cpu speed being ignored by the VIP timing
+    pub fn cycles_per_second(&self, cpu_hz: u32) -> u32 {
+        match self {
+            Timing::Instructions => cpu_hz,
+            Timing::CosmacVip => VIP_CYCLES_PER_SECOND,
+        }
+    }
+}
+
+// machine cycles the VIP interpreter spends on the instruction, with the registers as they were before it ran
+// a skip that is taken costs SKIP_CYCLES more
+// approximate costs, measured on the original interpreter, instructions it doesn't have cost a register load
+pub(crate) fn vip_cycles(instruction: &Instruction, registers: &Registers) -> u64 {
+    let cycles = match *instruction {
+        Instruction::Cls => 24,
+        Instruction::Ret => 10,
+        Instruction::Jp { .. } => 12,
+        Instruction::Call { .. } => 26,
+        Instruction::SeByte { .. } | Instruction::SneByte { .. } => 10,
+        Instruction::SeReg { .. } | Instruction::SneReg { .. } => 14,
+        Instruction::LdByte { .. } => 6,
+        Instruction::AddByte { .. } => 10,
+        Instruction::LdReg { .. } => 12,
+        // the interpreter writes the operation to memory and runs it
+        Instruction::Or { .. }
+        | Instruction::And { .. }
+        | Instruction::Xor { .. }
+        | Instruction::AddReg { .. }
+        | Instruction::Sub { .. }
+        | Instruction::Shr { .. }
+        | Instruction::Subn { .. }
+        | Instruction::Shl { .. } => 44,
+        Instruction::LdI { .. } => 12,
+        Instruction::JpV0 { .. } => 22,
+        Instruction::Rnd { .. } => 36,
+        // up to the wait for the vertical blank, the rows are drawn after it
+        Instruction::Drw { .. } => 26,
+        Instruction::Skp { .. } | Instruction::Sknp { .. } => 14,
+        Instruction::LdVxDt { .. }
+        | Instruction::LdVxK { .. }
+        | Instruction::LdDtVx { .. }
+        | Instruction::LdStVx { .. } => 10,
+        Instruction::AddI { .. } | Instruction::LdF { .. } => 16,
+        // every digit is found by repeated subtraction
+        Instruction::LdB { x } => {
+            let value = registers.get_vx(x as usize);
+            let digits = value / 100 + value / 10 % 10 + value % 10;
+
+            80 + 16 * digits as u64
+        }
+        Instruction::LdIVx { x } | Instruction::LdVxI { x } => 14 + 14 * (x as u64 + 1),
+        _ => 12,
+    };
+
+    FETCH_CYCLES + cycles
+}
+
+// machine cycles the VIP interpreter spends on the rows of a sprite once the vertical blank is over
+// sprites not aligned on a byte are shifted across two bytes of every row
+pub(crate) fn vip_sprite_cycles(instruction: &Instruction, registers: &Registers) -> u64 {
+    match *instruction {
+        Instruction::Drw { x, n, .. } => {
+            let row_cycles = match registers.get_vx(x as usize) % 8 {
+                0 => 46,
+                _ => 54,
+            };
+
+            n as u64 * row_cycles
+        }
+        _ => 0,
+    }
+}
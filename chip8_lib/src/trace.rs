@@ -0,0 +1,251 @@
+use crate::error::OpcodeFilterError;
+use crate::instruction::Instruction;
+use crate::mmu::Mmu;
+use crate::registers::Registers;
+use std::collections::VecDeque;
+use std::fs::File;
+use std::io::{self, BufWriter, Write};
+use std::ops::RangeInclusive;
+use std::path::Path;
+use std::str::FromStr;
+
+// Matches opcodes against a pattern such as dxyn or 8xy4, hex digits having to match and anything else
+// matching every digit
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct OpcodeFilter {
+    mask: u16,
+    value: u16,
+}
+
+impl OpcodeFilter {
+    pub fn matches(&self, op_code: u16) -> bool {
+        op_code & self.mask == self.value
+    }
+}
+
+impl FromStr for OpcodeFilter {
+    type Err = OpcodeFilterError;
+
+    fn from_str(pattern: &str) -> Result<Self, Self::Err> {
+        if pattern.chars().count() != 4 {
+            return Err(OpcodeFilterError {
+                pattern: pattern.to_string(),
+            });
+        }
+
+        Ok(pattern.chars().fold(
+            Self { mask: 0, value: 0 },
+            |filter, character| match character.to_digit(16) {
+                Some(digit) => Self {
+                    mask: filter.mask << 4 | 0xF,
+                    value: filter.value << 4 | digit as u16,
+                },
+                None => Self {
+                    mask: filter.mask << 4,
+                    value: filter.value << 4,
+                },
+            },
+        ))
+    }
+}
+
+// registers an instruction can change, to only log the ones it did
+struct Snapshot {
+    vx: [u8; 16],
+    i: u16,
+    stack_pointer: usize,
+    delay_timer: u8,
+    sound_timer: u8,
+}
+
+impl Snapshot {
+    fn new(registers: &Registers, mmu: &Mmu) -> Self {
+        Self {
+            vx: std::array::from_fn(|index| registers.get_vx(index)),
+            i: registers.get_i(),
+            stack_pointer: mmu.get_stack_pointer(),
+            delay_timer: mmu.get_delay_timer(),
+            sound_timer: mmu.get_sound_timer(),
+        }
+    }
+
+    // "V0=08 I=0300" for the registers that differ in the other snapshot
+    fn changes(&self, after: &Snapshot) -> String {
+        let mut changes: Vec<String> = (0..16)
+            .filter(|index| self.vx[*index] != after.vx[*index])
+            .map(|index| format!("V{:X}={:02x}", index, after.vx[index]))
+            .collect();
+
+        if self.i != after.i {
+            changes.push(format!("I={:04x}", after.i));
+        }
+
+        if self.stack_pointer != after.stack_pointer {
+            changes.push(format!("SP={}", after.stack_pointer));
+        }
+
+        if self.delay_timer != after.delay_timer {
+            changes.push(format!("DT={:02x}", after.delay_timer));
+        }
+
+        if self.sound_timer != after.sound_timer {
+            changes.push(format!("ST={:02x}", after.sound_timer));
+        }
+
+        changes.join(" ")
+    }
+}
+
+// Logs the executed instructions, one line each:
+// "<cycle> <pc> <opcode> <disassembly>" followed by " ; <changed registers>" when any changed,
+// e.g. "0000000042 0204 6008 LD V0, 0x08 ; V0=08"
+// the cycle counts every instruction, including the ones filtered out
+// In ring buffer mode only the last lines are kept, and written by dump once something went wrong
+pub struct Tracer {
+    writer: Box<dyn Write + Send>,
+    address_ranges: Vec<RangeInclusive<u16>>,
+    opcode_filters: Vec<OpcodeFilter>,
+    ring_buffer: Option<(usize, VecDeque<String>)>,
+    cycle: u64,
+    // first error writing the trace, returned by finish
+    error: Option<io::Error>,
+    before: Option<Snapshot>,
+}
+
+impl Tracer {
+    pub fn new(writer: impl Write + Send + 'static) -> Self {
+        Self {
+            writer: Box::new(writer),
+            address_ranges: Vec::new(),
+            opcode_filters: Vec::new(),
+            ring_buffer: None,
+            cycle: 0,
+            error: None,
+            before: None,
+        }
+    }
+
+    pub fn to_file(path: impl AsRef<Path>) -> io::Result<Self> {
+        Ok(Self::new(BufWriter::new(File::create(path)?)))
+    }
+
+    // only logs instructions in one of the ranges, every address when there is none
+    pub fn with_address_range(mut self, range: RangeInclusive<u16>) -> Self {
+        self.address_ranges.push(range);
+        self
+    }
+
+    // only logs instructions matching one of the filters, every opcode when there is none
+    pub fn with_opcode_filter(mut self, filter: OpcodeFilter) -> Self {
+        self.opcode_filters.push(filter);
+        self
+    }
+
+    // keeps the last lines instead of writing them
+    pub fn with_ring_buffer(mut self, size: usize) -> Self {
+        self.ring_buffer = Some((size, VecDeque::with_capacity(size)));
+        self
+    }
+
+    // instructions executed since tracing started
+    pub fn get_cycle(&self) -> u64 {
+        self.cycle
+    }
+
+    // writes a "# <reason>" line, followed by the lines kept in ring buffer mode
+    pub fn dump(&mut self, reason: &str) {
+        self.write_line(format!("# {}", reason));
+
+        if let Some((_, lines)) = self.ring_buffer.as_mut() {
+            let lines = std::mem::take(lines);
+
+            for line in lines {
+                self.write_line(line);
+            }
+        }
+    }
+
+    // flushes the trace, returning the first error writing it
+    pub fn finish(mut self) -> io::Result<()> {
+        if let Some(error) = self.error.take() {
+            return Err(error);
+        }
+
+        self.writer.flush()
+    }
+
+    pub(crate) fn before_instruction(&mut self, registers: &Registers, mmu: &Mmu) {
+        self.before = Some(Snapshot::new(registers, mmu));
+    }
+
+    // instruction is None for invalid opcodes
+    pub(crate) fn after_instruction(
+        &mut self,
+        pc: u16,
+        op_code: u16,
+        instruction: Option<&Instruction>,
+        registers: &Registers,
+        mmu: &Mmu,
+    ) {
+        let cycle = self.cycle;
+        self.cycle += 1;
+
+        let before = match self.before.take() {
+            Some(before) => before,
+            None => return,
+        };
+
+        if !self.is_traced(pc, op_code) {
+            return;
+        }
+
+        let disassembly = match instruction {
+            Some(instruction) => instruction.to_string(),
+            None => "invalid".to_string(),
+        };
+
+        let mut line = format!("{:010} {:04x} {:04x} {}", cycle, pc, op_code, disassembly);
+        let changes = before.changes(&Snapshot::new(registers, mmu));
+
+        if !changes.is_empty() {
+            line.push_str(" ; ");
+            line.push_str(&changes);
+        }
+
+        match self.ring_buffer.as_mut() {
+            Some((size, lines)) => {
+                if lines.len() == *size {
+                    lines.pop_front();
+                }
+
+                if *size > 0 {
+                    lines.push_back(line);
+                }
+            }
+            None => self.write_line(line),
+        }
+    }
+
+    fn is_traced(&self, pc: u16, op_code: u16) -> bool {
+        let in_range = self.address_ranges.is_empty()
+            || self.address_ranges.iter().any(|range| range.contains(&pc));
+        let matching = self.opcode_filters.is_empty()
+            || self
+                .opcode_filters
+                .iter()
+                .any(|filter| filter.matches(op_code));
+
+        in_range && matching
+    }
+
+    // errors are kept for finish, as execution can't stop for them
+    fn write_line(&mut self, line: String) {
+        if self.error.is_some() {
+            return;
+        }
+
+        if let Err(error) = writeln!(self.writer, "{}", line) {
+            self.error = Some(error);
+        }
+    }
+}
@@ -0,0 +1,101 @@
+// Clocks and frame pacing, on a manual clock
+
+use chip8_lib::clock::{Clock, FramePacer, ManualClock};
+use chip8_lib::config::Config;
+use chip8_lib::emulator::Emulator;
+use chip8_lib::headless::{NullController, NullSynthetizer};
+use chip8_lib::interfaces::Renderer;
+use chip8_lib::platform::Platform;
+use std::time::Duration;
+
+const MILLISECOND: Duration = Duration::from_millis(1);
+
+#[test]
+fn manual_clock_is_shared() {
+    let clock = ManualClock::new();
+    let other = clock.clone();
+
+    other.advance(Duration::from_secs(2));
+
+    assert_eq!(clock.now(), Duration::from_secs(2));
+}
+
+#[test]
+fn frames_are_due_at_a_steady_rate() {
+    let clock = ManualClock::new();
+    let mut pacer = FramePacer::new(&clock, 60);
+
+    assert_eq!(pacer.frames_due(&clock), 1);
+    assert_eq!(pacer.frames_due(&clock), 0);
+    assert_eq!(
+        pacer.until_next_frame(&clock),
+        Duration::from_nanos(16_666_666)
+    );
+
+    // a late frame doesn't push the next ones back
+    clock.advance(20 * MILLISECOND);
+    assert_eq!(pacer.frames_due(&clock), 1);
+    assert_eq!(
+        pacer.until_next_frame(&clock),
+        Duration::from_nanos(13_333_333)
+    );
+
+    // frames 2 to 60 are due over the rest of the first second, the last one at exactly one second
+    let mut frames = 0;
+    for _ in 0..980 {
+        clock.advance(MILLISECOND);
+        frames += pacer.frames_due(&clock);
+    }
+
+    assert_eq!(frames, 59);
+}
+
+#[test]
+fn catching_up_is_limited() {
+    let clock = ManualClock::new();
+    let mut pacer = FramePacer::new(&clock, 60).with_max_catch_up(3);
+
+    pacer.frames_due(&clock);
+    clock.advance(Duration::from_secs(1));
+
+    // the missed frames are dropped, the next one being due a frame from now
+    assert_eq!(pacer.frames_due(&clock), 3);
+    assert_eq!(pacer.frames_due(&clock), 0);
+    assert_eq!(
+        pacer.until_next_frame(&clock),
+        Duration::from_nanos(16_666_666)
+    );
+}
+
+// renders slowly, on the clock of the emulator
+struct SlowRenderer {
+    clock: ManualClock,
+}
+
+impl Renderer for SlowRenderer {
+    fn render(&mut self, _screen: &[u8], _width: usize, _height: usize) {
+        self.clock.advance(5 * MILLISECOND);
+    }
+}
+
+#[test]
+fn reports_use_the_clock_of_the_emulator() {
+    let clock = ManualClock::new();
+    let mut emulator = Emulator::from_bytes(&[0x12, 0x00], Config::new(Platform::Chip8))
+        .unwrap()
+        .with_clock(clock.clone());
+    let mut renderer = SlowRenderer {
+        clock: clock.clone(),
+    };
+
+    // time passed between frames isn't part of them
+    clock.advance(Duration::from_secs(1));
+
+    for _ in 0..2 {
+        let report = emulator.step_frame(&NullController, &mut renderer, &mut NullSynthetizer);
+
+        assert_eq!(report.elapsed, 5 * MILLISECOND);
+    }
+
+    assert_eq!(clock.now(), Duration::from_secs(1) + 10 * MILLISECOND);
+}
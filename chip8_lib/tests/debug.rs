@@ -0,0 +1,210 @@
+// Breakpoints, watchpoints and conditions stopping step_frame
+
+use chip8_lib::config::Config;
+use chip8_lib::debug::{Access, Condition, StopReason, Watchpoint};
+use chip8_lib::emulator::Emulator;
+use chip8_lib::headless::{CaptureRenderer, NullController, NullSynthetizer};
+use chip8_lib::platform::Platform;
+
+fn emulator(rom: &[u8]) -> Emulator {
+    Emulator::from_bytes(rom, Config::new(Platform::SuperChip)).unwrap()
+}
+
+fn step_frame(emulator: &mut Emulator) -> Option<StopReason> {
+    emulator
+        .step_frame(
+            &NullController,
+            &mut CaptureRenderer::new(),
+            &mut NullSynthetizer,
+        )
+        .stop_reason
+}
+
+// V0 += 1, jump back to 0x200
+const COUNTER: [u8; 4] = [0x70, 0x01, 0x12, 0x00];
+
+#[test]
+fn breakpoint_stops_before_the_instruction() {
+    let mut emulator = emulator(&COUNTER);
+    emulator.add_breakpoint(0x202, None);
+
+    assert_eq!(
+        step_frame(&mut emulator),
+        Some(StopReason::Breakpoint { address: 0x202 })
+    );
+    assert_eq!(emulator.registers().get_pc(), 0x202);
+    assert_eq!(emulator.registers().get_vx(0), 1);
+}
+
+#[test]
+fn resuming_runs_the_instruction_stopped_at() {
+    let mut emulator = emulator(&COUNTER);
+    emulator.add_breakpoint(0x202, None);
+
+    step_frame(&mut emulator);
+
+    assert_eq!(
+        step_frame(&mut emulator),
+        Some(StopReason::Breakpoint { address: 0x202 })
+    );
+    assert_eq!(emulator.registers().get_vx(0), 2);
+}
+
+#[test]
+fn frame_ends_without_breakpoint() {
+    let mut emulator = emulator(&COUNTER);
+
+    assert_eq!(step_frame(&mut emulator), None);
+    assert_eq!(emulator.registers().get_vx(0), 5);
+}
+
+#[test]
+fn conditional_breakpoint() {
+    let mut emulator = emulator(&COUNTER);
+    emulator.add_breakpoint(0x202, Some(Condition::parse("V0 == 7").unwrap()));
+
+    // stops in the middle of the second frame
+    assert_eq!(step_frame(&mut emulator), None);
+    assert_eq!(
+        step_frame(&mut emulator),
+        Some(StopReason::Breakpoint { address: 0x202 })
+    );
+    assert_eq!(emulator.registers().get_vx(0), 7);
+
+    // the rest of the frame runs when resuming
+    assert_eq!(step_frame(&mut emulator), None);
+    assert_eq!(emulator.registers().get_vx(0), 10);
+}
+
+#[test]
+fn removed_breakpoint_does_not_stop() {
+    let mut emulator = emulator(&COUNTER);
+    emulator.add_breakpoint(0x202, None);
+
+    assert!(emulator.remove_breakpoint(0x202));
+    assert!(!emulator.remove_breakpoint(0x202));
+    assert_eq!(step_frame(&mut emulator), None);
+}
+
+#[test]
+fn write_watchpoint_stops_after_the_write() {
+    // I := 0x300, V0 := 0x42, save V0, V1 := 1
+    let rom = [0xA3, 0x00, 0x60, 0x42, 0xF0, 0x55, 0x61, 0x01];
+    let mut emulator = emulator(&rom);
+    emulator.add_watchpoint(Watchpoint::write(0x300..=0x30F));
+
+    assert_eq!(
+        step_frame(&mut emulator),
+        Some(StopReason::Watchpoint {
+            address: 0x300,
+            access: Access::Write,
+            value: 0x42
+        })
+    );
+    assert_eq!(emulator.registers().get_pc(), 0x206);
+    assert_eq!(emulator.registers().get_vx(1), 0);
+}
+
+#[test]
+fn read_watchpoint_on_sprite_data() {
+    // I := 0x208, draw 1 row, loop, then the sprite
+    let rom = [0xA2, 0x08, 0xD0, 0x01, 0x12, 0x04, 0x00, 0x00, 0xF0];
+    let mut emulator = emulator(&rom);
+    emulator.add_watchpoint(Watchpoint::read(0x208..=0x208));
+
+    assert_eq!(
+        step_frame(&mut emulator),
+        Some(StopReason::Watchpoint {
+            address: 0x208,
+            access: Access::Read,
+            value: 0xF0
+        })
+    );
+    assert_eq!(emulator.registers().get_pc(), 0x204);
+}
+
+#[test]
+fn write_watchpoint_ignores_reads() {
+    let rom = [0xA2, 0x08, 0xD0, 0x01, 0x12, 0x04, 0x00, 0x00, 0xF0];
+    let mut emulator = emulator(&rom);
+    emulator.add_watchpoint(Watchpoint::write(0x208..=0x208));
+
+    assert_eq!(step_frame(&mut emulator), None);
+}
+
+#[test]
+fn execute_watchpoint_stops_before_the_instruction() {
+    let mut emulator = emulator(&COUNTER);
+    emulator.add_watchpoint(Watchpoint::execute(0x202..=0x203));
+
+    assert_eq!(
+        step_frame(&mut emulator),
+        Some(StopReason::Watchpoint {
+            address: 0x202,
+            access: Access::Execute,
+            value: 0x12
+        })
+    );
+    assert_eq!(emulator.registers().get_vx(0), 1);
+}
+
+#[test]
+fn inspecting_memory_does_not_trigger_watchpoints() {
+    let mut emulator = emulator(&COUNTER);
+    emulator.add_watchpoint(Watchpoint::read(0x300..=0x300));
+
+    emulator.mmu().get_byte(0x300);
+
+    assert_eq!(step_frame(&mut emulator), None);
+}
+
+#[test]
+fn condition_operands() {
+    // V3 := 0x10, I := 0x301, DT := V3
+    let rom = [0x63, 0x10, 0xA3, 0x01, 0xF3, 0x15, 0x12, 0x06];
+    let mut emulator = emulator(&rom);
+    step_frame(&mut emulator);
+
+    let holds = |text: &str| {
+        Condition::parse(text)
+            .unwrap()
+            .evaluate(emulator.registers(), emulator.mmu())
+    };
+
+    assert!(holds("V3 == 0x10 && I > 0x300"));
+    assert!(holds("v3 == 16"));
+    assert!(holds("PC == 0x206 && SP == 0"));
+    assert!(holds("DT == 0x0f"));
+    assert!(holds("[0x200] == 0x63 && [I] == 0"));
+    assert!(holds("[0x201] == V3"));
+    assert!(holds("DT"));
+    assert!(!holds("ST"));
+    assert!(!holds("VF"));
+}
+
+#[test]
+fn condition_precedence() {
+    let emulator = emulator(&COUNTER);
+    let holds = |text: &str| {
+        Condition::parse(text)
+            .unwrap()
+            .evaluate(emulator.registers(), emulator.mmu())
+    };
+
+    // && binds tighter than ||
+    assert!(holds("1 == 1 || 1 == 2 && 2 == 3"));
+    assert!(!holds("(1 == 1 || 1 == 2) && 2 == 3"));
+    assert!(holds("1 < 2 && 2 <= 2 && 3 > 2 && 3 >= 3 && 1 != 2"));
+}
+
+#[test]
+fn condition_errors() {
+    let error = |text: &str| Condition::parse(text).unwrap_err().to_string();
+
+    assert_eq!(error("V3 =="), "column 6: unexpected end of condition");
+    assert_eq!(error("VG == 1"), "column 1: unknown register VG");
+    assert_eq!(error("(V0 == 1"), "column 9: expected )");
+    assert_eq!(error("V0 == 1 1"), "column 9: unexpected 1");
+    assert_eq!(error("V0 = 1"), "column 4: unexpected =");
+    assert_eq!(error("0xZZ"), "column 1: invalid number 0xZZ");
+}
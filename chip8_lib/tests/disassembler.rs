@@ -0,0 +1,76 @@
+// Recursive descent disassembly of roms
+
+use chip8_lib::disassembler::{disassemble, LabelKind};
+use chip8_lib::instruction::Instruction;
+
+#[test]
+fn follows_calls_jumps_and_skips() {
+    // call 0x208, skip if V0 == 1, jump to 0x200, return, then a sprite byte
+    let rom = [
+        0x22, 0x08, 0x30, 0x01, 0x12, 0x00, 0x00, 0xE0, 0x00, 0xEE, 0xF0,
+    ];
+    let disassembly = disassemble(&rom);
+
+    assert_eq!(
+        disassembly.instruction_at(0x200),
+        Some(Instruction::Call { addr: 0x208 })
+    );
+    // reached through the skip only
+    assert_eq!(disassembly.instruction_at(0x206), Some(Instruction::Cls));
+    assert_eq!(disassembly.instruction_at(0x208), Some(Instruction::Ret));
+    assert_eq!(disassembly.instruction_at(0x20A), None);
+
+    assert_eq!(
+        disassembly.labels().get(&0x208),
+        Some(&LabelKind::Subroutine)
+    );
+    assert_eq!(disassembly.labels().get(&0x200), Some(&LabelKind::Jump));
+
+    let text = disassembly.to_string();
+
+    assert!(text.contains("sub_208:\n    0x208  00ee      RET"));
+    assert!(text.contains("CALL sub_208"));
+    assert!(text.contains("JP label_200"));
+    assert!(text.contains("0x20a  f0        DB 0xf0  ; ####...."));
+}
+
+#[test]
+fn data_labels() {
+    // I := 0x206, draw, loop, sprite
+    let rom = [0xA2, 0x06, 0xD0, 0x11, 0x12, 0x04, 0x80];
+    let text = disassemble(&rom).to_string();
+
+    assert!(text.contains("LD I, data_206"));
+    assert!(text.contains("data_206:\n    0x206  80"));
+}
+
+#[test]
+fn targets_without_a_line_keep_their_address() {
+    // I := 0x050 (the font, before the rom), skip, jump past the end, jump into the middle of the skip
+    let rom = [0xA0, 0x50, 0x30, 0x00, 0x12, 0x40, 0x12, 0x03];
+    let disassembly = disassemble(&rom);
+    let text = disassembly.to_string();
+
+    assert!(text.contains("LD I, 0x050"));
+    assert!(text.contains("JP 0x203"));
+    assert!(text.contains("JP 0x240"));
+
+    assert!(!disassembly.labels().contains_key(&0x050));
+    assert!(!disassembly.labels().contains_key(&0x203));
+    assert!(!disassembly.labels().contains_key(&0x240));
+}
+
+#[test]
+fn rom_filling_the_xo_chip_memory() {
+    // skips up to the very last instruction, which ends at 0x10000
+    let rom = vec![0x30; 0x10000 - 0x200];
+    let disassembly = disassemble(&rom);
+
+    assert_eq!(
+        disassembly.instruction_at(0xFFFE),
+        Some(Instruction::SeByte { x: 0, byte: 0x30 })
+    );
+    assert!(disassembly
+        .to_string()
+        .ends_with("    0xfffe  3030      SE V0, 0x30\n"));
+}
@@ -0,0 +1,193 @@
+// CPU faults and what their policies do about them
+
+use chip8_lib::config::{Config, FaultPolicies, FaultPolicy};
+use chip8_lib::debug::StopReason;
+use chip8_lib::emulator::Emulator;
+use chip8_lib::error::CpuFault;
+use chip8_lib::headless::{CaptureRenderer, NullController, NullSynthetizer};
+use chip8_lib::platform::Platform;
+use chip8_lib::test_util::TestMachine;
+
+const FULL_STACK: [u16; 16] = [
+    0x300, 0x302, 0x304, 0x306, 0x308, 0x30A, 0x30C, 0x30E, 0x310, 0x312, 0x314, 0x316, 0x318,
+    0x31A, 0x31C, 0x31E,
+];
+
+fn with_policy(policy: FaultPolicy) -> TestMachine {
+    TestMachine::new().with_faults(FaultPolicies::all(policy))
+}
+
+#[test]
+fn stack_overflow() {
+    let machine = with_policy(FaultPolicy::Halt)
+        .with_stack(&FULL_STACK)
+        .execute(0x2400);
+
+    assert_eq!(
+        machine.fault(),
+        Some(CpuFault::StackOverflow { address: 0x200 })
+    );
+    assert_eq!(machine.pc(), 0x200);
+
+    let machine = with_policy(FaultPolicy::Ignore)
+        .with_stack(&FULL_STACK)
+        .execute(0x2400);
+
+    assert_eq!(machine.fault(), None);
+    assert_eq!(machine.pc(), 0x202);
+    assert_eq!(machine.stack_pointer(), 16);
+
+    // the oldest return address makes room for the new one
+    let machine = with_policy(FaultPolicy::Wrap)
+        .with_stack(&FULL_STACK)
+        .execute(0x2400);
+
+    assert_eq!(machine.pc(), 0x400);
+    assert_eq!(machine.stack_pointer(), 16);
+}
+
+#[test]
+fn stack_underflow() {
+    let machine = with_policy(FaultPolicy::Halt).execute(0x00EE);
+
+    assert_eq!(
+        machine.fault(),
+        Some(CpuFault::StackUnderflow { address: 0x200 })
+    );
+
+    let machine = with_policy(FaultPolicy::Log).execute(0x00EE);
+
+    assert_eq!(machine.fault(), None);
+    assert_eq!(machine.pc(), 0x202);
+}
+
+#[test]
+fn memory_out_of_range() {
+    let machine = with_policy(FaultPolicy::Halt)
+        .with_registers(&[1, 2, 3])
+        .with_i(0xFFE)
+        .execute(0xF255);
+
+    assert_eq!(
+        machine.fault(),
+        Some(CpuFault::MemoryOutOfRange {
+            address: 0x200,
+            target: 0x1000
+        })
+    );
+    assert_eq!(machine.byte(0xFFE), 0);
+
+    // the last byte lands at the start of memory
+    let machine = with_policy(FaultPolicy::Wrap)
+        .with_registers(&[1, 2, 3])
+        .with_i(0xFFE)
+        .execute(0xF255);
+
+    assert_eq!(machine.byte(0xFFF), 2);
+    assert_eq!(machine.byte(0x000), 3);
+}
+
+#[test]
+fn log_skips_like_ignore() {
+    // overflow, underflow, invalid opcode and out of range store
+    let cases: [(&[u16], u16); 4] = [
+        (&FULL_STACK, 0x2400),
+        (&[], 0x00EE),
+        (&[], 0x5001),
+        (&[], 0xF255),
+    ];
+
+    for (stack, op_code) in cases {
+        let run = |policy| {
+            with_policy(policy)
+                .with_stack(stack)
+                .with_registers(&[1, 2, 3])
+                .with_i(0xFFE)
+                .execute(op_code)
+        };
+        let ignored = run(FaultPolicy::Ignore);
+        let logged = run(FaultPolicy::Log);
+
+        assert_eq!(logged.fault(), None);
+        assert_eq!(logged.pc(), ignored.pc());
+        assert_eq!(logged.stack_pointer(), ignored.stack_pointer());
+        assert_eq!(logged.byte(0xFFE), ignored.byte(0xFFE));
+        assert_eq!(logged.byte(0x000), ignored.byte(0x000));
+    }
+}
+
+#[test]
+fn last_instruction_of_memory() {
+    // the opcode is split between the last byte and the first one
+    let machine = TestMachine::for_platform(Platform::XoChip)
+        .with_faults(FaultPolicies::all(FaultPolicy::Wrap))
+        .with_pc(0xFFFF)
+        .execute(0x6005);
+
+    assert_eq!(machine.vx(0), 5);
+    assert_eq!(machine.pc(), 0x0001);
+
+    let machine = TestMachine::for_platform(Platform::XoChip)
+        .with_faults(FaultPolicies::all(FaultPolicy::Wrap))
+        .with_i(0xFFFF)
+        .execute(0xD00F);
+
+    assert_eq!(machine.fault(), None);
+}
+
+#[test]
+fn halted_emulator_stays_on_the_fault() {
+    // V0 := 1, invalid opcode
+    let config = Config::new(Platform::Chip8).with_faults(FaultPolicies::all(FaultPolicy::Halt));
+    let mut emulator = Emulator::from_bytes(&[0x60, 0x01, 0xFF, 0xFF], config).unwrap();
+    let mut renderer = CaptureRenderer::new();
+
+    let report = emulator.step_frame(&NullController, &mut renderer, &mut NullSynthetizer);
+    let fault = CpuFault::InvalidOpcode {
+        address: 0x202,
+        op_code: 0xFFFF,
+    };
+
+    assert_eq!(report.stop_reason, Some(StopReason::Fault(fault)));
+    assert_eq!(emulator.get_fault(), Some(fault));
+
+    let report = emulator.step_frame(&NullController, &mut renderer, &mut NullSynthetizer);
+
+    assert_eq!(report.stop_reason, None);
+    assert_eq!(emulator.registers().get_pc(), 0x202);
+}
+
+#[test]
+fn opcodes_of_later_platforms_are_invalid() {
+    // exit, high resolution, f000 nnnn, save range
+    for op_code in [0x00FD, 0x00FF, 0xF000, 0x5012] {
+        let machine = with_policy(FaultPolicy::Halt).execute(op_code);
+
+        assert_eq!(
+            machine.fault(),
+            Some(CpuFault::InvalidOpcode {
+                address: 0x200,
+                op_code
+            })
+        );
+    }
+
+    // scrolling up is XO-CHIP only
+    let machine = TestMachine::for_platform(Platform::SuperChip)
+        .with_faults(FaultPolicies::all(FaultPolicy::Halt))
+        .execute(0x00D1);
+
+    assert_eq!(
+        machine.fault(),
+        Some(CpuFault::InvalidOpcode {
+            address: 0x200,
+            op_code: 0x00D1
+        })
+    );
+
+    let machine = TestMachine::for_platform(Platform::SuperChip)
+        .with_faults(FaultPolicies::all(FaultPolicy::Halt))
+        .execute(0x00FD);
+
+    assert!(machine.has_exited());
+}
@@ -0,0 +1,38 @@
+// Decoding and encoding op codes
+
+use chip8_lib::instruction::{decode, encode, DecodeError, Instruction};
+
+#[test]
+fn every_instruction_round_trips() {
+    // every instruction with operands in range is the decoding of an op code
+    for op_code in 0..=u16::MAX {
+        if let Ok(instruction) = decode(op_code) {
+            assert_eq!(encode(&instruction), op_code, "{}", instruction);
+            assert_eq!(decode(encode(&instruction)), Ok(instruction));
+        }
+    }
+}
+
+#[test]
+fn invalid_op_codes() {
+    for op_code in [
+        0x0000, 0x00E1, 0x5121, 0x8128, 0x9121, 0xE19F, 0xF100, 0xF1FF,
+    ] {
+        assert_eq!(decode(op_code), Err(DecodeError { op_code }));
+    }
+}
+
+#[test]
+fn operands() {
+    assert_eq!(
+        decode(0xD12F),
+        Ok(Instruction::Drw {
+            x: 0x1,
+            y: 0x2,
+            n: 0xF
+        })
+    );
+    assert_eq!(decode(0xB3A5), Ok(Instruction::JpV0 { addr: 0x3A5 }));
+    assert_eq!(decode(0xF201), Ok(Instruction::Plane { n: 2 }));
+    assert_eq!(encode(&Instruction::SeByte { x: 0xA, byte: 0x42 }), 0x3A42);
+}
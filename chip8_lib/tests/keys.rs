@@ -0,0 +1,132 @@
+// fx0a waiting for a key across frames
+
+use chip8_lib::config::Config;
+use chip8_lib::emulator::Emulator;
+use chip8_lib::headless::{CaptureRenderer, NullSynthetizer};
+use chip8_lib::interfaces::Controller;
+use chip8_lib::platform::Platform;
+use std::cell::Cell;
+
+// V0 := 10, DT := V0, V1 := key, loop
+const ROM: [u8; 8] = [0x60, 0x0A, 0xF0, 0x15, 0xF1, 0x0A, 0x12, 0x06];
+
+struct Keys {
+    down: Cell<Option<usize>>,
+}
+
+impl Controller for Keys {
+    fn is_key_down(&self, key_index: usize) -> bool {
+        self.down.get() == Some(key_index)
+    }
+}
+
+fn step_frame(emulator: &mut Emulator, keys: &Keys) {
+    emulator.step_frame(keys, &mut CaptureRenderer::new(), &mut NullSynthetizer);
+}
+
+#[test]
+fn timers_run_while_waiting() {
+    let mut emulator = Emulator::from_bytes(&ROM, Config::new(Platform::Chip8)).unwrap();
+    let keys = Keys {
+        down: Cell::new(None),
+    };
+
+    for _ in 0..4 {
+        step_frame(&mut emulator, &keys);
+    }
+
+    assert!(emulator.is_waiting_for_key());
+    assert_eq!(emulator.mmu().get_delay_timer(), 6);
+
+    // held for two frames, then released
+    keys.down.set(Some(0xB));
+    step_frame(&mut emulator, &keys);
+    step_frame(&mut emulator, &keys);
+
+    assert!(emulator.is_waiting_for_key());
+
+    keys.down.set(None);
+    step_frame(&mut emulator, &keys);
+
+    assert!(!emulator.is_waiting_for_key());
+    assert_eq!(emulator.registers().get_vx(1), 0xB);
+    assert_eq!(emulator.mmu().get_delay_timer(), 3);
+}
+
+#[test]
+fn keys_held_before_the_wait_are_ignored() {
+    let mut emulator = Emulator::from_bytes(&ROM, Config::new(Platform::Chip8)).unwrap();
+    let keys = Keys {
+        down: Cell::new(Some(4)),
+    };
+
+    // held since before fx0a runs, then released
+    for _ in 0..3 {
+        step_frame(&mut emulator, &keys);
+    }
+
+    keys.down.set(None);
+    step_frame(&mut emulator, &keys);
+    step_frame(&mut emulator, &keys);
+
+    assert!(emulator.is_waiting_for_key());
+    assert_eq!(emulator.registers().get_vx(1), 0);
+
+    // pressed again once waiting
+    keys.down.set(Some(4));
+    step_frame(&mut emulator, &keys);
+    keys.down.set(None);
+    step_frame(&mut emulator, &keys);
+
+    assert!(!emulator.is_waiting_for_key());
+    assert_eq!(emulator.registers().get_vx(1), 4);
+}
+
+#[test]
+fn save_states_keep_the_wait() {
+    let mut emulator = Emulator::from_bytes(&ROM, Config::new(Platform::Chip8)).unwrap();
+    let keys = Keys {
+        down: Cell::new(None),
+    };
+
+    step_frame(&mut emulator, &keys);
+    assert!(emulator.is_waiting_for_key());
+
+    // pressed once waiting, the state is saved before it is released
+    keys.down.set(Some(4));
+    step_frame(&mut emulator, &keys);
+
+    let state = emulator.save_state();
+    let mut other = Emulator::from_bytes(&ROM, Config::new(Platform::Chip8)).unwrap();
+    other.load_state(&state).unwrap();
+
+    assert!(other.is_waiting_for_key());
+
+    keys.down.set(None);
+    step_frame(&mut other, &keys);
+
+    assert!(!other.is_waiting_for_key());
+    assert_eq!(other.registers().get_vx(1), 4);
+}
+
+#[test]
+fn save_states_keep_the_keys_held() {
+    let mut emulator = Emulator::from_bytes(&ROM, Config::new(Platform::Chip8)).unwrap();
+    let keys = Keys {
+        down: Cell::new(Some(4)),
+    };
+
+    // held before the wait, which a loaded state must still tell
+    step_frame(&mut emulator, &keys);
+    step_frame(&mut emulator, &keys);
+
+    let state = emulator.save_state();
+    let mut other = Emulator::from_bytes(&ROM, Config::new(Platform::Chip8)).unwrap();
+    other.load_state(&state).unwrap();
+
+    keys.down.set(None);
+    step_frame(&mut other, &keys);
+
+    assert!(other.is_waiting_for_key());
+    assert_eq!(other.registers().get_vx(1), 0);
+}
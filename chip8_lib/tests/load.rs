@@ -0,0 +1,60 @@
+// Creating emulators from roms, and the roms that can't be loaded
+
+use chip8_lib::config::Config;
+use chip8_lib::emulator::Emulator;
+use chip8_lib::error::LoadError;
+use chip8_lib::platform::Platform;
+
+#[test]
+fn empty_rom() {
+    let result = Emulator::from_bytes(&[], Config::new(Platform::Chip8));
+
+    assert!(matches!(result, Err(LoadError::EmptyRom)));
+}
+
+#[test]
+fn rom_size_depends_on_the_platform() {
+    // everything from 0x200 to the end of memory
+    for (platform, max_size) in [
+        (Platform::Chip8, 0xE00),
+        (Platform::SuperChip, 0xE00),
+        (Platform::XoChip, 0xFE00),
+    ] {
+        let rom = vec![0; max_size];
+
+        assert!(Emulator::from_bytes(&rom, Config::new(platform)).is_ok());
+
+        let rom = vec![0; max_size + 1];
+
+        match Emulator::from_bytes(&rom, Config::new(platform)) {
+            Err(LoadError::RomTooLarge {
+                size,
+                max_size: max,
+            }) => {
+                assert_eq!((size, max), (max_size + 1, max_size));
+            }
+            _ => panic!("a rom of {} bytes loaded on {:?}", max_size + 1, platform),
+        }
+    }
+}
+
+#[test]
+fn zero_cpu_speed() {
+    let result = Emulator::from_bytes(&[0x00, 0xE0], Config::default().with_cpu_hz(0));
+
+    assert!(matches!(result, Err(LoadError::ZeroCpuSpeed)));
+}
+
+#[test]
+fn load_errors_are_readable() {
+    let error = LoadError::RomTooLarge {
+        size: 4000,
+        max_size: 3584,
+    };
+
+    assert_eq!(
+        error.to_string(),
+        "rom is too large: 4000 bytes, at most 3584 bytes fit in memory"
+    );
+    assert_eq!(LoadError::EmptyRom.to_string(), "rom is empty");
+}
@@ -0,0 +1,112 @@
+// Movies recorded from a run and played back on a new emulator
+
+use chip8_lib::config::Config;
+use chip8_lib::emulator::Emulator;
+use chip8_lib::error::MovieError;
+use chip8_lib::headless::{CaptureRenderer, NullSynthetizer};
+use chip8_lib::interfaces::Controller;
+use chip8_lib::movie::{Movie, Playback};
+use chip8_lib::platform::Platform;
+use chip8_lib::random::Generator;
+use std::cell::Cell;
+
+const FRAMES: u32 = 120;
+
+// V0 := random, V1 += V0, V4 += 1 while key 0 is held, loop
+const ROM: [u8; 12] = [
+    0xC0, 0xFF, 0x81, 0x04, 0xE3, 0x9E, 0x12, 0x00, 0x74, 0x01, 0x12, 0x00,
+];
+
+// holds key 0 on every third frame
+struct Pattern {
+    frame: Cell<u32>,
+}
+
+impl Controller for Pattern {
+    fn is_key_down(&self, key_index: usize) -> bool {
+        key_index == 0 && self.frame.get().is_multiple_of(3)
+    }
+}
+
+fn step_frame(emulator: &mut Emulator, controller: &impl Controller) {
+    emulator.step_frame(
+        controller,
+        &mut CaptureRenderer::new(),
+        &mut NullSynthetizer,
+    );
+}
+
+fn record(config: Config) -> (Movie, Vec<u8>) {
+    let mut emulator = Emulator::from_bytes(&ROM, config).unwrap();
+    let mut movie = Movie::new(&emulator);
+    let controller = Pattern {
+        frame: Cell::new(0),
+    };
+
+    for frame in 0..FRAMES {
+        controller.frame.set(frame);
+        movie.record_frame(&controller);
+        step_frame(&mut emulator, &controller);
+    }
+
+    (movie, emulator.save_state())
+}
+
+fn play(movie: Movie) -> Vec<u8> {
+    movie.check_rom(&ROM).unwrap();
+
+    let mut emulator = Emulator::from_bytes(&ROM, *movie.config()).unwrap();
+    let mut playback = Playback::new(movie);
+
+    while !playback.is_finished() {
+        step_frame(&mut emulator, &playback);
+        playback.next_frame();
+    }
+
+    emulator.save_state()
+}
+
+#[test]
+fn playback_reproduces_the_run() {
+    // no seed given, the one picked is recorded
+    let (movie, state) = record(Config::new(Platform::Chip8));
+    let movie = Movie::from_bytes(&movie.to_bytes()).unwrap();
+
+    assert_eq!(movie.frames().len(), FRAMES as usize);
+    assert_eq!(movie.frames()[3], 0b1);
+    assert_eq!(play(movie), state);
+}
+
+#[test]
+fn seed_decides_the_random_numbers() {
+    let (_, state) = record(Config::new(Platform::Chip8).with_seed(1));
+    let (_, same_state) = record(Config::new(Platform::Chip8).with_seed(1));
+    let (_, other_state) = record(Config::new(Platform::Chip8).with_seed(2));
+
+    assert_eq!(state, same_state);
+    assert_ne!(state, other_state);
+}
+
+#[test]
+fn movies_keep_the_generator() {
+    let config = Config::new(Platform::Chip8).with_generator(Generator::PageSum);
+    let (movie, state) = record(config);
+    let movie = Movie::from_bytes(&movie.to_bytes()).unwrap();
+
+    assert_eq!(movie.config().generator, Generator::PageSum);
+    assert_eq!(play(movie), state);
+}
+
+#[test]
+fn invalid_movies() {
+    let (movie, _) = record(Config::new(Platform::XoChip).with_seed(7));
+    let bytes = movie.to_bytes();
+
+    assert_eq!(Movie::from_bytes(&bytes).unwrap(), movie);
+    assert_eq!(movie.check_rom(&[0x12, 0x00]), Err(MovieError::RomMismatch));
+    assert_eq!(
+        Movie::from_bytes(&bytes[..bytes.len() - 1]),
+        Err(MovieError::Truncated)
+    );
+    assert_eq!(Movie::from_bytes(b"C8SS"), Err(MovieError::InvalidMagic));
+}
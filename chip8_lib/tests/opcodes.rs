@@ -0,0 +1,930 @@
+// Effects of each opcode on the registers, flags, memory and PC, run on a TestMachine
+
+use chip8_lib::platform::Platform;
+use chip8_lib::quirks::Quirks;
+use chip8_lib::test_util::TestMachine;
+
+fn schip() -> TestMachine {
+    TestMachine::for_platform(Platform::SuperChip)
+}
+
+fn xo_chip() -> TestMachine {
+    TestMachine::for_platform(Platform::XoChip)
+}
+
+// 00cn, 00dn, 00e0, 00ee, 00fb, 00fc, 00fd, 00fe, 00ff
+
+#[test]
+fn scroll_down() {
+    // draws a single pixel at (0, 0), then scrolls 3 lines down
+    let machine = schip()
+        .with_i(0x300)
+        .with_memory(0x300, &[0x80])
+        .execute(0xD011)
+        .execute(0x00C3);
+
+    assert_eq!(machine.pixel(0, 0), 0);
+    assert_eq!(machine.pixel(0, 3), 1);
+}
+
+#[test]
+fn scroll_up() {
+    let machine = xo_chip()
+        .with_registers(&[0, 5])
+        .with_i(0x300)
+        .with_memory(0x300, &[0x80])
+        .execute(0xD011)
+        .execute(0x00D2);
+
+    assert_eq!(machine.pixel(0, 5), 0);
+    assert_eq!(machine.pixel(0, 3), 1);
+}
+
+#[test]
+fn scroll_right_and_left() {
+    let machine = schip()
+        .with_registers(&[8])
+        .with_i(0x300)
+        .with_memory(0x300, &[0x80])
+        .execute(0xD011)
+        .execute(0x00FB);
+
+    assert_eq!(machine.pixel(12, 0), 1);
+
+    let machine = machine.execute(0x00FC).execute(0x00FC);
+
+    assert_eq!(machine.pixel(4, 0), 1);
+    assert_eq!(machine.lit_pixels(), 1);
+}
+
+#[test]
+fn clear_screen() {
+    let machine = TestMachine::new()
+        .with_i(0x300)
+        .with_memory(0x300, &[0xFF])
+        .execute(0xD011);
+
+    assert_eq!(machine.lit_pixels(), 8);
+
+    let machine = machine.execute(0x00E0);
+
+    assert_eq!(machine.lit_pixels(), 0);
+}
+
+#[test]
+fn clear_screen_only_clears_selected_planes() {
+    let machine = xo_chip()
+        .with_selected_planes(3)
+        .with_i(0x300)
+        .with_memory(0x300, &[0x80, 0x80])
+        .execute(0xD011)
+        .execute(0xF101)
+        .execute(0x00E0);
+
+    assert_eq!(machine.pixel(0, 0), 2);
+}
+
+#[test]
+fn return_from_subroutine() {
+    let machine = TestMachine::new()
+        .with_stack(&[0x250, 0x340])
+        .execute(0x00EE);
+
+    assert_eq!(machine.pc(), 0x340);
+    assert_eq!(machine.stack_pointer(), 1);
+}
+
+#[test]
+fn exit() {
+    let machine = schip().execute(0x00FD);
+
+    assert!(machine.has_exited());
+
+    // nothing runs once exited
+    let machine = machine.execute(0x6012);
+
+    assert_eq!(machine.vx(0), 0);
+}
+
+#[test]
+fn high_and_low_resolution() {
+    let machine = schip().execute(0x00FF);
+
+    assert_eq!((machine.screen_width(), machine.screen_height()), (128, 64));
+
+    let machine = machine.execute(0x00FE);
+
+    assert_eq!((machine.screen_width(), machine.screen_height()), (64, 32));
+}
+
+// 1nnn, 2nnn, bnnn
+
+#[test]
+fn jump() {
+    let machine = TestMachine::new().execute(0x1ABC);
+
+    assert_eq!(machine.pc(), 0xABC);
+}
+
+#[test]
+fn call_then_return() {
+    let machine = TestMachine::new().execute(0x2400);
+
+    assert_eq!(machine.pc(), 0x400);
+    assert_eq!(machine.stack_pointer(), 1);
+
+    let machine = machine.execute(0x00EE);
+
+    assert_eq!(machine.pc(), 0x202);
+    assert_eq!(machine.stack_pointer(), 0);
+}
+
+#[test]
+fn call_then_return_above_0x1000() {
+    // the return address is past the 4 KiB of CHIP-8 memory
+    let machine = xo_chip().with_pc(0x1FFE).execute(0x2400);
+
+    assert_eq!(machine.pc(), 0x400);
+
+    let machine = machine.execute(0x00EE);
+
+    assert_eq!(machine.pc(), 0x2000);
+}
+
+#[test]
+fn jump_with_v0_offset() {
+    let machine = TestMachine::new()
+        .with_registers(&[0x10, 0, 0x20])
+        .execute(0xB234);
+
+    assert_eq!(machine.pc(), 0x244);
+}
+
+#[test]
+fn jump_with_v0_offset_wraps_around_memory() {
+    let machine = TestMachine::new().with_registers(&[0x10]).execute(0xBFF8);
+
+    assert_eq!(machine.pc(), 0x008);
+}
+
+#[test]
+fn jump_with_vx_offset_with_jumping_quirk() {
+    let machine = schip().with_registers(&[0x10, 0, 0x20]).execute(0xB234);
+
+    assert_eq!(machine.pc(), 0x254);
+}
+
+// 3xnn, 4xnn, 5xy0, 9xy0
+
+#[test]
+fn skip_if_equal_to_byte() {
+    let machine = TestMachine::new()
+        .with_registers(&[0, 0x42])
+        .execute(0x3142);
+
+    assert_eq!(machine.pc(), 0x204);
+
+    let machine = TestMachine::new()
+        .with_registers(&[0, 0x42])
+        .execute(0x3143);
+
+    assert_eq!(machine.pc(), 0x202);
+}
+
+#[test]
+fn skip_if_not_equal_to_byte() {
+    let machine = TestMachine::new()
+        .with_registers(&[0, 0x42])
+        .execute(0x4143);
+
+    assert_eq!(machine.pc(), 0x204);
+
+    let machine = TestMachine::new()
+        .with_registers(&[0, 0x42])
+        .execute(0x4142);
+
+    assert_eq!(machine.pc(), 0x202);
+}
+
+#[test]
+fn skip_if_registers_equal() {
+    let machine = TestMachine::new()
+        .with_registers(&[7, 7, 8])
+        .execute(0x5010);
+
+    assert_eq!(machine.pc(), 0x204);
+
+    let machine = TestMachine::new()
+        .with_registers(&[7, 7, 8])
+        .execute(0x5020);
+
+    assert_eq!(machine.pc(), 0x202);
+}
+
+#[test]
+fn skip_if_registers_not_equal() {
+    let machine = TestMachine::new()
+        .with_registers(&[7, 7, 8])
+        .execute(0x9020);
+
+    assert_eq!(machine.pc(), 0x204);
+
+    let machine = TestMachine::new()
+        .with_registers(&[7, 7, 8])
+        .execute(0x9010);
+
+    assert_eq!(machine.pc(), 0x202);
+}
+
+#[test]
+fn skip_over_long_load_of_i() {
+    // f000 nnnn is 4 bytes long
+    let machine = xo_chip().with_memory(0x202, &[0xF0, 0x00]).execute(0x3000);
+
+    assert_eq!(machine.pc(), 0x206);
+}
+
+// 5xy2, 5xy3
+
+#[test]
+fn save_register_range() {
+    let machine = xo_chip()
+        .with_registers(&[1, 2, 3, 4])
+        .with_i(0x300)
+        .execute(0x5132);
+
+    assert_eq!(
+        [
+            machine.byte(0x300),
+            machine.byte(0x301),
+            machine.byte(0x302)
+        ],
+        [2, 3, 4]
+    );
+    assert_eq!(machine.byte(0x303), 0);
+    assert_eq!(machine.i(), 0x300);
+}
+
+#[test]
+fn save_register_range_in_reverse_order() {
+    let machine = xo_chip()
+        .with_registers(&[1, 2, 3, 4])
+        .with_i(0x300)
+        .execute(0x5312);
+
+    assert_eq!(
+        [
+            machine.byte(0x300),
+            machine.byte(0x301),
+            machine.byte(0x302)
+        ],
+        [4, 3, 2]
+    );
+}
+
+#[test]
+fn load_register_range() {
+    let machine = xo_chip()
+        .with_i(0x300)
+        .with_memory(0x300, &[9, 8, 7])
+        .execute(0x5243)
+        .execute(0x5A83);
+
+    assert_eq!([machine.vx(2), machine.vx(3), machine.vx(4)], [9, 8, 7]);
+    assert_eq!(
+        [machine.vx(0xA), machine.vx(0x9), machine.vx(0x8)],
+        [9, 8, 7]
+    );
+    assert_eq!(machine.i(), 0x300);
+}
+
+// 6xnn, 7xnn
+
+#[test]
+fn load_byte() {
+    let machine = TestMachine::new().execute(0x6A5C);
+
+    assert_eq!(machine.vx(0xA), 0x5C);
+}
+
+#[test]
+fn add_byte_wraps_without_carry() {
+    let machine = TestMachine::new()
+        .with_register(3, 0xFF)
+        .with_register(0xF, 0x12)
+        .execute(0x7302);
+
+    assert_eq!(machine.vx(3), 0x01);
+    assert_eq!(machine.vx(0xF), 0x12);
+}
+
+// 8xy0 - 8xye
+
+#[test]
+fn load_register() {
+    let machine = TestMachine::new()
+        .with_registers(&[0, 0x33])
+        .execute(0x8010);
+
+    assert_eq!(machine.vx(0), 0x33);
+}
+
+#[test]
+fn logical_operations() {
+    let registers = [0b1100, 0b1010];
+
+    let machine = TestMachine::new()
+        .with_registers(&registers)
+        .execute(0x8011);
+    assert_eq!(machine.vx(0), 0b1110);
+
+    let machine = TestMachine::new()
+        .with_registers(&registers)
+        .execute(0x8012);
+    assert_eq!(machine.vx(0), 0b1000);
+
+    let machine = TestMachine::new()
+        .with_registers(&registers)
+        .execute(0x8013);
+    assert_eq!(machine.vx(0), 0b0110);
+}
+
+#[test]
+fn logical_operations_reset_vf_with_vf_reset_quirk() {
+    for op_code in [0x8011, 0x8012, 0x8013] {
+        let machine = TestMachine::new()
+            .with_registers(&[1, 1])
+            .with_register(0xF, 0x55)
+            .execute(op_code);
+
+        assert_eq!(machine.vx(0xF), 0, "{:#06x}", op_code);
+
+        let machine = TestMachine::new()
+            .with_quirks(Quirks::super_chip())
+            .with_registers(&[1, 1])
+            .with_register(0xF, 0x55)
+            .execute(op_code);
+
+        assert_eq!(machine.vx(0xF), 0x55, "{:#06x}", op_code);
+    }
+}
+
+#[test]
+fn add_registers_sets_carry() {
+    let machine = TestMachine::new()
+        .with_registers(&[0xF0, 0x20])
+        .execute(0x8014);
+
+    assert_eq!(machine.vx(0), 0x10);
+    assert_eq!(machine.vx(0xF), 1);
+
+    let machine = TestMachine::new()
+        .with_registers(&[0x10, 0x20])
+        .execute(0x8014);
+
+    assert_eq!(machine.vx(0), 0x30);
+    assert_eq!(machine.vx(0xF), 0);
+}
+
+#[test]
+fn subtract_registers_sets_not_borrow() {
+    let machine = TestMachine::new()
+        .with_registers(&[0x30, 0x20])
+        .execute(0x8015);
+
+    assert_eq!(machine.vx(0), 0x10);
+    assert_eq!(machine.vx(0xF), 1);
+
+    let machine = TestMachine::new()
+        .with_registers(&[0x20, 0x30])
+        .execute(0x8015);
+
+    assert_eq!(machine.vx(0), 0xF0);
+    assert_eq!(machine.vx(0xF), 0);
+
+    // equal values don't borrow
+    let machine = TestMachine::new()
+        .with_registers(&[0x20, 0x20])
+        .execute(0x8015);
+
+    assert_eq!(machine.vx(0), 0);
+    assert_eq!(machine.vx(0xF), 1);
+}
+
+#[test]
+fn subtract_reversed_sets_not_borrow() {
+    let machine = TestMachine::new()
+        .with_registers(&[0x20, 0x30])
+        .execute(0x8017);
+
+    assert_eq!(machine.vx(0), 0x10);
+    assert_eq!(machine.vx(0xF), 1);
+
+    let machine = TestMachine::new()
+        .with_registers(&[0x30, 0x20])
+        .execute(0x8017);
+
+    assert_eq!(machine.vx(0), 0xF0);
+    assert_eq!(machine.vx(0xF), 0);
+}
+
+#[test]
+fn shift_right_shifts_vy() {
+    let machine = TestMachine::new()
+        .with_registers(&[0, 0b101])
+        .execute(0x8016);
+
+    assert_eq!(machine.vx(0), 0b10);
+    assert_eq!(machine.vx(1), 0b101);
+    assert_eq!(machine.vx(0xF), 1);
+}
+
+#[test]
+fn shift_right_shifts_vx_with_shifting_quirk() {
+    let machine = schip().with_registers(&[0b100, 0b101]).execute(0x8016);
+
+    assert_eq!(machine.vx(0), 0b10);
+    assert_eq!(machine.vx(0xF), 0);
+}
+
+#[test]
+fn shift_left_shifts_vy() {
+    let machine = TestMachine::new()
+        .with_registers(&[0, 0x81])
+        .execute(0x801E);
+
+    assert_eq!(machine.vx(0), 0x02);
+    assert_eq!(machine.vx(0xF), 1);
+}
+
+#[test]
+fn shift_left_shifts_vx_with_shifting_quirk() {
+    let machine = schip().with_registers(&[0x41, 0x81]).execute(0x801E);
+
+    assert_eq!(machine.vx(0), 0x82);
+    assert_eq!(machine.vx(0xF), 0);
+}
+
+// the flag is written last, and wins over the result when VF is the destination
+#[test]
+fn vf_as_destination_holds_the_flag() {
+    let machine = TestMachine::new()
+        .with_register(0xF, 0xFF)
+        .with_register(1, 0x02)
+        .execute(0x8F14);
+
+    assert_eq!(machine.vx(0xF), 1);
+
+    let machine = TestMachine::new()
+        .with_register(0xF, 0x10)
+        .with_register(1, 0x20)
+        .execute(0x8F15);
+
+    assert_eq!(machine.vx(0xF), 0);
+
+    let machine = TestMachine::new().with_register(1, 0x03).execute(0x8F16);
+
+    assert_eq!(machine.vx(0xF), 1);
+
+    let machine = TestMachine::new().with_register(1, 0x40).execute(0x8F1E);
+
+    assert_eq!(machine.vx(0xF), 0);
+}
+
+// the operands are read before the flag is written when VF is the source
+#[test]
+fn vf_as_source_is_read_before_the_flag() {
+    let machine = TestMachine::new()
+        .with_register(0, 0xFF)
+        .with_register(0xF, 0x01)
+        .execute(0x80F4);
+
+    assert_eq!(machine.vx(0), 0x00);
+    assert_eq!(machine.vx(0xF), 1);
+
+    let machine = TestMachine::new()
+        .with_register(0, 0x05)
+        .with_register(0xF, 0x03)
+        .execute(0x80F5);
+
+    assert_eq!(machine.vx(0), 0x02);
+    assert_eq!(machine.vx(0xF), 1);
+}
+
+// annn, cxnn
+
+#[test]
+fn load_i() {
+    let machine = TestMachine::new().execute(0xA123);
+
+    assert_eq!(machine.i(), 0x123);
+}
+
+#[test]
+fn random_is_masked() {
+    let machine = TestMachine::new()
+        .with_random_bytes(&[0xAB])
+        .with_register(0, 0xFF)
+        .execute(0xC000);
+
+    assert_eq!(machine.vx(0), 0);
+
+    let machine = TestMachine::new()
+        .with_random_bytes(&[0xAB])
+        .execute(0xC00F);
+
+    assert_eq!(machine.vx(0), 0x0B);
+}
+
+// dxyn
+
+#[test]
+fn draw_sprite() {
+    let machine = TestMachine::new()
+        .with_registers(&[2, 3])
+        .with_i(0x300)
+        .with_memory(0x300, &[0b1010_0000, 0b0100_0000])
+        .execute(0xD012);
+
+    assert_eq!(machine.pixel(2, 3), 1);
+    assert_eq!(machine.pixel(3, 3), 0);
+    assert_eq!(machine.pixel(4, 3), 1);
+    assert_eq!(machine.pixel(3, 4), 1);
+    assert_eq!(machine.lit_pixels(), 3);
+    assert_eq!(machine.vx(0xF), 0);
+    assert!(machine.is_waiting_for_vblank());
+}
+
+#[test]
+fn draw_sprite_sets_vf_on_collision() {
+    let machine = TestMachine::new()
+        .with_i(0x300)
+        .with_memory(0x300, &[0xC0])
+        .execute(0xD011)
+        .execute(0xD011);
+
+    assert_eq!(machine.lit_pixels(), 0);
+    assert_eq!(machine.vx(0xF), 1);
+}
+
+#[test]
+fn draw_sprite_with_vf_as_coordinate() {
+    let machine = TestMachine::new()
+        .with_register(0xF, 4)
+        .with_i(0x300)
+        .with_memory(0x300, &[0x80])
+        .execute(0xDFF1);
+
+    assert_eq!(machine.pixel(4, 4), 1);
+    assert_eq!(machine.vx(0xF), 0);
+}
+
+#[test]
+fn draw_sprite_clips_at_the_edges_with_clipping_quirk() {
+    let machine = TestMachine::new()
+        .with_registers(&[60, 31])
+        .with_i(0x300)
+        .with_memory(0x300, &[0xFF, 0xFF])
+        .execute(0xD012);
+
+    assert_eq!(machine.lit_pixels(), 4);
+    assert_eq!(machine.pixel(0, 0), 0);
+}
+
+#[test]
+fn draw_sprite_wraps_around_without_clipping_quirk() {
+    let machine = xo_chip()
+        .with_registers(&[60, 31])
+        .with_i(0x300)
+        .with_memory(0x300, &[0xFF, 0xFF])
+        .execute(0xD012);
+
+    assert_eq!(machine.lit_pixels(), 16);
+    assert_eq!(machine.pixel(0, 0), 1);
+}
+
+#[test]
+fn draw_sprite_starting_off_screen_wraps() {
+    let machine = TestMachine::new()
+        .with_registers(&[64 + 2, 32 + 1])
+        .with_i(0x300)
+        .with_memory(0x300, &[0x80])
+        .execute(0xD011);
+
+    assert_eq!(machine.pixel(2, 1), 1);
+}
+
+#[test]
+fn draw_big_sprite() {
+    let sprite = [0xFF; 32];
+    let machine = schip()
+        .execute(0x00FF)
+        .with_i(0x300)
+        .with_memory(0x300, &sprite)
+        .execute(0xD010);
+
+    assert_eq!(machine.lit_pixels(), 256);
+    assert_eq!(machine.pixel(15, 15), 1);
+    assert_eq!(machine.pixel(16, 0), 0);
+}
+
+#[test]
+fn draw_sprite_on_both_planes() {
+    // plane 1 data is followed by plane 2 data
+    let machine = xo_chip()
+        .with_selected_planes(3)
+        .with_i(0x300)
+        .with_memory(0x300, &[0xC0, 0x80])
+        .execute(0xD011);
+
+    assert_eq!(machine.pixel(0, 0), 3);
+    assert_eq!(machine.pixel(1, 0), 1);
+    assert_eq!(machine.i(), 0x300);
+}
+
+#[test]
+fn draw_sprite_does_not_wait_without_display_wait_quirk() {
+    let machine = schip()
+        .with_i(0x300)
+        .with_memory(0x300, &[0x80])
+        .execute(0xD011);
+
+    assert!(!machine.is_waiting_for_vblank());
+}
+
+// ex9e, exa1
+
+#[test]
+fn skip_if_key_down() {
+    let machine = TestMachine::new()
+        .with_registers(&[0xA])
+        .with_keys_down(&[0xA])
+        .execute(0xE09E);
+
+    assert_eq!(machine.pc(), 0x204);
+
+    let machine = TestMachine::new().with_registers(&[0xA]).execute(0xE09E);
+
+    assert_eq!(machine.pc(), 0x202);
+}
+
+#[test]
+fn skip_if_key_up() {
+    let machine = TestMachine::new().with_registers(&[0xA]).execute(0xE0A1);
+
+    assert_eq!(machine.pc(), 0x204);
+
+    let machine = TestMachine::new()
+        .with_registers(&[0xA])
+        .with_keys_down(&[0xA])
+        .execute(0xE0A1);
+
+    assert_eq!(machine.pc(), 0x202);
+}
+
+// f000, fn01, f002, fx3a
+
+#[test]
+fn load_long_i() {
+    let machine = xo_chip().with_memory(0x202, &[0xBE, 0xEF]).execute(0xF000);
+
+    assert_eq!(machine.i(), 0xBEEF);
+    assert_eq!(machine.pc(), 0x204);
+}
+
+#[test]
+fn select_planes() {
+    let machine = xo_chip().execute(0xF201);
+
+    assert_eq!(machine.selected_planes(), 2);
+}
+
+#[test]
+fn load_audio_pattern_and_pitch() {
+    let pattern: Vec<u8> = (0..16).collect();
+    let machine = xo_chip()
+        .with_registers(&[100])
+        .with_i(0x300)
+        .with_memory(0x300, &pattern)
+        .execute(0xF002)
+        .execute(0xF03A);
+
+    assert_eq!(
+        machine.audio_pattern().unwrap().as_slice(),
+        pattern.as_slice()
+    );
+    assert_eq!(machine.pitch(), 100);
+}
+
+// fx07, fx0a, fx15, fx18
+
+#[test]
+fn load_delay_timer() {
+    let machine = TestMachine::new().with_delay_timer(0x2A).execute(0xF307);
+
+    assert_eq!(machine.vx(3), 0x2A);
+}
+
+#[test]
+fn wait_for_key() {
+    let machine = TestMachine::new().execute(0xF30A).next_frame(&[]);
+
+    assert!(machine.is_waiting_for_key());
+
+    // the key is stored once released
+    let machine = machine.next_frame(&[5, 9]);
+
+    assert!(machine.is_waiting_for_key());
+
+    let machine = machine.next_frame(&[5]).next_frame(&[]);
+
+    assert!(!machine.is_waiting_for_key());
+    assert_eq!(machine.vx(3), 5);
+    assert_eq!(machine.pc(), 0x202);
+}
+
+#[test]
+fn wait_for_key_ignores_held_keys() {
+    // held when fx0a runs, then released
+    let machine = TestMachine::new()
+        .with_keys_down(&[5])
+        .execute(0xF30A)
+        .next_frame(&[5])
+        .next_frame(&[]);
+
+    assert!(machine.is_waiting_for_key());
+
+    let machine = machine.next_frame(&[5]).next_frame(&[]);
+
+    assert!(!machine.is_waiting_for_key());
+    assert_eq!(machine.vx(3), 5);
+}
+
+#[test]
+fn set_timers() {
+    let machine = TestMachine::new()
+        .with_registers(&[0x10, 0x20])
+        .execute(0xF015)
+        .execute(0xF118);
+
+    assert_eq!(machine.delay_timer(), 0x10);
+    assert_eq!(machine.sound_timer(), 0x20);
+}
+
+// fx1e, fx29, fx30, fx33
+
+#[test]
+fn add_to_i() {
+    let machine = TestMachine::new()
+        .with_registers(&[0x10])
+        .with_i(0x300)
+        .execute(0xF01E);
+
+    assert_eq!(machine.i(), 0x310);
+    assert_eq!(machine.vx(0xF), 0);
+}
+
+#[test]
+fn add_to_i_overflows_past_the_memory() {
+    let machine = TestMachine::new()
+        .with_registers(&[0x10])
+        .with_i(0xFF8)
+        .execute(0xF01E);
+
+    assert_eq!(machine.i(), 0x008);
+    assert_eq!(machine.vx(0xF), 1);
+}
+
+#[test]
+fn load_font_character() {
+    // the font is at 0x50, 5 bytes per character
+    let machine = TestMachine::new().with_registers(&[0xA]).execute(0xF029);
+
+    assert_eq!(machine.i(), 0x50 + 0xA * 5);
+    assert_eq!(machine.byte(machine.i() as usize), 0xF0);
+    assert_eq!(machine.byte(machine.i() as usize + 4), 0x90);
+}
+
+#[test]
+fn load_font_character_uses_the_low_nibble() {
+    let machine = TestMachine::new().with_registers(&[0x13]).execute(0xF029);
+
+    assert_eq!(machine.i(), 0x50 + 3 * 5);
+}
+
+#[test]
+fn load_big_font_character() {
+    // the big font is at 0xa0, 10 bytes per character
+    let machine = schip().with_registers(&[0x1]).execute(0xF030);
+
+    assert_eq!(machine.i(), 0xA0 + 10);
+    assert_eq!(machine.byte(machine.i() as usize), 0x18);
+}
+
+#[test]
+fn store_bcd() {
+    let machine = TestMachine::new()
+        .with_registers(&[254])
+        .with_i(0x300)
+        .execute(0xF033);
+
+    assert_eq!(
+        [
+            machine.byte(0x300),
+            machine.byte(0x301),
+            machine.byte(0x302)
+        ],
+        [2, 5, 4]
+    );
+
+    let machine = TestMachine::new()
+        .with_registers(&[7])
+        .with_i(0x300)
+        .execute(0xF033);
+
+    assert_eq!(
+        [
+            machine.byte(0x300),
+            machine.byte(0x301),
+            machine.byte(0x302)
+        ],
+        [0, 0, 7]
+    );
+}
+
+// fx55, fx65, fx75, fx85
+
+#[test]
+fn store_registers_increments_i_with_memory_increment_quirk() {
+    let machine = TestMachine::new()
+        .with_registers(&[1, 2, 3])
+        .with_i(0x300)
+        .execute(0xF255);
+
+    assert_eq!(
+        [
+            machine.byte(0x300),
+            machine.byte(0x301),
+            machine.byte(0x302)
+        ],
+        [1, 2, 3]
+    );
+    assert_eq!(machine.i(), 0x303);
+}
+
+#[test]
+fn store_registers_leaves_i_without_memory_increment_quirk() {
+    let machine = schip()
+        .with_registers(&[1, 2, 3])
+        .with_i(0x300)
+        .execute(0xF155);
+
+    assert_eq!([machine.byte(0x300), machine.byte(0x301)], [1, 2]);
+    assert_eq!(machine.byte(0x302), 0);
+    assert_eq!(machine.i(), 0x300);
+}
+
+#[test]
+fn store_and_load_registers_advance_i_by_x_on_chip48() {
+    let machine = TestMachine::new()
+        .with_quirks(Quirks::chip48())
+        .with_registers(&[1, 2, 3])
+        .with_i(0x300)
+        .execute(0xF255);
+
+    assert_eq!(machine.i(), 0x302);
+
+    let machine = machine.execute(0xF165);
+
+    assert_eq!(machine.i(), 0x303);
+}
+
+#[test]
+fn load_registers() {
+    let machine = TestMachine::new()
+        .with_i(0x300)
+        .with_memory(0x300, &[4, 5, 6, 7])
+        .execute(0xF265);
+
+    assert_eq!([machine.vx(0), machine.vx(1), machine.vx(2)], [4, 5, 6]);
+    assert_eq!(machine.vx(3), 0);
+    assert_eq!(machine.i(), 0x303);
+
+    let machine = schip()
+        .with_i(0x300)
+        .with_memory(0x300, &[4, 5, 6, 7])
+        .execute(0xF265);
+
+    assert_eq!(machine.i(), 0x300);
+}
+
+#[test]
+fn store_and_load_rpl_flags() {
+    let machine = schip().with_registers(&[1, 2, 3]).execute(0xF275);
+
+    assert_eq!([machine.rpl_flag(0), machine.rpl_flag(1)], [1, 2]);
+
+    let machine = schip().with_rpl_flags(&[9, 8, 7]).execute(0xF185);
+
+    assert_eq!([machine.vx(0), machine.vx(1), machine.vx(2)], [9, 8, 0]);
+}
@@ -0,0 +1,111 @@
+// Random sources of cxnn
+
+use chip8_lib::config::Config;
+use chip8_lib::emulator::Emulator;
+use chip8_lib::headless::{CaptureRenderer, NullSynthetizer};
+use chip8_lib::interfaces::Controller;
+use chip8_lib::platform::Platform;
+use chip8_lib::random::{FixedSequence, Generator, PageSum, RandomSource, SeededRandom};
+
+// V0 := random, V1 := random, V2 := random, loop
+const ROM: [u8; 8] = [0xC0, 0xFF, 0xC1, 0xFF, 0xC2, 0xFF, 0x12, 0x06];
+
+struct NoKeys;
+
+impl Controller for NoKeys {
+    fn is_key_down(&self, _key_index: usize) -> bool {
+        false
+    }
+}
+
+fn draw(emulator: Emulator) -> [u8; 3] {
+    let mut emulator = emulator;
+
+    emulator.step_frame(&NoKeys, &mut CaptureRenderer::new(), &mut NullSynthetizer);
+
+    let registers = emulator.registers();
+
+    [
+        registers.get_vx(0),
+        registers.get_vx(1),
+        registers.get_vx(2),
+    ]
+}
+
+fn bytes(source: &mut impl RandomSource, count: usize) -> Vec<u8> {
+    let memory = [0; 0x1000];
+
+    (0..count).map(|_| source.next_byte(&memory)).collect()
+}
+
+#[test]
+fn fixed_sequence_loops() {
+    assert_eq!(
+        bytes(&mut FixedSequence::new([1, 2]), 5),
+        vec![1, 2, 1, 2, 1]
+    );
+    assert_eq!(bytes(&mut FixedSequence::new([]), 2), vec![0, 0]);
+}
+
+#[test]
+fn seeded_random_depends_on_the_seed() {
+    let first = bytes(&mut SeededRandom::new(1), 32);
+
+    assert_eq!(first, bytes(&mut SeededRandom::new(1), 32));
+    assert_ne!(first, bytes(&mut SeededRandom::new(2), 32));
+    // not stuck on a single value
+    assert!(first.iter().any(|byte| *byte != first[0]));
+}
+
+#[test]
+fn page_sum_reads_memory() {
+    let mut zeros = [0; 0x1000];
+    let mut source = PageSum::new(0);
+
+    // the counter alone is summed over empty memory
+    assert_eq!(source.next_byte(&zeros), 1);
+    assert_eq!(source.next_byte(&zeros), 3);
+
+    zeros[3] = 0x10;
+
+    assert_eq!(source.next_byte(&zeros), 0x16);
+}
+
+#[test]
+fn emulator_uses_the_random_source() {
+    let config = Config::new(Platform::Chip8);
+    let emulator = Emulator::from_bytes(&ROM, config)
+        .unwrap()
+        .with_random_source(FixedSequence::new([7, 8, 9]));
+
+    assert_eq!(draw(emulator), [7, 8, 9]);
+}
+
+#[test]
+fn default_source_follows_the_seed() {
+    let config = Config::new(Platform::Chip8).with_seed(42);
+
+    assert_eq!(
+        draw(Emulator::from_bytes(&ROM, config).unwrap()),
+        draw(Emulator::from_bytes(&ROM, config).unwrap())
+    );
+}
+
+#[test]
+fn config_picks_the_generator() {
+    let config = Config::new(Platform::Chip8)
+        .with_seed(0x1234)
+        .with_generator(Generator::PageSum);
+    let emulator = Emulator::from_bytes(&ROM, config).unwrap();
+
+    // the page read holds the fonts, which the rom doesn't change
+    let memory = emulator.mmu().get_memory().to_vec();
+    let mut source = PageSum::new(0x1234);
+    let expected = [
+        source.next_byte(&memory),
+        source.next_byte(&memory),
+        source.next_byte(&memory),
+    ];
+
+    assert_eq!(draw(emulator), expected);
+}
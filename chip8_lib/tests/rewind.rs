@@ -0,0 +1,136 @@
+// Rewinding snapshots stored as deltas within a memory budget
+
+use chip8_lib::config::Config;
+use chip8_lib::emulator::Emulator;
+use chip8_lib::headless::{CaptureRenderer, NullController, NullSynthetizer};
+use chip8_lib::platform::Platform;
+use chip8_lib::quirks::Quirks;
+use chip8_lib::rewind::Rewind;
+
+// V0 += 1, loop, so every frame has a different state
+const COUNTER: [u8; 4] = [0x70, 0x01, 0x12, 0x00];
+
+// high resolution, draw, low resolution, draw, loop, the display wait quirk ending a frame on every draw
+const RESOLUTIONS: [u8; 10] = [0x00, 0xFF, 0xD0, 0x00, 0x00, 0xFE, 0xD0, 0x00, 0x12, 0x00];
+
+// runs the frames, recording a snapshot after each one, and returns the state after each frame
+fn record(emulator: &mut Emulator, rewind: &mut Rewind, frames: usize) -> Vec<Vec<u8>> {
+    (0..frames)
+        .map(|_| {
+            emulator.step_frame(
+                &NullController,
+                &mut CaptureRenderer::new(),
+                &mut NullSynthetizer,
+            );
+            rewind.record(emulator);
+
+            emulator.save_state()
+        })
+        .collect()
+}
+
+fn counter() -> Emulator {
+    Emulator::from_bytes(&COUNTER, Config::new(Platform::Chip8).with_seed(0)).unwrap()
+}
+
+#[test]
+fn rewinds_every_snapshot_in_order() {
+    let mut emulator = counter();
+    let mut rewind = Rewind::new(1, usize::MAX);
+    let states = record(&mut emulator, &mut rewind, 20);
+
+    assert_eq!(rewind.len(), 20);
+
+    // the snapshot of the current frame is skipped, the first press goes back a frame
+    for state in states[..19].iter().rev() {
+        assert!(rewind.rewind(&mut emulator));
+        assert_eq!(&emulator.save_state(), state);
+    }
+
+    assert!(!rewind.rewind(&mut emulator));
+    assert!(rewind.is_empty());
+    assert_eq!(rewind.memory_used(), 0);
+}
+
+#[test]
+fn rewinds_after_running_past_the_last_snapshot() {
+    let mut emulator = counter();
+    let mut rewind = Rewind::new(4, usize::MAX);
+    let states = record(&mut emulator, &mut rewind, 10);
+
+    // snapshots of frames 4 and 8, the current state being the one of frame 10
+    assert!(rewind.rewind(&mut emulator));
+    assert_eq!(emulator.save_state(), states[7]);
+
+    assert!(rewind.rewind(&mut emulator));
+    assert_eq!(emulator.save_state(), states[3]);
+
+    assert!(!rewind.rewind(&mut emulator));
+}
+
+#[test]
+fn rewinds_across_resolution_changes() {
+    // states of both resolutions have different sizes, so their deltas hold the whole state
+    let config = Config::new(Platform::SuperChip)
+        .with_quirks(Quirks {
+            display_wait: true,
+            ..Quirks::super_chip()
+        })
+        .with_seed(0);
+    let mut emulator = Emulator::from_bytes(&RESOLUTIONS, config).unwrap();
+    let mut rewind = Rewind::new(1, usize::MAX);
+    let states = record(&mut emulator, &mut rewind, 6);
+
+    assert_ne!(states[4].len(), states[5].len());
+
+    for state in states[..5].iter().rev() {
+        assert!(rewind.rewind(&mut emulator));
+        assert_eq!(&emulator.save_state(), state);
+    }
+}
+
+#[test]
+fn deltas_are_smaller_than_states() {
+    let mut emulator = counter();
+    let mut rewind = Rewind::new(1, usize::MAX);
+    let states = record(&mut emulator, &mut rewind, 100);
+
+    // a whole state, and 99 deltas of a few bytes
+    assert!(rewind.memory_used() > states[99].len());
+    assert!(rewind.memory_used() < states[99].len() + 99 * 64);
+}
+
+#[test]
+fn oldest_snapshots_are_evicted_over_the_budget() {
+    let mut emulator = counter();
+    let budget = emulator.save_state().len() + 200;
+    let mut rewind = Rewind::new(1, budget);
+    let states = record(&mut emulator, &mut rewind, 100);
+
+    let kept = rewind.len();
+
+    assert!(kept > 1 && kept < 100);
+    assert!(rewind.memory_used() <= budget);
+
+    for _ in 1..kept {
+        assert!(rewind.rewind(&mut emulator));
+    }
+
+    // the oldest snapshot left is the last one restored
+    assert_eq!(emulator.save_state(), states[100 - kept]);
+    assert!(!rewind.rewind(&mut emulator));
+    assert_eq!(rewind.memory_used(), 0);
+}
+
+#[test]
+fn clear_forgets_everything() {
+    let mut emulator = counter();
+    let mut rewind = Rewind::new(1, usize::MAX);
+    record(&mut emulator, &mut rewind, 5);
+
+    rewind.clear();
+
+    assert!(rewind.is_empty());
+    assert_eq!(rewind.memory_used(), 0);
+    assert!(!rewind.rewind(&mut emulator));
+}
@@ -0,0 +1,138 @@
+// Save states taken mid-run and loaded back, which must carry on exactly as the run did
+
+use chip8_lib::config::Config;
+use chip8_lib::emulator::Emulator;
+use chip8_lib::error::StateError;
+use chip8_lib::headless::{CaptureRenderer, NullController, NullSynthetizer};
+use chip8_lib::platform::Platform;
+use chip8_lib::random::Generator;
+use std::time::Duration;
+
+// V0 := random, V1 += V0, DT := V1, loop
+const RANDOM_ROM: [u8; 8] = [0xC0, 0xFF, 0x81, 0x04, 0xF1, 0x15, 0x12, 0x00];
+
+// return with an empty stack
+const FAULT_ROM: [u8; 2] = [0x00, 0xEE];
+
+fn emulator(rom: &[u8], config: Config) -> Emulator {
+    Emulator::from_bytes(rom, config).unwrap()
+}
+
+fn run_for(emulator: &mut Emulator, duration: Duration) {
+    emulator.run_for(
+        duration,
+        &NullController,
+        &mut CaptureRenderer::new(),
+        &mut NullSynthetizer,
+    );
+}
+
+fn run_cycles(emulator: &mut Emulator, cycles: u64) {
+    emulator.run_cycles(
+        cycles,
+        &NullController,
+        &mut CaptureRenderer::new(),
+        &mut NullSynthetizer,
+    );
+}
+
+// runs a few uneven slices of time, leaving parts of cycles and frames over
+fn run_slices(emulator: &mut Emulator) {
+    for micros in [7_333, 1_001, 16_667, 250] {
+        run_for(emulator, Duration::from_micros(micros));
+    }
+
+    run_cycles(emulator, 3);
+}
+
+fn round_trip(config: Config) {
+    let mut emulator = emulator(&RANDOM_ROM, config);
+    run_slices(&mut emulator);
+
+    let state = emulator.save_state();
+    run_slices(&mut emulator);
+    let expected = emulator.save_state();
+
+    // loaded into a fresh emulator, which has run nothing
+    let mut loaded = self::emulator(&RANDOM_ROM, config);
+    loaded.load_state(&state).unwrap();
+    assert_eq!(loaded.save_state(), state);
+
+    run_slices(&mut loaded);
+    assert_eq!(loaded.save_state(), expected);
+    assert_eq!(loaded.get_cycle_count(), emulator.get_cycle_count());
+    assert_eq!(loaded.get_frame_count(), emulator.get_frame_count());
+}
+
+#[test]
+fn loading_reproduces_the_run() {
+    round_trip(Config::new(Platform::Chip8).with_seed(3));
+}
+
+#[test]
+fn loading_reproduces_every_generator() {
+    round_trip(
+        Config::new(Platform::Chip8)
+            .with_seed(3)
+            .with_generator(Generator::PageSum),
+    );
+}
+
+#[test]
+fn random_numbers_follow_the_state() {
+    let config = Config::new(Platform::Chip8).with_seed(9);
+    let mut emulator = emulator(&RANDOM_ROM, config);
+    run_cycles(&mut emulator, 4);
+
+    let state = emulator.save_state();
+    run_cycles(&mut emulator, 4);
+    let random = emulator.registers().get_vx(0);
+
+    // the numbers drawn since the state was saved are drawn again
+    emulator.load_state(&state).unwrap();
+    run_cycles(&mut emulator, 4);
+    assert_eq!(emulator.registers().get_vx(0), random);
+}
+
+#[test]
+fn loading_keeps_the_fault() {
+    let config = Config::new(Platform::Chip8);
+    let mut emulator = emulator(&FAULT_ROM, config);
+    run_cycles(&mut emulator, 1);
+
+    let fault = emulator.get_fault();
+    assert!(fault.is_some());
+
+    let mut loaded = self::emulator(&FAULT_ROM, config);
+    loaded.load_state(&emulator.save_state()).unwrap();
+    assert_eq!(loaded.get_fault(), fault);
+
+    // a state saved before the fault clears it
+    emulator
+        .load_state(&self::emulator(&FAULT_ROM, config).save_state())
+        .unwrap();
+    assert_eq!(emulator.get_fault(), None);
+}
+
+#[test]
+fn invalid_states() {
+    let config = Config::new(Platform::Chip8);
+    let mut emulator = emulator(&RANDOM_ROM, config);
+    let state = emulator.save_state();
+
+    let mut old_version = state.clone();
+    old_version[4] = 2;
+
+    assert_eq!(
+        emulator.load_state(&old_version),
+        Err(StateError::UnsupportedVersion(2))
+    );
+    assert_eq!(
+        emulator.load_state(&state[..state.len() - 1]),
+        Err(StateError::Truncated)
+    );
+    assert_eq!(
+        self::emulator(&FAULT_ROM, config).load_state(&state),
+        Err(StateError::RomMismatch)
+    );
+}
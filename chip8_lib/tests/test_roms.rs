@@ -0,0 +1,170 @@
+// Runs the Timendus test roms (https://github.com/Timendus/chip8-test-suite) and compares their final screen
+// against the golden screens in tests/golden
+// Set UPDATE_GOLDEN=1 to write the current screens as the new golden ones
+
+use chip8_lib::config::Config;
+use chip8_lib::emulator::Emulator;
+use chip8_lib::headless::{CaptureRenderer, NullSynthetizer};
+use chip8_lib::interfaces::Controller;
+use chip8_lib::platform::Platform;
+use std::cell::Cell;
+use std::env;
+use std::fs;
+use std::ops::Range;
+use std::path::PathBuf;
+
+// characters of the pixels by plane index
+const PIXELS: [char; 4] = ['.', '#', '+', '@'];
+
+// presses keys during the given frames
+struct ScriptedController {
+    frame: Cell<u32>,
+    script: Vec<(Range<u32>, usize)>,
+}
+
+impl Controller for ScriptedController {
+    fn is_key_down(&self, key_index: usize) -> bool {
+        let frame = self.frame.get();
+
+        self.script
+            .iter()
+            .any(|(frames, key)| *key == key_index && frames.contains(&frame))
+    }
+}
+
+fn run_rom(rom: &str, platform: Platform, frames: u32, script: &[(Range<u32>, usize)]) -> String {
+    let path = PathBuf::from(env!("CARGO_MANIFEST_DIR"))
+        .join("../roms/test")
+        .join(rom);
+    let mut emulator = Emulator::from_file(&path, Config::new(platform)).unwrap();
+
+    let controller = ScriptedController {
+        frame: Cell::new(0),
+        script: script.to_vec(),
+    };
+    let mut renderer = CaptureRenderer::new();
+    let mut synthetizer = NullSynthetizer;
+
+    for frame in 0..frames {
+        controller.frame.set(frame);
+        emulator.step_frame(&controller, &mut renderer, &mut synthetizer);
+    }
+
+    renderer
+        .get_screen()
+        .chunks(renderer.get_width())
+        .map(|row| {
+            let mut line: String = row.iter().map(|pixel| PIXELS[*pixel as usize]).collect();
+            line.push('\n');
+            line
+        })
+        .collect()
+}
+
+fn assert_golden(name: &str, screen: &str) {
+    let path = PathBuf::from(env!("CARGO_MANIFEST_DIR"))
+        .join("tests/golden")
+        .join(format!("{}.txt", name));
+
+    if env::var_os("UPDATE_GOLDEN").is_some() {
+        fs::write(&path, screen).unwrap();
+        return;
+    }
+
+    let golden =
+        fs::read_to_string(&path).unwrap_or_else(|error| panic!("{}: {}", path.display(), error));
+
+    if golden == screen {
+        return;
+    }
+
+    // mark the pixels that differ with an X
+    let mut differences = 0;
+    let diff: String = golden
+        .lines()
+        .zip(screen.lines())
+        .map(|(expected, actual)| {
+            let mut line: String = expected
+                .chars()
+                .zip(actual.chars())
+                .map(|(expected, actual)| {
+                    if expected == actual {
+                        expected
+                    } else {
+                        differences += 1;
+                        'X'
+                    }
+                })
+                .collect();
+            line.push('\n');
+            line
+        })
+        .collect();
+
+    panic!(
+        "{}: screen differs from the golden one ({} pixels, {}x{} expected, {}x{} actual)\n{}",
+        name,
+        differences,
+        golden.lines().next().map_or(0, str::len),
+        golden.lines().count(),
+        screen.lines().next().map_or(0, str::len),
+        screen.lines().count(),
+        diff
+    );
+}
+
+#[test]
+fn chip8_logo() {
+    let screen = run_rom("1-chip8-logo.ch8", Platform::Chip8, 60, &[]);
+
+    assert_golden("1-chip8-logo", &screen);
+}
+
+#[test]
+fn ibm_logo() {
+    let screen = run_rom("2-ibm-logo.ch8", Platform::Chip8, 60, &[]);
+
+    assert_golden("2-ibm-logo", &screen);
+}
+
+#[test]
+fn corax_plus() {
+    let screen = run_rom("3-corax+.ch8", Platform::Chip8, 120, &[]);
+
+    assert_golden("3-corax+", &screen);
+}
+
+#[test]
+fn flags() {
+    let screen = run_rom("4-flags.ch8", Platform::Chip8, 240, &[]);
+
+    assert_golden("4-flags", &screen);
+}
+
+// the quirks rom asks for the platform to test, 1 being CHIP-8, 2 SUPER-CHIP and 3 XO-CHIP
+#[test]
+fn quirks_chip8() {
+    let screen = run_rom("5-quirks.ch8", Platform::Chip8, 1200, &[(60..64, 1)]);
+
+    assert_golden("5-quirks-chip8", &screen);
+}
+
+// SUPER-CHIP then asks for the modern or legacy behaviour, 1 being modern
+#[test]
+fn quirks_super_chip() {
+    let screen = run_rom(
+        "5-quirks.ch8",
+        Platform::SuperChip,
+        1200,
+        &[(60..64, 2), (160..168, 1)],
+    );
+
+    assert_golden("5-quirks-super-chip", &screen);
+}
+
+#[test]
+fn quirks_xo_chip() {
+    let screen = run_rom("5-quirks.ch8", Platform::XoChip, 1200, &[(60..64, 3)]);
+
+    assert_golden("5-quirks-xo-chip", &screen);
+}
@@ -0,0 +1,201 @@
+// Emulated time: cpu speed, frames and the 60 Hz timers
+
+use chip8_lib::config::Config;
+use chip8_lib::emulator::Emulator;
+use chip8_lib::error::LoadError;
+use chip8_lib::headless::{CaptureRenderer, NullController, NullSynthetizer};
+use chip8_lib::platform::Platform;
+use chip8_lib::quirks::Quirks;
+use chip8_lib::timing::{Timing, VIP_CYCLES_PER_SECOND};
+use std::time::Duration;
+
+// V0 := 60, DT := V0, loop
+const TIMER_ROM: [u8; 6] = [0x60, 0x3C, 0xF0, 0x15, 0x12, 0x04];
+
+// draws a sprite, loop
+const DRAW_ROM: [u8; 4] = [0xD0, 0x01, 0x12, 0x00];
+
+fn emulator(rom: &[u8], config: Config) -> Emulator {
+    Emulator::from_bytes(rom, config).unwrap()
+}
+
+fn run_for(emulator: &mut Emulator, duration: Duration) {
+    emulator.run_for(
+        duration,
+        &NullController,
+        &mut CaptureRenderer::new(),
+        &mut NullSynthetizer,
+    );
+}
+
+fn run_cycles(emulator: &mut Emulator, cycles: u64) {
+    emulator.run_cycles(
+        cycles,
+        &NullController,
+        &mut CaptureRenderer::new(),
+        &mut NullSynthetizer,
+    );
+}
+
+#[test]
+fn timers_ignore_how_time_is_split() {
+    let config = Config::new(Platform::Chip8);
+
+    let mut whole = emulator(&TIMER_ROM, config);
+    run_for(&mut whole, Duration::from_millis(500));
+
+    let mut chunks = emulator(&TIMER_ROM, config);
+    for _ in 0..71 {
+        run_for(&mut chunks, Duration::from_millis(7));
+    }
+    run_for(&mut chunks, Duration::from_millis(3));
+
+    for emulator in [whole, chunks] {
+        assert_eq!(emulator.get_cycle_count(), 300);
+        assert_eq!(emulator.get_frame_count(), 30);
+        assert_eq!(emulator.mmu().get_delay_timer(), 30);
+    }
+}
+
+#[test]
+fn frames_follow_the_cpu_speed() {
+    let mut emulator = emulator(&TIMER_ROM, Config::new(Platform::Chip8).with_cpu_hz(1000));
+    let mut renderer = CaptureRenderer::new();
+
+    // 1000 / 60 instructions per frame, rounded so that frames never drift
+    let cycles: Vec<u64> = (0..3)
+        .map(|_| {
+            emulator.step_frame(&NullController, &mut renderer, &mut NullSynthetizer);
+            emulator.get_cycle_count()
+        })
+        .collect();
+
+    assert_eq!(cycles, vec![17, 34, 50]);
+
+    run_cycles(&mut emulator, 950);
+
+    assert_eq!(emulator.get_frame_count(), 60);
+}
+
+#[test]
+fn slow_instructions_last_several_frames() {
+    let mut emulator = emulator(&TIMER_ROM, Config::new(Platform::Chip8).with_cpu_hz(30));
+
+    for _ in 0..10 {
+        emulator.step_instruction(
+            &NullController,
+            &mut CaptureRenderer::new(),
+            &mut NullSynthetizer,
+        );
+    }
+
+    assert_eq!(emulator.get_frame_count(), 20);
+}
+
+#[test]
+fn display_wait_idles_until_the_frame_ends() {
+    let config = Config::new(Platform::Chip8).with_quirks(Quirks::cosmac_vip());
+    let mut emulator = emulator(&DRAW_ROM, config);
+
+    run_cycles(&mut emulator, 5);
+
+    assert_eq!(emulator.get_cycle_count(), 5);
+    assert_eq!(emulator.registers().get_pc(), 0x202);
+
+    run_cycles(&mut emulator, 5);
+
+    assert_eq!(emulator.get_frame_count(), 1);
+    assert_eq!(emulator.registers().get_pc(), 0x202);
+
+    run_cycles(&mut emulator, 1);
+
+    assert_eq!(emulator.registers().get_pc(), 0x200);
+}
+
+fn vip(rom: &[u8]) -> Emulator {
+    let config = Config::new(Platform::Chip8).with_timing(Timing::CosmacVip);
+
+    emulator(rom, config)
+}
+
+fn step_instruction(emulator: &mut Emulator) {
+    emulator.step_instruction(
+        &NullController,
+        &mut CaptureRenderer::new(),
+        &mut NullSynthetizer,
+    );
+}
+
+#[test]
+fn vip_instructions_cost_machine_cycles() {
+    // V0 := 5, skip if V0 == 5, skip if V0 == 4
+    let mut emulator = vip(&[0x60, 0x05, 0x30, 0x05, 0x00, 0x00, 0x30, 0x04]);
+
+    // the display takes its cycles at the start of the frame, then the fetch and the load
+    step_instruction(&mut emulator);
+    assert_eq!(emulator.get_cycle_count(), 1024 + 40 + 6);
+
+    // a skip that is taken costs more
+    step_instruction(&mut emulator);
+    assert_eq!(emulator.get_cycle_count(), 1070 + 40 + 10 + 4);
+
+    step_instruction(&mut emulator);
+    assert_eq!(emulator.get_cycle_count(), 1124 + 40 + 10);
+}
+
+#[test]
+fn vip_drawing_waits_for_the_vertical_blank() {
+    // without the display wait quirk, which the timing model makes irrelevant
+    let config = Config::new(Platform::Chip8)
+        .with_quirks(Quirks::chip48())
+        .with_timing(Timing::CosmacVip);
+    let mut emulator = emulator(&DRAW_ROM, config);
+
+    step_instruction(&mut emulator);
+
+    assert_eq!(emulator.get_frame_count(), 1);
+    assert_eq!(
+        emulator.get_cycle_count(),
+        VIP_CYCLES_PER_SECOND as u64 / 60
+    );
+}
+
+#[test]
+fn vip_sprite_rows_take_the_next_frame() {
+    // V0 := 3, draw 15 rows at V0, V1 += 1, loop
+    let rom = [0x60, 0x03, 0xD0, 0x0F, 0x71, 0x01, 0x12, 0x06];
+    let mut emulator = vip(&rom);
+
+    step_instruction(&mut emulator);
+    step_instruction(&mut emulator);
+    let frame_end = emulator.get_cycle_count();
+
+    // the display, then the unaligned rows, then the add
+    step_instruction(&mut emulator);
+    assert_eq!(
+        emulator.get_cycle_count(),
+        frame_end + 1024 + 15 * 54 + 40 + 10
+    );
+}
+
+#[test]
+fn vip_frames_run_at_60_hz() {
+    let mut emulator = vip(&TIMER_ROM);
+
+    // the cycles an instruction runs past a chunk are taken from the next one
+    for _ in 0..200 {
+        run_for(&mut emulator, Duration::from_millis(5));
+    }
+
+    assert_eq!(emulator.get_frame_count(), 60);
+    assert!(emulator.get_cycle_count() - (VIP_CYCLES_PER_SECOND as u64) < 100);
+    assert_eq!(emulator.mmu().get_delay_timer(), 0);
+}
+
+#[test]
+fn zero_cpu_speed() {
+    assert!(matches!(
+        Emulator::from_bytes(&TIMER_ROM, Config::new(Platform::Chip8).with_cpu_hz(0)),
+        Err(LoadError::ZeroCpuSpeed)
+    ));
+}
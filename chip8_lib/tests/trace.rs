@@ -0,0 +1,145 @@
+// Instruction traces, their line format, filters and ring buffer mode
+
+use chip8_lib::config::Config;
+use chip8_lib::emulator::Emulator;
+use chip8_lib::headless::{CaptureRenderer, NullController, NullSynthetizer};
+use chip8_lib::platform::Platform;
+use chip8_lib::trace::{OpcodeFilter, Tracer};
+use std::io::{self, Write};
+use std::sync::{Arc, Mutex};
+
+// V0 := 8, I := 0x300, V0 += 1, [I] = V0, jump back to V0 += 1
+const ROM: [u8; 10] = [0x60, 0x08, 0xA3, 0x00, 0x70, 0x01, 0xF0, 0x55, 0x12, 0x04];
+
+// keeps what the tracer writes, while the emulator owns it
+#[derive(Clone, Default)]
+struct SharedBuffer(Arc<Mutex<Vec<u8>>>);
+
+impl Write for SharedBuffer {
+    fn write(&mut self, bytes: &[u8]) -> io::Result<usize> {
+        self.0.lock().unwrap().write(bytes)
+    }
+
+    fn flush(&mut self) -> io::Result<()> {
+        Ok(())
+    }
+}
+
+impl SharedBuffer {
+    fn lines(&self) -> Vec<String> {
+        String::from_utf8(self.0.lock().unwrap().clone())
+            .unwrap()
+            .lines()
+            .map(str::to_string)
+            .collect()
+    }
+}
+
+fn run(rom: &[u8], tracer: impl FnOnce(Tracer) -> Tracer, instructions: u32) -> SharedBuffer {
+    let buffer = SharedBuffer::default();
+    let mut emulator = Emulator::from_bytes(rom, Config::new(Platform::Chip8)).unwrap();
+
+    emulator.set_tracer(tracer(Tracer::new(buffer.clone())));
+
+    for _ in 0..instructions {
+        emulator.step_instruction(
+            &NullController,
+            &mut CaptureRenderer::new(),
+            &mut NullSynthetizer,
+        );
+    }
+
+    emulator.take_tracer().unwrap().finish().unwrap();
+
+    buffer
+}
+
+#[test]
+fn line_format() {
+    let lines = run(&ROM, |tracer| tracer, 5).lines();
+
+    assert_eq!(
+        lines,
+        [
+            "0000000000 0200 6008 LD V0, 0x08 ; V0=08",
+            "0000000001 0202 a300 LD I, 0x300 ; I=0300",
+            "0000000002 0204 7001 ADD V0, 0x01 ; V0=09",
+            "0000000003 0206 f055 LD [I], V0 ; I=0301",
+            "0000000004 0208 1204 JP 0x204",
+        ]
+    );
+}
+
+#[test]
+fn filters() {
+    let lines = run(
+        &ROM,
+        |tracer| {
+            tracer
+                .with_address_range(0x204..=0x206)
+                .with_opcode_filter("7xkk".parse().unwrap())
+        },
+        8,
+    )
+    .lines();
+
+    // the cycles still count the filtered instructions
+    assert_eq!(
+        lines,
+        [
+            "0000000002 0204 7001 ADD V0, 0x01 ; V0=09",
+            "0000000005 0204 7001 ADD V0, 0x01 ; V0=0a",
+        ]
+    );
+}
+
+#[test]
+fn opcode_patterns() {
+    let filter: OpcodeFilter = "Dxyn".parse().unwrap();
+
+    assert!(filter.matches(0xD12F));
+    assert!(!filter.matches(0x812F));
+    assert!("8xy".parse::<OpcodeFilter>().is_err());
+}
+
+#[test]
+fn ring_buffer_dumps_on_invalid_opcode() {
+    // V0 := 1, V0 := 2, V0 := 3, then an invalid opcode
+    let rom = [0x60, 0x01, 0x60, 0x02, 0x60, 0x03, 0xFF, 0xFF];
+    let lines = run(&rom, |tracer| tracer.with_ring_buffer(2), 4).lines();
+
+    assert_eq!(
+        lines,
+        [
+            "# invalid opcode: 0xffff at 0x0206",
+            "0000000002 0204 6003 LD V0, 0x03 ; V0=03",
+            "0000000003 0206 ffff invalid",
+        ]
+    );
+}
+
+#[test]
+fn ring_buffer_dumps_on_breakpoint() {
+    let buffer = SharedBuffer::default();
+    let mut emulator = Emulator::from_bytes(&ROM, Config::new(Platform::Chip8)).unwrap();
+
+    emulator.set_tracer(Tracer::new(buffer.clone()).with_ring_buffer(3));
+    emulator.add_breakpoint(0x208, None);
+
+    let report = emulator.step_frame(
+        &NullController,
+        &mut CaptureRenderer::new(),
+        &mut NullSynthetizer,
+    );
+    assert!(report.stop_reason.is_some());
+
+    assert_eq!(
+        buffer.lines(),
+        [
+            "# breakpoint at 0x208",
+            "0000000001 0202 a300 LD I, 0x300 ; I=0300",
+            "0000000002 0204 7001 ADD V0, 0x01 ; V0=09",
+            "0000000003 0206 f055 LD [I], V0 ; I=0301",
+        ]
+    );
+}
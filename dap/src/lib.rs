@@ -0,0 +1,38 @@
+// Debug Adapter Protocol server, debugging a rom or an Octo source from an editor
+// https://microsoft.github.io/debug-adapter-protocol/specification
+
+mod protocol;
+mod session;
+
+use std::io::{self, BufReader, Read, Write};
+use std::sync::mpsc;
+use std::thread;
+
+use crate::protocol::read_message;
+use crate::session::Session;
+
+// serves a single client, the input being read on its own thread so a running rom can be paused
+pub fn serve(input: impl Read + Send + 'static, output: impl Write) -> io::Result<()> {
+    let (sender, receiver) = mpsc::channel();
+
+    let reader = thread::spawn(move || -> io::Result<()> {
+        let mut input = BufReader::new(input);
+
+        while let Some(message) = read_message(&mut input)? {
+            if sender.send(message).is_err() {
+                break;
+            }
+        }
+
+        Ok(())
+    });
+
+    Session::new(output).run(receiver)?;
+
+    // the reader is left blocked on the input when the client disconnects first
+    if reader.is_finished() {
+        reader.join().unwrap_or(Ok(()))?;
+    }
+
+    Ok(())
+}
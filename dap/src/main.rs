@@ -0,0 +1,14 @@
+use std::io;
+use std::process::ExitCode;
+
+// the client talks to the adapter over the standard input and output
+fn main() -> ExitCode {
+    match chip8_dap::serve(io::stdin(), io::stdout()) {
+        Ok(()) => ExitCode::SUCCESS,
+        Err(error) => {
+            eprintln!("{}", error);
+
+            ExitCode::FAILURE
+        }
+    }
+}
@@ -0,0 +1,70 @@
+use serde_json::Value;
+use std::io::{self, BufRead, ErrorKind, Write};
+
+// Messages are JSON preceded by a Content-Length header and an empty line
+// None once the input is closed
+pub fn read_message(reader: &mut impl BufRead) -> io::Result<Option<Value>> {
+    let mut content_length = None;
+
+    loop {
+        let mut header = String::new();
+
+        if reader.read_line(&mut header)? == 0 {
+            return Ok(None);
+        }
+
+        let header = header.trim_end();
+
+        if header.is_empty() {
+            break;
+        }
+
+        if let Some(length) = header.strip_prefix("Content-Length:") {
+            content_length = length.trim().parse::<usize>().ok();
+        }
+    }
+
+    let content_length = content_length
+        .ok_or_else(|| io::Error::new(ErrorKind::InvalidData, "missing Content-Length header"))?;
+
+    let mut content = vec![0; content_length];
+    reader.read_exact(&mut content)?;
+
+    serde_json::from_slice(&content)
+        .map(Some)
+        .map_err(|error| io::Error::new(ErrorKind::InvalidData, error))
+}
+
+pub fn write_message(writer: &mut impl Write, message: &Value) -> io::Result<()> {
+    let content = message.to_string();
+
+    write!(
+        writer,
+        "Content-Length: {}\r\n\r\n{}",
+        content.len(),
+        content
+    )?;
+    writer.flush()
+}
+
+// memory is sent base64 encoded
+pub fn encode_base64(bytes: &[u8]) -> String {
+    const ALPHABET: &[u8; 64] = b"ABCDEFGHIJKLMNOPQRSTUVWXYZabcdefghijklmnopqrstuvwxyz0123456789+/";
+
+    bytes
+        .chunks(3)
+        .flat_map(|chunk| {
+            let group = chunk.iter().enumerate().fold(0u32, |group, (index, byte)| {
+                group | (*byte as u32) << (16 - index * 8)
+            });
+
+            (0..4).map(move |index| {
+                if index <= chunk.len() {
+                    ALPHABET[(group >> (18 - index * 6)) as usize & 0x3F] as char
+                } else {
+                    '='
+                }
+            })
+        })
+        .collect()
+}
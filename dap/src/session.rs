@@ -0,0 +1,693 @@
+use chip8_asm::{assemble, Program};
+use chip8_lib::config::Config;
+use chip8_lib::debug::{Condition, StopReason};
+use chip8_lib::emulator::Emulator;
+use chip8_lib::headless::{CaptureRenderer, NullController, NullSynthetizer};
+use chip8_lib::instruction::{decode, Instruction};
+use chip8_lib::platform::Platform;
+
+use serde_json::{json, Value};
+use std::collections::BTreeMap;
+use std::fs;
+use std::io::{self, Write};
+use std::path::{Path, PathBuf};
+use std::sync::mpsc::{Receiver, TryRecvError};
+
+use crate::protocol::{encode_base64, write_message};
+
+// instructions stepped between two looks at the requests of the client
+const INSTRUCTIONS_PER_BATCH: u32 = 10;
+
+// the CHIP-8 has a single thread of execution
+const THREAD_ID: u64 = 1;
+
+// variables references of the scopes
+const REGISTERS_REFERENCE: u64 = 1;
+const TIMERS_REFERENCE: u64 = 2;
+
+// what runs while waiting for the next request
+enum Run {
+    Continue,
+    // until the instruction following the call, back at the same stack depth
+    StepOver { return_address: u16, depth: usize },
+    // until the current subroutine returns
+    StepOut { depth: usize },
+}
+
+struct Target {
+    emulator: Emulator,
+    // Octo source the rom was assembled from, which breakpoints can be set on by line
+    source: Option<(PathBuf, Program)>,
+    stop_on_entry: bool,
+    // set by source line and by address, each request replacing its own kind
+    line_breakpoints: BTreeMap<u16, Option<Condition>>,
+    instruction_breakpoints: BTreeMap<u16, Option<Condition>>,
+}
+
+pub struct Session<W: Write> {
+    writer: W,
+    seq: u64,
+    target: Option<Target>,
+    running: Option<Run>,
+    // events to send once the response to the current request is sent
+    events: Vec<Value>,
+    controller: NullController,
+    renderer: CaptureRenderer,
+    synthetizer: NullSynthetizer,
+}
+
+impl<W: Write> Session<W> {
+    pub fn new(writer: W) -> Self {
+        Self {
+            writer,
+            seq: 0,
+            target: None,
+            running: None,
+            events: Vec::new(),
+            controller: NullController,
+            renderer: CaptureRenderer::new(),
+            synthetizer: NullSynthetizer,
+        }
+    }
+
+    // handles requests until the client disconnects, running the rom in between when it isn't stopped
+    pub fn run(mut self, requests: Receiver<Value>) -> io::Result<()> {
+        loop {
+            let request = if self.running.is_some() {
+                match requests.try_recv() {
+                    Ok(request) => Some(request),
+                    Err(TryRecvError::Empty) => None,
+                    Err(TryRecvError::Disconnected) => return Ok(()),
+                }
+            } else {
+                match requests.recv() {
+                    Ok(request) => Some(request),
+                    Err(_) => return Ok(()),
+                }
+            };
+
+            let connected = match request {
+                Some(request) => self.handle(&request)?,
+                None => {
+                    self.run_frame();
+                    true
+                }
+            };
+
+            for event in std::mem::take(&mut self.events) {
+                self.send(event)?;
+            }
+
+            if !connected {
+                return Ok(());
+            }
+        }
+    }
+
+    // false once the client asked to disconnect
+    fn handle(&mut self, request: &Value) -> io::Result<bool> {
+        let command = request["command"].as_str().unwrap_or_default();
+        let arguments = &request["arguments"];
+
+        let result = match command {
+            "initialize" => Ok(capabilities()),
+            "launch" => self.launch(arguments),
+            "setBreakpoints" => self.set_breakpoints(arguments),
+            "setInstructionBreakpoints" => self.set_instruction_breakpoints(arguments),
+            "setExceptionBreakpoints" => Ok(json!({ "breakpoints": [] })),
+            "configurationDone" => self.configuration_done(),
+            "threads" => Ok(json!({ "threads": [{ "id": THREAD_ID, "name": "CHIP-8" }] })),
+            "stackTrace" => self.stack_trace(),
+            "scopes" => Ok(scopes()),
+            "variables" => self.variables(arguments),
+            "readMemory" => self.read_memory(arguments),
+            "continue" => self.resume(),
+            "next" => self.next(),
+            "stepIn" => self.step_in(),
+            "stepOut" => self.step_out(),
+            "pause" => self.pause(),
+            "disconnect" | "terminate" => Ok(Value::Null),
+            _ => Err(format!("unsupported request '{}'", command)),
+        };
+
+        let mut response = json!({
+            "type": "response",
+            "request_seq": request["seq"],
+            "command": command,
+            "success": result.is_ok(),
+        });
+
+        match result {
+            Ok(Value::Null) => {}
+            Ok(body) => response["body"] = body,
+            Err(message) => response["message"] = Value::String(message),
+        }
+
+        self.send(response)?;
+
+        Ok(!matches!(command, "disconnect" | "terminate"))
+    }
+
+    fn send(&mut self, mut message: Value) -> io::Result<()> {
+        self.seq += 1;
+        message["seq"] = json!(self.seq);
+
+        write_message(&mut self.writer, &message)
+    }
+
+    fn event(&mut self, event: &str, body: Value) {
+        self.events
+            .push(json!({ "type": "event", "event": event, "body": body }));
+    }
+
+    fn stopped(&mut self, reason: &str, description: Option<String>) {
+        self.running = None;
+
+        let mut body = json!({
+            "reason": reason,
+            "threadId": THREAD_ID,
+            "allThreadsStopped": true,
+        });
+
+        if let Some(description) = description {
+            body["description"] = Value::String(description);
+        }
+
+        self.event("stopped", body);
+    }
+
+    fn stopped_by(&mut self, stop_reason: StopReason) {
+        let reason = match stop_reason {
+            StopReason::Breakpoint { .. } => "breakpoint",
+            StopReason::Watchpoint { .. } => "data breakpoint",
+            StopReason::Fault(_) => "exception",
+        };
+
+        self.stopped(reason, Some(stop_reason.to_string()));
+    }
+
+    fn exited(&mut self) {
+        self.running = None;
+
+        self.event("exited", json!({ "exitCode": 0 }));
+        self.event("terminated", json!({}));
+    }
+
+    fn target(&self) -> Result<&Target, String> {
+        self.target
+            .as_ref()
+            .ok_or_else(|| "no rom launched".to_string())
+    }
+
+    fn target_mut(&mut self) -> Result<&mut Target, String> {
+        self.target
+            .as_mut()
+            .ok_or_else(|| "no rom launched".to_string())
+    }
+
+    // program: path to the rom, or to an Octo source (.8o) to assemble
+    // platform: chip8 (default), schip or xochip, stopOnEntry: false by default
+    fn launch(&mut self, arguments: &Value) -> Result<Value, String> {
+        let path = arguments["program"]
+            .as_str()
+            .ok_or("missing 'program' argument")?;
+
+        let platform = match arguments["platform"].as_str().unwrap_or("chip8") {
+            "chip8" => Platform::Chip8,
+            "schip" => Platform::SuperChip,
+            "xochip" => Platform::XoChip,
+            platform => return Err(format!("unknown platform '{}'", platform)),
+        };
+
+        let (rom, source) = if path.ends_with(".8o") {
+            let text = fs::read_to_string(path).map_err(|error| format!("{}: {}", path, error))?;
+            let program = assemble(&text).map_err(|error| format!("{}: {}", path, error))?;
+
+            (program.rom.clone(), Some((PathBuf::from(path), program)))
+        } else {
+            (
+                fs::read(path).map_err(|error| format!("{}: {}", path, error))?,
+                None,
+            )
+        };
+
+        let emulator = Emulator::from_bytes(&rom, Config::new(platform))
+            .map_err(|error| format!("{}: {}", path, error))?;
+
+        self.target = Some(Target {
+            emulator,
+            source,
+            stop_on_entry: arguments["stopOnEntry"].as_bool().unwrap_or(false),
+            line_breakpoints: BTreeMap::new(),
+            instruction_breakpoints: BTreeMap::new(),
+        });
+
+        // breakpoints can be set from now on
+        self.event("initialized", json!({}));
+
+        Ok(Value::Null)
+    }
+
+    fn configuration_done(&mut self) -> Result<Value, String> {
+        if self.target()?.stop_on_entry {
+            self.stopped("entry", None);
+        } else {
+            self.running = Some(Run::Continue);
+        }
+
+        Ok(Value::Null)
+    }
+
+    // only the source the rom was assembled from has lines, breakpoints in other files stay unverified
+    fn set_breakpoints(&mut self, arguments: &Value) -> Result<Value, String> {
+        let target = self.target_mut()?;
+        let requested = arguments["breakpoints"]
+            .as_array()
+            .cloned()
+            .unwrap_or_default();
+
+        let program = match (&target.source, arguments["source"]["path"].as_str()) {
+            (Some((source_path, program)), Some(path))
+                if same_file(source_path, Path::new(path)) =>
+            {
+                program
+            }
+            _ => {
+                let breakpoints: Vec<Value> = requested
+                    .iter()
+                    .map(|_| json!({ "verified": false, "message": "no line information for this file" }))
+                    .collect();
+
+                return Ok(json!({ "breakpoints": breakpoints }));
+            }
+        };
+
+        let mut line_breakpoints = BTreeMap::new();
+        let mut breakpoints = Vec::new();
+
+        for breakpoint in &requested {
+            let line = breakpoint["line"].as_u64().unwrap_or_default() as usize;
+
+            let breakpoint = match (program.address_of_line(line), parse_condition(breakpoint)) {
+                (None, _) => {
+                    json!({ "verified": false, "message": "no code at or after this line" })
+                }
+                (_, Err(message)) => json!({ "verified": false, "message": message }),
+                (Some((address, line)), Ok(condition)) => {
+                    line_breakpoints.insert(address, condition);
+
+                    json!({
+                        "verified": true,
+                        "line": line,
+                        "instructionReference": format_address(address),
+                    })
+                }
+            };
+
+            breakpoints.push(breakpoint);
+        }
+
+        target.line_breakpoints = line_breakpoints;
+        target.update_breakpoints();
+
+        Ok(json!({ "breakpoints": breakpoints }))
+    }
+
+    fn set_instruction_breakpoints(&mut self, arguments: &Value) -> Result<Value, String> {
+        let target = self.target_mut()?;
+        let requested = arguments["breakpoints"]
+            .as_array()
+            .cloned()
+            .unwrap_or_default();
+
+        let mut instruction_breakpoints = BTreeMap::new();
+        let mut breakpoints = Vec::new();
+
+        for breakpoint in &requested {
+            let address = breakpoint["instructionReference"]
+                .as_str()
+                .and_then(parse_address)
+                .map(|address| address as i64 + breakpoint["offset"].as_i64().unwrap_or(0))
+                .and_then(|address| u16::try_from(address).ok());
+
+            let breakpoint = match (address, parse_condition(breakpoint)) {
+                (None, _) => json!({ "verified": false, "message": "invalid address" }),
+                (_, Err(message)) => json!({ "verified": false, "message": message }),
+                (Some(address), Ok(condition)) => {
+                    instruction_breakpoints.insert(address, condition);
+
+                    json!({ "verified": true, "instructionReference": format_address(address) })
+                }
+            };
+
+            breakpoints.push(breakpoint);
+        }
+
+        target.instruction_breakpoints = instruction_breakpoints;
+        target.update_breakpoints();
+
+        Ok(json!({ "breakpoints": breakpoints }))
+    }
+
+    // the instruction about to run, then the calls that led to it
+    fn stack_trace(&self) -> Result<Value, String> {
+        let target = self.target()?;
+        let emulator = &target.emulator;
+
+        // the stack holds the addresses following the calls
+        let addresses = std::iter::once(emulator.registers().get_pc()).chain(
+            emulator
+                .mmu()
+                .get_stack()
+                .iter()
+                .rev()
+                .map(|return_address| return_address.wrapping_sub(2)),
+        );
+
+        let frames: Vec<Value> = addresses
+            .enumerate()
+            .map(|(index, address)| target.frame(index, address))
+            .collect();
+
+        Ok(json!({ "stackFrames": frames, "totalFrames": frames.len() }))
+    }
+
+    fn variables(&self, arguments: &Value) -> Result<Value, String> {
+        let emulator = &self.target()?.emulator;
+        let registers = emulator.registers();
+        let mmu = emulator.mmu();
+
+        let variables: Vec<Value> = match arguments["variablesReference"].as_u64() {
+            Some(REGISTERS_REFERENCE) => (0..16)
+                .map(|index| variable(&format!("V{:X}", index), registers.get_vx(index) as u16, 2))
+                .chain([
+                    memory_variable("I", registers.get_i()),
+                    memory_variable("PC", registers.get_pc()),
+                    variable("SP", mmu.get_stack_pointer() as u16, 2),
+                ])
+                .collect(),
+            Some(TIMERS_REFERENCE) => vec![
+                variable("DT", mmu.get_delay_timer() as u16, 2),
+                variable("ST", mmu.get_sound_timer() as u16, 2),
+            ],
+            _ => Vec::new(),
+        };
+
+        Ok(json!({ "variables": variables }))
+    }
+
+    // bytes past the end of memory are reported as unreadable
+    fn read_memory(&self, arguments: &Value) -> Result<Value, String> {
+        let mmu = self.target()?.emulator.mmu();
+
+        let reference = arguments["memoryReference"]
+            .as_str()
+            .and_then(parse_address)
+            .ok_or("invalid memory reference")?;
+        let start = reference as i64 + arguments["offset"].as_i64().unwrap_or(0);
+        let count = arguments["count"].as_u64().unwrap_or(0) as i64;
+
+        let memory_size = mmu.get_address_mask() as i64 + 1;
+        let end = (start + count).clamp(0, memory_size);
+        let start = start.clamp(0, end);
+
+        let bytes: Vec<u8> = (start..end)
+            .map(|address| mmu.peek_byte(address as usize))
+            .collect();
+
+        Ok(json!({
+            "address": format_address(start as u16),
+            "data": encode_base64(&bytes),
+            "unreadableBytes": count - bytes.len() as i64,
+        }))
+    }
+
+    // the instruction at PC runs first, even when a breakpoint is on it
+    fn resume(&mut self) -> Result<Value, String> {
+        self.target()?;
+
+        if !self.step_instruction() {
+            self.running = Some(Run::Continue);
+        }
+
+        Ok(json!({ "allThreadsContinued": true }))
+    }
+
+    fn next(&mut self) -> Result<Value, String> {
+        let emulator = &self.target()?.emulator;
+        let pc = emulator.registers().get_pc();
+        let mmu = emulator.mmu();
+
+        match decode(mmu.get_short(pc as usize)) {
+            Ok(Instruction::Call { .. }) => {
+                let depth = mmu.get_stack_pointer();
+
+                if !self.step_instruction() {
+                    self.running = Some(Run::StepOver {
+                        return_address: pc.wrapping_add(2),
+                        depth,
+                    });
+                }
+            }
+            _ => self.step(),
+        }
+
+        Ok(Value::Null)
+    }
+
+    fn step_in(&mut self) -> Result<Value, String> {
+        self.target()?;
+        self.step();
+
+        Ok(Value::Null)
+    }
+
+    fn step_out(&mut self) -> Result<Value, String> {
+        let depth = self.target()?.emulator.mmu().get_stack_pointer();
+
+        if !self.step_instruction() {
+            self.running = Some(Run::StepOut { depth });
+        }
+
+        Ok(Value::Null)
+    }
+
+    fn pause(&mut self) -> Result<Value, String> {
+        self.target()?;
+
+        if self.running.is_some() {
+            self.stopped("pause", None);
+        }
+
+        Ok(Value::Null)
+    }
+
+    fn step(&mut self) {
+        if !self.step_instruction() {
+            self.stopped("step", None);
+        }
+    }
+
+    // runs the instruction at PC, true if execution stopped because of it
+    fn step_instruction(&mut self) -> bool {
+        let Some(target) = self.target.as_mut() else {
+            return true;
+        };
+
+        let stop_reason = target.emulator.step_instruction(
+            &self.controller,
+            &mut self.renderer,
+            &mut self.synthetizer,
+        );
+
+        if target.emulator.has_exited() {
+            self.exited();
+            return true;
+        }
+
+        match stop_reason {
+            Some(stop_reason) => {
+                self.stopped_by(stop_reason);
+                true
+            }
+            None => false,
+        }
+    }
+
+    // runs a batch of instructions, until a breakpoint, a watchpoint or the end of a step
+    fn run_frame(&mut self) {
+        let Some(target) = self.target.as_mut() else {
+            return;
+        };
+
+        // a halted cpu never runs again
+        if let Some(fault) = target.emulator.get_fault() {
+            self.stopped_by(StopReason::Fault(fault));
+            return;
+        }
+
+        if let Some(Run::Continue) = self.running {
+            let report = target.emulator.step_frame(
+                &self.controller,
+                &mut self.renderer,
+                &mut self.synthetizer,
+            );
+
+            match report.stop_reason {
+                Some(stop_reason) => self.stopped_by(stop_reason),
+                None if target.emulator.has_exited() => self.exited(),
+                None => {}
+            }
+
+            return;
+        }
+
+        for _ in 0..INSTRUCTIONS_PER_BATCH {
+            // a breakpoint on the instruction stops the step before it runs
+            if let Some(stop_reason) = self
+                .target()
+                .ok()
+                .and_then(|target| target.emulator.check_breakpoints())
+            {
+                self.stopped_by(stop_reason);
+                return;
+            }
+
+            if self.step_instruction() {
+                return;
+            }
+
+            let Ok(target) = self.target() else {
+                return;
+            };
+            let pc = target.emulator.registers().get_pc();
+            let depth = target.emulator.mmu().get_stack_pointer();
+
+            let done = match self.running {
+                Some(Run::StepOver {
+                    return_address,
+                    depth: call_depth,
+                }) => pc == return_address && depth == call_depth,
+                Some(Run::StepOut { depth: call_depth }) => depth < call_depth,
+                _ => false,
+            };
+
+            if done {
+                self.stopped("step", None);
+                return;
+            }
+        }
+    }
+}
+
+impl Target {
+    // the client owns every breakpoint of the emulator
+    fn update_breakpoints(&mut self) {
+        let addresses: Vec<u16> = self.emulator.breakpoints().keys().copied().collect();
+
+        for address in addresses {
+            self.emulator.remove_breakpoint(address);
+        }
+
+        for (address, condition) in self
+            .instruction_breakpoints
+            .iter()
+            .chain(self.line_breakpoints.iter())
+        {
+            self.emulator.add_breakpoint(*address, condition.clone());
+        }
+    }
+
+    // named after the label the address belongs to, with its line when assembled from a source
+    fn frame(&self, id: usize, address: u16) -> Value {
+        let mut frame = json!({
+            "id": id,
+            "name": format_address(address),
+            "line": 0,
+            "column": 0,
+            "instructionPointerReference": format_address(address),
+        });
+
+        if let Some((path, program)) = &self.source {
+            if let Some(label) = program.label_of(address) {
+                frame["name"] = Value::String(format!("{} ({})", label, format_address(address)));
+            }
+
+            if let Some(line) = program.lines.get(&address) {
+                frame["line"] = json!(line);
+                frame["column"] = json!(1);
+                frame["source"] = json!({
+                    "name": path.file_name().map(|name| name.to_string_lossy()),
+                    "path": path.to_string_lossy(),
+                });
+            }
+        }
+
+        frame
+    }
+}
+
+fn capabilities() -> Value {
+    json!({
+        "supportsConfigurationDoneRequest": true,
+        "supportsConditionalBreakpoints": true,
+        "supportsInstructionBreakpoints": true,
+        "supportsReadMemoryRequest": true,
+        "supportsSteppingGranularity": false,
+    })
+}
+
+fn scopes() -> Value {
+    json!({
+        "scopes": [
+            { "name": "Registers", "variablesReference": REGISTERS_REFERENCE, "expensive": false },
+            { "name": "Timers", "variablesReference": TIMERS_REFERENCE, "expensive": false },
+        ]
+    })
+}
+
+fn variable(name: &str, value: u16, digits: usize) -> Value {
+    json!({
+        "name": name,
+        "value": format!("{:#0width$x}", value, width = digits + 2),
+        "variablesReference": 0,
+    })
+}
+
+// registers holding an address can be opened in the memory view
+fn memory_variable(name: &str, value: u16) -> Value {
+    let mut variable = variable(name, value, 4);
+    variable["memoryReference"] = Value::String(format_address(value));
+
+    variable
+}
+
+fn parse_condition(breakpoint: &Value) -> Result<Option<Condition>, String> {
+    match breakpoint["condition"].as_str() {
+        Some(condition) if !condition.trim().is_empty() => condition
+            .parse()
+            .map(Some)
+            .map_err(|error| format!("condition {}", error)),
+        _ => Ok(None),
+    }
+}
+
+fn format_address(address: u16) -> String {
+    format!("{:#06x}", address)
+}
+
+fn parse_address(text: &str) -> Option<u16> {
+    let text = text.trim();
+
+    match text.strip_prefix("0x") {
+        Some(hex) => u16::from_str_radix(hex, 16).ok(),
+        None => text.parse().ok(),
+    }
+}
+
+fn same_file(a: &Path, b: &Path) -> bool {
+    match (fs::canonicalize(a), fs::canonicalize(b)) {
+        (Ok(a), Ok(b)) => a == b,
+        _ => a == b,
+    }
+}
@@ -0,0 +1,291 @@
+// Scripted client driving the adapter over its standard input and output
+
+use serde_json::{json, Value};
+use std::collections::VecDeque;
+use std::env;
+use std::fs;
+use std::io::{BufRead, BufReader, Read, Write};
+use std::path::PathBuf;
+use std::process::{Child, ChildStdin, ChildStdout, Command, Stdio};
+
+const SOURCE: &str = "\
+# counts in v0, calling a subroutine every time
+: increment
+  v0 += 1
+  i := 0x300
+  save v0
+;
+
+: main
+  loop
+    increment
+    v1 := v0
+  again
+";
+
+struct Client {
+    adapter: Child,
+    input: ChildStdin,
+    output: BufReader<ChildStdout>,
+    seq: u64,
+    events: VecDeque<Value>,
+}
+
+impl Client {
+    fn start() -> Self {
+        let mut adapter = Command::new(env!("CARGO_BIN_EXE_chip8-dap"))
+            .stdin(Stdio::piped())
+            .stdout(Stdio::piped())
+            .spawn()
+            .unwrap();
+
+        Self {
+            input: adapter.stdin.take().unwrap(),
+            output: BufReader::new(adapter.stdout.take().unwrap()),
+            adapter,
+            seq: 0,
+            events: VecDeque::new(),
+        }
+    }
+
+    // body of the response, events received meanwhile are kept for wait_event
+    fn request(&mut self, command: &str, arguments: Value) -> Value {
+        let response = self.try_request(command, arguments);
+        assert_eq!(response["success"], true, "{}", response);
+
+        response["body"].clone()
+    }
+
+    fn try_request(&mut self, command: &str, arguments: Value) -> Value {
+        self.seq += 1;
+
+        let content = json!({
+            "seq": self.seq,
+            "type": "request",
+            "command": command,
+            "arguments": arguments,
+        })
+        .to_string();
+        write!(
+            self.input,
+            "Content-Length: {}\r\n\r\n{}",
+            content.len(),
+            content
+        )
+        .unwrap();
+        self.input.flush().unwrap();
+
+        loop {
+            let message = self.receive();
+
+            if message["type"] == "response" {
+                assert_eq!(message["request_seq"], self.seq);
+                return message;
+            }
+
+            self.events.push_back(message);
+        }
+    }
+
+    fn wait_event(&mut self, event: &str) -> Value {
+        loop {
+            let message = match self.events.pop_front() {
+                Some(message) => message,
+                None => self.receive(),
+            };
+
+            if message["event"] == event {
+                return message["body"].clone();
+            }
+        }
+    }
+
+    fn receive(&mut self) -> Value {
+        let mut content_length = 0;
+
+        loop {
+            let mut header = String::new();
+            self.output.read_line(&mut header).unwrap();
+
+            match header.trim_end().strip_prefix("Content-Length: ") {
+                Some(length) => content_length = length.parse().unwrap(),
+                None if header.trim_end().is_empty() => break,
+                None => {}
+            }
+        }
+
+        let mut content = vec![0; content_length];
+        self.output.read_exact(&mut content).unwrap();
+
+        serde_json::from_slice(&content).unwrap()
+    }
+
+    fn variable(&mut self, reference: u64, name: &str) -> String {
+        let body = self.request("variables", json!({ "variablesReference": reference }));
+
+        body["variables"]
+            .as_array()
+            .unwrap()
+            .iter()
+            .find(|variable| variable["name"] == name)
+            .map(|variable| variable["value"].as_str().unwrap().to_string())
+            .unwrap()
+    }
+
+    fn disconnect(mut self) {
+        self.request("disconnect", json!({}));
+
+        assert!(self.adapter.wait().unwrap().success());
+    }
+}
+
+// one per test, so they can run in parallel
+fn write_source(name: &str) -> PathBuf {
+    let path = env::temp_dir().join(format!("chip8-dap-{}-{}.8o", name, std::process::id()));
+    fs::write(&path, SOURCE).unwrap();
+
+    path
+}
+
+fn launch(client: &mut Client, program: &PathBuf, stop_on_entry: bool) {
+    let capabilities = client.request("initialize", json!({ "adapterID": "chip8" }));
+    assert_eq!(capabilities["supportsReadMemoryRequest"], true);
+
+    client.request(
+        "launch",
+        json!({ "program": program, "stopOnEntry": stop_on_entry }),
+    );
+    client.wait_event("initialized");
+}
+
+#[test]
+fn breakpoint_by_line() {
+    let path = write_source("line");
+    let mut client = Client::start();
+
+    launch(&mut client, &path, false);
+
+    // the comment line moves to the first instruction after it
+    let body = client.request(
+        "setBreakpoints",
+        json!({ "source": { "path": path }, "breakpoints": [{ "line": 11 }, { "line": 1 }] }),
+    );
+    assert_eq!(body["breakpoints"][0]["verified"], true);
+    assert_eq!(body["breakpoints"][0]["line"], 11);
+    assert_eq!(body["breakpoints"][1]["line"], 3);
+
+    client.request("configurationDone", json!({}));
+
+    assert_eq!(client.wait_event("stopped")["reason"], "breakpoint");
+    let trace = client.request("stackTrace", json!({ "threadId": 1 }));
+    assert_eq!(trace["stackFrames"][0]["line"], 3);
+    assert_eq!(trace["stackFrames"][0]["name"], "increment (0x0202)");
+    // called from the loop of main
+    assert_eq!(trace["stackFrames"][1]["line"], 10);
+
+    client.request("continue", json!({ "threadId": 1 }));
+    assert_eq!(client.wait_event("stopped")["reason"], "breakpoint");
+    assert_eq!(
+        client.request("stackTrace", json!({ "threadId": 1 }))["stackFrames"][0]["line"],
+        11
+    );
+    assert_eq!(client.variable(1, "V0"), "0x01");
+
+    // removing the breakpoints lets the rom run until paused
+    client.request(
+        "setBreakpoints",
+        json!({ "source": { "path": path }, "breakpoints": [] }),
+    );
+    client.request("continue", json!({ "threadId": 1 }));
+    client.request("pause", json!({ "threadId": 1 }));
+    assert_eq!(client.wait_event("stopped")["reason"], "pause");
+
+    client.disconnect();
+    fs::remove_file(path).unwrap();
+}
+
+#[test]
+fn stepping_and_inspection() {
+    let path = write_source("step");
+    let mut client = Client::start();
+
+    launch(&mut client, &path, true);
+    client.request("configurationDone", json!({}));
+    assert_eq!(client.wait_event("stopped")["reason"], "entry");
+    assert_eq!(client.variable(1, "PC"), "0x0200");
+
+    // jump to main, then over the call
+    client.request("stepIn", json!({ "threadId": 1 }));
+    assert_eq!(client.wait_event("stopped")["reason"], "step");
+    client.request("next", json!({ "threadId": 1 }));
+    assert_eq!(client.wait_event("stopped")["reason"], "step");
+    assert_eq!(client.variable(1, "PC"), "0x020c");
+    assert_eq!(client.variable(1, "V0"), "0x01");
+
+    // into the call, then out of it
+    client.request("next", json!({ "threadId": 1 }));
+    client.wait_event("stopped");
+    client.request("next", json!({ "threadId": 1 }));
+    client.wait_event("stopped");
+    client.request("stepIn", json!({ "threadId": 1 }));
+    client.wait_event("stopped");
+    assert_eq!(client.variable(1, "SP"), "0x01");
+    client.request("stepOut", json!({ "threadId": 1 }));
+    client.wait_event("stopped");
+    assert_eq!(client.variable(1, "SP"), "0x00");
+    assert_eq!(client.variable(1, "V0"), "0x02");
+    assert_eq!(client.variable(2, "DT"), "0x00");
+
+    // save v0 wrote the counter to 0x300
+    let memory = client.request(
+        "readMemory",
+        json!({ "memoryReference": "0x0300", "count": 2 }),
+    );
+    assert_eq!(memory["data"], "AgA=");
+    assert_eq!(memory["unreadableBytes"], 0);
+
+    let memory = client.request(
+        "readMemory",
+        json!({ "memoryReference": "0x0ffe", "count": 4 }),
+    );
+    assert_eq!(memory["unreadableBytes"], 2);
+
+    client.disconnect();
+    fs::remove_file(path).unwrap();
+}
+
+#[test]
+fn instruction_breakpoints_on_a_rom() {
+    let mut client = Client::start();
+    let rom = PathBuf::from(env!("CARGO_MANIFEST_DIR")).join("../roms/test/2-ibm-logo.ch8");
+
+    launch(&mut client, &rom, false);
+
+    let body = client.request(
+        "setInstructionBreakpoints",
+        json!({ "breakpoints": [{ "instructionReference": "0x0208", "condition": "V0 == 0x0c" }] }),
+    );
+    assert_eq!(body["breakpoints"][0]["verified"], true);
+
+    // no source to set breakpoints by line in
+    let body = client.request(
+        "setBreakpoints",
+        json!({ "source": { "path": "ibm.8o" }, "breakpoints": [{ "line": 1 }] }),
+    );
+    assert_eq!(body["breakpoints"][0]["verified"], false);
+
+    client.request("configurationDone", json!({}));
+    assert_eq!(client.wait_event("stopped")["reason"], "breakpoint");
+    assert_eq!(client.variable(1, "PC"), "0x0208");
+
+    let trace = client.request("stackTrace", json!({ "threadId": 1 }));
+    assert_eq!(
+        trace["stackFrames"][0]["instructionPointerReference"],
+        "0x0208"
+    );
+
+    let response = client.try_request("launch", json!({ "program": "missing.ch8" }));
+    assert_eq!(response["success"], false);
+
+    client.disconnect();
+}
@@ -0,0 +1,134 @@
+use chip8_lib::debug::StopReason;
+use chip8_lib::emulator::Emulator;
+use chip8_lib::headless::{CaptureRenderer, NullSynthetizer};
+use chip8_lib::instruction::{decode, Instruction};
+use chip8_lib::interfaces::Controller;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::Arc;
+
+// keys are held from the debugger prompt, as there is no keyboard to read
+pub struct Keypad {
+    key_down: Vec<bool>,
+}
+
+impl Controller for Keypad {
+    fn is_key_down(&self, key_index: usize) -> bool {
+        self.key_down[key_index & 0xF]
+    }
+}
+
+// why execution gave control back to the prompt
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Stop {
+    Stepped,
+    // breakpoint or watchpoint of the emulator
+    Debug(StopReason),
+    // the rom jumps to itself, nothing else will ever run
+    Looping(u16),
+    Exited,
+    // the interrupt flag was raised, e.g. by Ctrl-C
+    Interrupted,
+}
+
+pub struct Debugger {
+    emulator: Emulator,
+    keypad: Keypad,
+    renderer: CaptureRenderer,
+    synthetizer: NullSynthetizer,
+    interrupt: Arc<AtomicBool>,
+}
+
+impl Debugger {
+    pub fn new(emulator: Emulator) -> Self {
+        Self {
+            emulator,
+            keypad: Keypad {
+                key_down: vec![false; 16],
+            },
+            renderer: CaptureRenderer::new(),
+            synthetizer: NullSynthetizer,
+            interrupt: Arc::new(AtomicBool::new(false)),
+        }
+    }
+
+    pub fn emulator(&self) -> &Emulator {
+        &self.emulator
+    }
+
+    // breakpoints and watchpoints are kept by the emulator
+    pub fn emulator_mut(&mut self) -> &mut Emulator {
+        &mut self.emulator
+    }
+
+    // raising the flag stops a running command after the current instruction
+    pub fn get_interrupt(&self) -> Arc<AtomicBool> {
+        Arc::clone(&self.interrupt)
+    }
+
+    pub fn set_key_down(&mut self, key: usize, value: bool) {
+        self.keypad.key_down[key & 0xF] = value;
+    }
+
+    // step into calls
+    pub fn step(&mut self) -> Stop {
+        self.run_until(|_| true)
+    }
+
+    // step over calls, running the whole subroutine
+    pub fn step_over(&mut self) -> Stop {
+        let pc = self.emulator.registers().get_pc();
+        let mmu = self.emulator.mmu();
+
+        match decode(mmu.get_short(pc as usize)) {
+            Ok(Instruction::Call { .. }) => {
+                let depth = mmu.get_stack_pointer();
+
+                self.run_until(move |emulator| {
+                    emulator.registers().get_pc() == pc.wrapping_add(2)
+                        && emulator.mmu().get_stack_pointer() == depth
+                })
+            }
+            _ => self.step(),
+        }
+    }
+
+    pub fn continue_execution(&mut self) -> Stop {
+        self.run_until(|_| false)
+    }
+
+    // the instruction at PC always runs, even with a breakpoint on it, as execution stopped there
+    fn run_until(&mut self, done: impl Fn(&Emulator) -> bool) -> Stop {
+        loop {
+            if self.interrupt.swap(false, Ordering::Relaxed) {
+                return Stop::Interrupted;
+            }
+
+            if self.emulator.has_exited() {
+                return Stop::Exited;
+            }
+
+            // a halted cpu never runs again
+            if let Some(fault) = self.emulator.get_fault() {
+                return Stop::Debug(StopReason::Fault(fault));
+            }
+
+            let stop_reason = self.emulator.step_instruction(
+                &self.keypad,
+                &mut self.renderer,
+                &mut self.synthetizer,
+            );
+
+            if let Some(stop_reason) = stop_reason.or_else(|| self.emulator.check_breakpoints()) {
+                return Stop::Debug(stop_reason);
+            }
+
+            if done(&self.emulator) {
+                return Stop::Stepped;
+            }
+
+            if self.emulator.is_looping() {
+                return Stop::Looping(self.emulator.registers().get_pc());
+            }
+        }
+    }
+}
@@ -0,0 +1,5 @@
+// Interactive debugger, running a rom one instruction or one breakpoint at a time
+
+mod debugger;
+
+pub use debugger::{Debugger, Stop};
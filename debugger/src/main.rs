@@ -0,0 +1,404 @@
+use chip8_lib::cli::ConfigArgs;
+use chip8_lib::debug::{Condition, Watchpoint};
+use chip8_lib::emulator::Emulator;
+use chip8_lib::instruction::{decode, Instruction};
+
+use chip8_debugger::{Debugger, Stop};
+use clap::Parser;
+use std::io::{self, BufRead, Write};
+use std::process::ExitCode;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::{Arc, OnceLock};
+
+const HELP: &str = "\
+addresses are hexadecimal, counts are decimal
+
+step [count]        run one instruction, stepping into calls
+next                run one instruction, stepping over calls
+continue            run until a breakpoint, a watch, the rom stops, or Ctrl-C
+break [addr] [if <condition>]
+                    add a breakpoint, or list them without address
+                    e.g. break 2a4 if V3 == 0x10 && I > 0x300
+delete <addr>       remove a breakpoint
+watch [range] [r|w|rw|x]
+                    stop when the range is read, written (default), or executed,
+                    or list the watchpoints without range, e.g. watch 300-30f rw
+unwatch <range> [r|w|rw|x]
+                    remove a watchpoint
+regs                show the registers and timers
+mem <addr> [len]    dump memory, 64 bytes by default
+stack               show the return addresses
+disasm [addr] [count] disassemble from addr (PC by default), 10 instructions by default
+screen              show the screen
+press <key>         hold a key of the keypad
+release <key>       release a key of the keypad
+quit                exit the debugger
+
+an empty line repeats the last command";
+
+fn main() -> ExitCode {
+    // cli arguments
+    let args = Args::parse();
+
+    let config = args.config.config();
+
+    let emulator = match Emulator::from_file(&args.path, config) {
+        Ok(emulator) => emulator,
+        Err(error) => {
+            eprintln!("{}: {}", args.path, error);
+
+            return ExitCode::FAILURE;
+        }
+    };
+
+    let mut debugger = Debugger::new(emulator);
+    let interrupt = debugger.get_interrupt();
+    let mut last_command = String::new();
+    let stdin = io::stdin();
+
+    install_interrupt_handler(Arc::clone(&interrupt));
+    print_location(&debugger);
+
+    loop {
+        print!("(chip8) ");
+        let _ = io::stdout().flush();
+
+        let mut line = String::new();
+        match stdin.lock().read_line(&mut line) {
+            Ok(0) | Err(_) => break,
+            Ok(_) => {}
+        }
+
+        // an empty line repeats the last command
+        let line = match line.trim() {
+            "" => last_command.clone(),
+            line => line.to_string(),
+        };
+        last_command = line.clone();
+
+        let words: Vec<&str> = line.split_whitespace().collect();
+
+        // Ctrl-C pressed at the prompt must not stop the next command
+        interrupt.store(false, Ordering::Relaxed);
+
+        match run_command(&mut debugger, &words) {
+            Ok(true) => {}
+            Ok(false) => break,
+            Err(error) => println!("{}", error),
+        }
+    }
+
+    ExitCode::SUCCESS
+}
+
+// the handler can only reach the flag through a static
+static INTERRUPT: OnceLock<Arc<AtomicBool>> = OnceLock::new();
+
+extern "C" fn on_interrupt(_signal: libc::c_int) {
+    if let Some(interrupt) = INTERRUPT.get() {
+        interrupt.store(true, Ordering::Relaxed);
+    }
+}
+
+// Ctrl-C stops a running command instead of killing the debugger
+fn install_interrupt_handler(interrupt: Arc<AtomicBool>) {
+    let _ = INTERRUPT.set(interrupt);
+
+    unsafe {
+        libc::signal(
+            libc::SIGINT,
+            on_interrupt as *const () as libc::sighandler_t,
+        );
+    }
+}
+
+// false once the debugger has to exit
+fn run_command(debugger: &mut Debugger, words: &[&str]) -> Result<bool, String> {
+    let (command, arguments) = match words.split_first() {
+        Some((command, arguments)) => (*command, arguments),
+        None => return Ok(true),
+    };
+
+    match command {
+        "step" | "s" => {
+            let count = parse_count(arguments.first(), 1)?;
+
+            for _ in 0..count {
+                match debugger.step() {
+                    Stop::Stepped => {}
+                    stop => {
+                        print_stop(debugger, stop);
+                        return Ok(true);
+                    }
+                }
+            }
+
+            print_location(debugger);
+        }
+        "next" | "n" => {
+            let stop = debugger.step_over();
+            print_stop(debugger, stop);
+        }
+        "continue" | "c" => {
+            let stop = debugger.continue_execution();
+            print_stop(debugger, stop);
+        }
+        "break" | "b" => match arguments.split_first() {
+            Some((address, condition)) => {
+                let address = parse_address(address)?;
+
+                let condition = match condition.split_first() {
+                    Some((&"if", condition)) => Some(
+                        Condition::parse(&condition.join(" "))
+                            .map_err(|error| format!("invalid condition, {}", error))?,
+                    ),
+                    Some(_) => return Err("expected if before the condition".to_string()),
+                    None => None,
+                };
+
+                debugger.emulator_mut().add_breakpoint(address, condition);
+                println!("breakpoint at {:#05x}", address);
+            }
+            None => {
+                for address in debugger.emulator().breakpoints().keys() {
+                    println!("breakpoint at {:#05x}", address);
+                }
+            }
+        },
+        "delete" | "d" => {
+            let address = parse_address(required(arguments.first())?)?;
+
+            if !debugger.emulator_mut().remove_breakpoint(address) {
+                return Err(format!("no breakpoint at {:#05x}", address));
+            }
+        }
+        "watch" | "w" => {
+            if arguments.is_empty() {
+                for watchpoint in debugger.emulator().watchpoints() {
+                    println!("{}", format_watchpoint(watchpoint));
+                }
+            } else {
+                let watchpoint = parse_watchpoint(arguments)?;
+
+                println!("{}", format_watchpoint(&watchpoint));
+                debugger.emulator_mut().add_watchpoint(watchpoint);
+            }
+        }
+        "unwatch" => {
+            let watchpoint = parse_watchpoint(arguments)?;
+
+            if !debugger.emulator_mut().remove_watchpoint(&watchpoint) {
+                return Err(format!("no {}", format_watchpoint(&watchpoint)));
+            }
+        }
+        "regs" | "r" => print_registers(debugger.emulator()),
+        "mem" | "m" => {
+            let address = parse_address(required(arguments.first())?)?;
+            let length = parse_count(arguments.get(1), 64)?;
+
+            print_memory(debugger.emulator(), address, length);
+        }
+        "stack" => {
+            let stack = debugger.emulator().mmu().get_stack();
+
+            if stack.is_empty() {
+                println!("stack is empty");
+            }
+
+            for (depth, address) in stack.iter().enumerate().rev() {
+                println!("#{:<2} {:#05x}", depth, address);
+            }
+        }
+        "disasm" => {
+            let address = match arguments.first() {
+                Some(address) => parse_address(address)?,
+                None => debugger.emulator().registers().get_pc(),
+            };
+            let count = parse_count(arguments.get(1), 10)?;
+
+            print_disassembly(debugger.emulator(), address, count);
+        }
+        "screen" => print_screen(debugger.emulator()),
+        "press" | "release" => {
+            let key = parse_address(required(arguments.first())?)?;
+
+            if key > 0xF {
+                return Err(format!("no key {:x}, keys go from 0 to f", key));
+            }
+
+            debugger.set_key_down(key as usize, command == "press");
+        }
+        "help" | "h" => println!("{}", HELP),
+        "quit" | "q" => return Ok(false),
+        _ => return Err(format!("unknown command {}, try help", command)),
+    }
+
+    Ok(true)
+}
+
+fn required<'a>(argument: Option<&'a &'a str>) -> Result<&'a str, String> {
+    argument.copied().ok_or("missing address".to_string())
+}
+
+fn parse_address(text: &str) -> Result<u16, String> {
+    let digits = text.trim_start_matches("0x");
+
+    u16::from_str_radix(digits, 16).map_err(|_| format!("invalid address {}", text))
+}
+
+// range as start[-end], then the accesses watched
+fn parse_watchpoint(arguments: &[&str]) -> Result<Watchpoint, String> {
+    let range = required(arguments.first())?;
+
+    let range = match range.split_once('-') {
+        Some((start, end)) => parse_address(start)?..=parse_address(end)?,
+        None => parse_address(range)?..=parse_address(range)?,
+    };
+
+    match arguments.get(1).copied().unwrap_or("w") {
+        "r" => Ok(Watchpoint::read(range)),
+        "w" => Ok(Watchpoint::write(range)),
+        "rw" => Ok(Watchpoint::read_write(range)),
+        "x" => Ok(Watchpoint::execute(range)),
+        access => Err(format!("invalid access {}, one of r, w, rw or x", access)),
+    }
+}
+
+fn format_watchpoint(watchpoint: &Watchpoint) -> String {
+    let accesses: String = [
+        (watchpoint.read, 'r'),
+        (watchpoint.write, 'w'),
+        (watchpoint.execute, 'x'),
+    ]
+    .iter()
+    .filter(|(watched, _)| *watched)
+    .map(|(_, access)| access)
+    .collect();
+
+    format!(
+        "watchpoint {:#05x}-{:#05x} {}",
+        watchpoint.range.start(),
+        watchpoint.range.end(),
+        accesses
+    )
+}
+
+fn parse_count(text: Option<&&str>, default: usize) -> Result<usize, String> {
+    match text {
+        Some(text) => text.parse().map_err(|_| format!("invalid count {}", text)),
+        None => Ok(default),
+    }
+}
+
+fn print_stop(debugger: &Debugger, stop: Stop) {
+    match stop {
+        Stop::Stepped => {}
+        Stop::Debug(stop_reason) => println!("{}", stop_reason),
+        Stop::Looping(address) => println!("rom is looping at {:#05x}", address),
+        Stop::Exited => println!("rom has exited"),
+        Stop::Interrupted => println!("interrupted"),
+    }
+
+    print_location(debugger);
+}
+
+fn print_location(debugger: &Debugger) {
+    let emulator = debugger.emulator();
+
+    print!("=> ");
+    print_disassembly(emulator, emulator.registers().get_pc(), 1);
+}
+
+fn print_registers(emulator: &Emulator) {
+    let registers = emulator.registers();
+    let mmu = emulator.mmu();
+
+    for row in 0..2 {
+        let line: Vec<String> = (0..8)
+            .map(|column| {
+                let index = row * 8 + column;
+                format!("V{:X}={:02x}", index, registers.get_vx(index))
+            })
+            .collect();
+
+        println!("{}", line.join(" "));
+    }
+
+    println!(
+        "I={:04x} PC={:04x} SP={} DT={:02x} ST={:02x}",
+        registers.get_i(),
+        registers.get_pc(),
+        mmu.get_stack_pointer(),
+        mmu.get_delay_timer(),
+        mmu.get_sound_timer()
+    );
+}
+
+fn print_memory(emulator: &Emulator, address: u16, length: usize) {
+    let mmu = emulator.mmu();
+
+    for row_start in (0..length).step_by(16) {
+        let row_address = address as usize + row_start;
+        let bytes: Vec<String> = (row_start..length.min(row_start + 16))
+            .map(|offset| format!("{:02x}", mmu.peek_byte(address as usize + offset)))
+            .collect();
+
+        println!(
+            "{:#06x}  {}",
+            row_address & mmu.get_address_mask(),
+            bytes.join(" ")
+        );
+    }
+}
+
+fn print_disassembly(emulator: &Emulator, address: u16, count: usize) {
+    let mmu = emulator.mmu();
+    let mut address = address;
+
+    for _ in 0..count {
+        let op_code = mmu.get_short(address as usize);
+
+        match decode(op_code) {
+            Ok(Instruction::LdLongI) => {
+                let long_address = mmu.get_short(address as usize + 2);
+
+                println!(
+                    "{:#06x}  {:04x}{:04x}  LD I, {:#06x}",
+                    address, op_code, long_address, long_address
+                );
+            }
+            Ok(instruction) => println!("{:#06x}  {:04x}      {}", address, op_code, instruction),
+            Err(_) => println!("{:#06x}  {:04x}      ???", address, op_code),
+        }
+
+        address = address.wrapping_add(match decode(op_code) {
+            Ok(instruction) => instruction.size(),
+            Err(_) => 2,
+        });
+    }
+}
+
+fn print_screen(emulator: &Emulator) {
+    let mmu = emulator.mmu();
+
+    for row in mmu.get_screen().chunks(mmu.get_screen_width()) {
+        let line: String = row
+            .iter()
+            .map(|pixel| ['.', '#', '+', '@'][(pixel & 0x3) as usize])
+            .collect();
+
+        println!("{}", line);
+    }
+}
+
+// cli arguments struct
+#[derive(Parser, Debug)]
+#[command(version, about = "Interactive debugger for CHIP-8 roms", long_about = None)]
+struct Args {
+    /// Path to the rom
+    #[arg(short, long)]
+    path: String,
+
+    #[command(flatten)]
+    config: ConfigArgs,
+}
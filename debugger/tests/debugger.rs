@@ -0,0 +1,111 @@
+// Stepping, stepping over calls, and what stops a running debugger
+
+use chip8_debugger::{Debugger, Stop};
+use chip8_lib::config::Config;
+use chip8_lib::debug::StopReason;
+use chip8_lib::emulator::Emulator;
+use chip8_lib::platform::Platform;
+use std::sync::atomic::Ordering;
+
+// call 0x206, V1 += 1, jump to 0x202, then V0 += 1 and return
+const CALLS: [u8; 10] = [0x22, 0x06, 0x71, 0x01, 0x12, 0x02, 0x70, 0x01, 0x00, 0xEE];
+
+// V0 += 1, jump back to 0x200
+const COUNTER: [u8; 4] = [0x70, 0x01, 0x12, 0x00];
+
+fn debugger(rom: &[u8]) -> Debugger {
+    let emulator = Emulator::from_bytes(rom, Config::new(Platform::Chip8)).unwrap();
+
+    Debugger::new(emulator)
+}
+
+fn pc(debugger: &Debugger) -> u16 {
+    debugger.emulator().registers().get_pc()
+}
+
+#[test]
+fn step_enters_calls() {
+    let mut debugger = debugger(&CALLS);
+
+    assert_eq!(debugger.step(), Stop::Stepped);
+    assert_eq!(pc(&debugger), 0x206);
+    assert_eq!(debugger.emulator().mmu().get_stack_pointer(), 1);
+}
+
+#[test]
+fn step_over_runs_the_whole_call() {
+    let mut debugger = debugger(&CALLS);
+
+    assert_eq!(debugger.step_over(), Stop::Stepped);
+    assert_eq!(pc(&debugger), 0x202);
+    assert_eq!(debugger.emulator().registers().get_vx(0), 1);
+    assert_eq!(debugger.emulator().mmu().get_stack_pointer(), 0);
+
+    // anything else is a single step
+    assert_eq!(debugger.step_over(), Stop::Stepped);
+    assert_eq!(pc(&debugger), 0x204);
+}
+
+#[test]
+fn continue_stops_at_a_breakpoint() {
+    let mut debugger = debugger(&CALLS);
+    debugger.emulator_mut().add_breakpoint(0x204, None);
+
+    assert_eq!(
+        debugger.continue_execution(),
+        Stop::Debug(StopReason::Breakpoint { address: 0x204 })
+    );
+    assert_eq!(pc(&debugger), 0x204);
+}
+
+#[test]
+fn continue_stops_when_interrupted() {
+    let mut debugger = debugger(&COUNTER);
+
+    // the rom never stops by itself, the flag is raised before running
+    debugger.get_interrupt().store(true, Ordering::Relaxed);
+
+    assert_eq!(debugger.continue_execution(), Stop::Interrupted);
+
+    // the flag is consumed by the stop
+    assert!(!debugger.get_interrupt().load(Ordering::Relaxed));
+    assert_eq!(debugger.step(), Stop::Stepped);
+}
+
+#[test]
+fn continue_stops_when_the_rom_loops() {
+    // jump to itself
+    let mut debugger = debugger(&[0x12, 0x00]);
+
+    assert_eq!(debugger.continue_execution(), Stop::Looping(0x200));
+}
+
+#[test]
+fn continue_stops_when_the_rom_exits() {
+    let emulator = Emulator::from_bytes(&[0x00, 0xFD], Config::new(Platform::SuperChip)).unwrap();
+    let mut debugger = Debugger::new(emulator);
+
+    assert_eq!(debugger.continue_execution(), Stop::Exited);
+}
+
+#[test]
+fn continue_stops_on_a_fault() {
+    // return with an empty stack
+    let mut debugger = debugger(&[0x00, 0xEE]);
+
+    assert!(matches!(
+        debugger.continue_execution(),
+        Stop::Debug(StopReason::Fault(_))
+    ));
+}
+
+#[test]
+fn keys_are_held_from_the_prompt() {
+    // skip if key 5 is down, V0 := 5
+    let mut debugger = debugger(&[0x60, 0x05, 0xE0, 0x9E, 0x12, 0x04, 0x12, 0x06]);
+    debugger.set_key_down(5, true);
+
+    debugger.step();
+    debugger.step();
+    assert_eq!(pc(&debugger), 0x206);
+}
@@ -0,0 +1,32 @@
+use chip8_lib::disassembler::disassemble;
+
+use clap::Parser;
+use std::fs;
+use std::process::ExitCode;
+
+fn main() -> ExitCode {
+    // cli arguments
+    let args = Args::parse();
+
+    match fs::read(&args.path) {
+        Ok(rom) => {
+            print!("{}", disassemble(&rom));
+
+            ExitCode::SUCCESS
+        }
+        Err(error) => {
+            eprintln!("Could not load file {}: {}", args.path, error);
+
+            ExitCode::FAILURE
+        }
+    }
+}
+
+// cli arguments struct
+#[derive(Parser, Debug)]
+#[command(version, about = "Disassembles a CHIP-8 rom into labelled assembly", long_about = None)]
+struct Args {
+    /// Path to the rom to disassemble
+    #[arg(short, long)]
+    path: String,
+}
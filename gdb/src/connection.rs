@@ -0,0 +1,156 @@
+use std::collections::VecDeque;
+use std::io::{self, ErrorKind, Read, Write};
+use std::net::TcpStream;
+
+// interrupt sent by the client to stop a running target
+const INTERRUPT: u8 = 0x03;
+
+pub enum Incoming {
+    Packet(String),
+    Interrupt,
+}
+
+// Packets of the remote serial protocol: $data#checksum, acknowledged with + unless in no ack mode
+pub struct Connection {
+    stream: TcpStream,
+    buffer: VecDeque<u8>,
+    no_ack: bool,
+}
+
+impl Connection {
+    pub fn new(stream: TcpStream) -> Self {
+        // packets are small and answered one at a time, don't wait to coalesce them
+        let _ = stream.set_nodelay(true);
+
+        Self {
+            stream,
+            buffer: VecDeque::new(),
+            no_ack: false,
+        }
+    }
+
+    pub fn set_no_ack(&mut self) {
+        self.no_ack = true;
+    }
+
+    // None once the client has disconnected
+    pub fn receive(&mut self) -> io::Result<Option<Incoming>> {
+        loop {
+            let byte = match self.read_byte()? {
+                Some(byte) => byte,
+                None => return Ok(None),
+            };
+
+            match byte {
+                b'$' => break,
+                INTERRUPT => return Ok(Some(Incoming::Interrupt)),
+                // acknowledgements, and anything else outside of a packet
+                _ => continue,
+            }
+        }
+
+        let mut data = Vec::new();
+
+        loop {
+            match self.read_byte()? {
+                Some(b'#') => break,
+                Some(byte) => data.push(byte),
+                None => return Ok(None),
+            }
+        }
+
+        let mut checksum = [0; 2];
+        for digit in checksum.iter_mut() {
+            *digit = self.read_byte()?.unwrap_or_default();
+        }
+
+        let expected = u8::from_str_radix(&String::from_utf8_lossy(&checksum), 16).ok();
+        let valid = expected == Some(compute_checksum(&data));
+
+        if !self.no_ack {
+            self.stream.write_all(if valid { b"+" } else { b"-" })?;
+        }
+
+        if !valid {
+            return self.receive();
+        }
+
+        Ok(Some(Incoming::Packet(
+            String::from_utf8_lossy(&data).into_owned(),
+        )))
+    }
+
+    pub fn send(&mut self, data: &str) -> io::Result<()> {
+        let packet = format!(
+            "${}#{:02x}",
+            escape(data),
+            compute_checksum(escape(data).as_bytes())
+        );
+
+        self.stream.write_all(packet.as_bytes())?;
+
+        if self.no_ack {
+            return Ok(());
+        }
+
+        // resend until acknowledged
+        loop {
+            match self.read_byte()? {
+                Some(b'+') | None => return Ok(()),
+                Some(b'-') => self.stream.write_all(packet.as_bytes())?,
+                Some(_) => {}
+            }
+        }
+    }
+
+    // true if the client asked to stop the target, without waiting
+    pub fn poll_interrupt(&mut self) -> io::Result<bool> {
+        self.stream.set_nonblocking(true)?;
+
+        let mut bytes = [0; 64];
+        let result = self.stream.read(&mut bytes);
+
+        self.stream.set_nonblocking(false)?;
+
+        match result {
+            Ok(count) => self.buffer.extend(&bytes[..count]),
+            Err(error) if error.kind() == ErrorKind::WouldBlock => {}
+            Err(error) => return Err(error),
+        }
+
+        match self.buffer.iter().position(|byte| *byte == INTERRUPT) {
+            Some(index) => {
+                self.buffer.remove(index);
+                Ok(true)
+            }
+            None => Ok(false),
+        }
+    }
+
+    fn read_byte(&mut self) -> io::Result<Option<u8>> {
+        if let Some(byte) = self.buffer.pop_front() {
+            return Ok(Some(byte));
+        }
+
+        let mut byte = [0];
+
+        match self.stream.read(&mut byte)? {
+            0 => Ok(None),
+            _ => Ok(Some(byte[0])),
+        }
+    }
+}
+
+fn compute_checksum(data: &[u8]) -> u8 {
+    data.iter().fold(0, |sum: u8, byte| sum.wrapping_add(*byte))
+}
+
+// $, #, } and * are escaped as } followed by the character xor 0x20
+fn escape(data: &str) -> String {
+    data.chars()
+        .flat_map(|character| match character {
+            '$' | '#' | '}' | '*' => vec!['}', (character as u8 ^ 0x20) as char],
+            _ => vec![character],
+        })
+        .collect()
+}
@@ -0,0 +1,7 @@
+// GDB remote serial protocol server, debugging a rom with gdb or any client of the protocol
+// https://sourceware.org/gdb/current/onlinedocs/gdb.html/Remote-Protocol.html
+
+mod connection;
+mod server;
+
+pub use server::GdbServer;
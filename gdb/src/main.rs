@@ -0,0 +1,56 @@
+use chip8_gdb::GdbServer;
+use chip8_lib::cli::ConfigArgs;
+use chip8_lib::emulator::Emulator;
+
+use clap::Parser;
+use std::net::TcpListener;
+use std::process::ExitCode;
+
+fn main() -> ExitCode {
+    // cli arguments
+    let args = Args::parse();
+
+    match run(&args) {
+        Ok(()) => ExitCode::SUCCESS,
+        Err(error) => {
+            eprintln!("{}", error);
+
+            ExitCode::FAILURE
+        }
+    }
+}
+
+fn run(args: &Args) -> Result<(), String> {
+    let config = args.config.config();
+
+    let emulator = Emulator::from_file(&args.path, config)
+        .map_err(|error| format!("{}: {}", args.path, error))?;
+
+    // only local clients, there is no authentication
+    let listener =
+        TcpListener::bind(("127.0.0.1", args.port)).map_err(|error| error.to_string())?;
+    eprintln!("waiting for gdb on 127.0.0.1:{}", args.port);
+
+    let (stream, address) = listener.accept().map_err(|error| error.to_string())?;
+    eprintln!("connected to {}", address);
+
+    GdbServer::new(emulator)
+        .serve(stream)
+        .map_err(|error| error.to_string())
+}
+
+// cli arguments struct
+#[derive(Parser, Debug)]
+#[command(version, about = "Serves a CHIP-8 rom to gdb over the remote serial protocol", long_about = None)]
+struct Args {
+    /// Path to the rom
+    #[arg(short, long)]
+    path: String,
+
+    #[command(flatten)]
+    config: ConfigArgs,
+
+    /// Local port to listen on
+    #[arg(long, default_value_t = 1234)]
+    port: u16,
+}
@@ -0,0 +1,356 @@
+use chip8_lib::debug::{Access, StopReason, Watchpoint};
+use chip8_lib::emulator::Emulator;
+use chip8_lib::error::CpuFault;
+use chip8_lib::headless::{CaptureRenderer, NullController, NullSynthetizer};
+
+use std::io;
+use std::net::TcpStream;
+
+use crate::connection::{Connection, Incoming};
+
+// registers in the order of the target description: V0-VF, I, PC then the stack pointer
+const REGISTER_COUNT: usize = 19;
+
+const TARGET_XML: &str = r#"<?xml version="1.0"?>
+<!DOCTYPE target SYSTEM "gdb-target.dtd">
+<target version="1.0">
+  <feature name="org.chip8.cpu">
+    <reg name="v0" bitsize="8" type="uint8" regnum="0"/>
+    <reg name="v1" bitsize="8" type="uint8"/>
+    <reg name="v2" bitsize="8" type="uint8"/>
+    <reg name="v3" bitsize="8" type="uint8"/>
+    <reg name="v4" bitsize="8" type="uint8"/>
+    <reg name="v5" bitsize="8" type="uint8"/>
+    <reg name="v6" bitsize="8" type="uint8"/>
+    <reg name="v7" bitsize="8" type="uint8"/>
+    <reg name="v8" bitsize="8" type="uint8"/>
+    <reg name="v9" bitsize="8" type="uint8"/>
+    <reg name="va" bitsize="8" type="uint8"/>
+    <reg name="vb" bitsize="8" type="uint8"/>
+    <reg name="vc" bitsize="8" type="uint8"/>
+    <reg name="vd" bitsize="8" type="uint8"/>
+    <reg name="ve" bitsize="8" type="uint8"/>
+    <reg name="vf" bitsize="8" type="uint8"/>
+    <reg name="i" bitsize="16" type="data_ptr"/>
+    <reg name="pc" bitsize="16" type="code_ptr"/>
+    <reg name="sp" bitsize="8" type="uint8"/>
+  </feature>
+</target>
+"#;
+
+// signals of the stop replies
+const SIGINT: u8 = 2;
+const SIGILL: u8 = 4;
+const SIGTRAP: u8 = 5;
+const SIGSEGV: u8 = 11;
+
+pub struct GdbServer {
+    emulator: Emulator,
+    controller: NullController,
+    renderer: CaptureRenderer,
+    synthetizer: NullSynthetizer,
+}
+
+impl GdbServer {
+    pub fn new(emulator: Emulator) -> Self {
+        Self {
+            emulator,
+            controller: NullController,
+            renderer: CaptureRenderer::new(),
+            synthetizer: NullSynthetizer,
+        }
+    }
+
+    pub fn emulator(&self) -> &Emulator {
+        &self.emulator
+    }
+
+    // serves a single client until it kills the target, detaches or disconnects
+    pub fn serve(&mut self, stream: TcpStream) -> io::Result<()> {
+        let mut connection = Connection::new(stream);
+
+        while let Some(incoming) = connection.receive()? {
+            let packet = match incoming {
+                Incoming::Packet(packet) => packet,
+                // the target is already stopped
+                Incoming::Interrupt => continue,
+            };
+
+            match packet.as_bytes().first() {
+                Some(b'k') => return Ok(()),
+                Some(b'D') => {
+                    connection.send("OK")?;
+                    return Ok(());
+                }
+                Some(b'c') => {
+                    let reply = self.continue_execution(&mut connection)?;
+                    connection.send(&reply)?;
+                }
+                _ => {
+                    let reply = self.handle(&packet);
+                    connection.send(&reply)?;
+
+                    if packet == "QStartNoAckMode" {
+                        connection.set_no_ack();
+                    }
+                }
+            }
+        }
+
+        Ok(())
+    }
+
+    // reply to a packet that doesn't run the rom for long, an empty reply for unsupported packets
+    fn handle(&mut self, packet: &str) -> String {
+        if packet.is_empty() {
+            return String::new();
+        }
+
+        let (command, arguments) = packet.split_at(1);
+
+        match command {
+            "?" => stop_reply(SIGTRAP),
+            "q" | "Q" => self.handle_query(packet),
+            // there is a single thread
+            "H" => "OK".to_string(),
+            "g" => (0..REGISTER_COUNT)
+                .map(|index| self.read_register(index))
+                .collect(),
+            "p" => match usize::from_str_radix(arguments, 16) {
+                Ok(index) if index < REGISTER_COUNT => self.read_register(index),
+                _ => error(1),
+            },
+            "m" => self.read_memory(arguments).unwrap_or_else(|| error(1)),
+            "M" => self.write_memory(arguments).unwrap_or_else(|| error(1)),
+            "Z" | "z" => self
+                .handle_breakpoint(command == "Z", arguments)
+                .unwrap_or_else(|| error(1)),
+            "s" => {
+                let stop_reason = self.emulator.step_instruction(
+                    &self.controller,
+                    &mut self.renderer,
+                    &mut self.synthetizer,
+                );
+
+                match stop_reason {
+                    _ if self.emulator.has_exited() => "W00".to_string(),
+                    Some(stop_reason) => watch_stop_reply(stop_reason),
+                    None => stop_reply(SIGTRAP),
+                }
+            }
+            _ => String::new(),
+        }
+    }
+
+    fn handle_query(&self, packet: &str) -> String {
+        if packet.starts_with("qSupported") {
+            return "PacketSize=4000;qXfer:features:read+;swbreak+;QStartNoAckMode+".to_string();
+        }
+
+        if let Some(range) = packet.strip_prefix("qXfer:features:read:target.xml:") {
+            return read_annex(TARGET_XML, range)
+                .unwrap_or_else(|| error(0))
+                .to_string();
+        }
+
+        match packet {
+            "QStartNoAckMode" => "OK".to_string(),
+            // attached to an existing process, killing it on exit is fine
+            "qAttached" => "1".to_string(),
+            "qfThreadInfo" => "m1".to_string(),
+            "qsThreadInfo" => "l".to_string(),
+            "qC" => "QC1".to_string(),
+            _ => String::new(),
+        }
+    }
+
+    // big endian, as the CHIP-8 stores shorts
+    fn read_register(&self, index: usize) -> String {
+        let registers = self.emulator.registers();
+
+        match index {
+            0..=15 => format!("{:02x}", registers.get_vx(index)),
+            16 => format!("{:04x}", registers.get_i()),
+            17 => format!("{:04x}", registers.get_pc()),
+            _ => format!("{:02x}", self.emulator.mmu().get_stack_pointer()),
+        }
+    }
+
+    // m addr,length
+    fn read_memory(&self, arguments: &str) -> Option<String> {
+        let (address, length) = arguments.split_once(',')?;
+        let range = self.memory_range(address, length)?;
+
+        Some(
+            range
+                .map(|address| format!("{:02x}", self.emulator.mmu().peek_byte(address)))
+                .collect(),
+        )
+    }
+
+    // M addr,length:bytes
+    fn write_memory(&mut self, arguments: &str) -> Option<String> {
+        let (location, data) = arguments.split_once(':')?;
+        let (address, length) = location.split_once(',')?;
+        let range = self.memory_range(address, length)?;
+
+        let bytes = decode_hex(data)?;
+        if bytes.len() != range.len() {
+            return None;
+        }
+
+        for (address, byte) in range.zip(bytes) {
+            self.emulator.poke_byte(address, byte);
+        }
+
+        Some("OK".to_string())
+    }
+
+    // addresses past the end of memory are errors rather than wrapping around
+    fn memory_range(&self, address: &str, length: &str) -> Option<std::ops::Range<usize>> {
+        let address = usize::from_str_radix(address, 16).ok()?;
+        let length = usize::from_str_radix(length, 16).ok()?;
+        let memory_size = self.emulator.mmu().get_address_mask() + 1;
+
+        if address.checked_add(length)? > memory_size {
+            return None;
+        }
+
+        Some(address..address + length)
+    }
+
+    // Z type,addr,kind: 0 software breakpoint, 2 write, 3 read and 4 access watchpoint
+    fn handle_breakpoint(&mut self, insert: bool, arguments: &str) -> Option<String> {
+        let mut fields = arguments.split(',');
+        let kind = fields.next()?;
+        let address = u16::from_str_radix(fields.next()?, 16).ok()?;
+        let length = fields
+            .next()
+            .and_then(|length| u16::from_str_radix(length, 16).ok())
+            .unwrap_or(1)
+            .max(1);
+        let range = address..=address.checked_add(length - 1)?;
+
+        let watchpoint = match kind {
+            "0" => {
+                if insert {
+                    self.emulator.add_breakpoint(address, None);
+                } else {
+                    self.emulator.remove_breakpoint(address);
+                }
+
+                return Some("OK".to_string());
+            }
+            "2" => Watchpoint::write(range),
+            "3" => Watchpoint::read(range),
+            "4" => Watchpoint::read_write(range),
+            // hardware breakpoints aren't supported
+            _ => return Some(String::new()),
+        };
+
+        if insert {
+            self.emulator.add_watchpoint(watchpoint);
+        } else {
+            self.emulator.remove_watchpoint(&watchpoint);
+        }
+
+        Some("OK".to_string())
+    }
+
+    // runs frames until a breakpoint, a watchpoint, the rom exits or the client interrupts
+    fn continue_execution(&mut self, connection: &mut Connection) -> io::Result<String> {
+        loop {
+            if self.emulator.has_exited() {
+                return Ok("W00".to_string());
+            }
+
+            // a halted cpu never runs again
+            if let Some(fault) = self.emulator.get_fault() {
+                return Ok(stop_reply(fault_signal(fault)));
+            }
+
+            let report = self.emulator.step_frame(
+                &self.controller,
+                &mut self.renderer,
+                &mut self.synthetizer,
+            );
+
+            match report.stop_reason {
+                Some(stop_reason @ StopReason::Watchpoint { .. }) => {
+                    return Ok(watch_stop_reply(stop_reason))
+                }
+                Some(StopReason::Breakpoint { .. }) => return Ok(stop_reply(SIGTRAP)),
+                Some(StopReason::Fault(fault)) => return Ok(stop_reply(fault_signal(fault))),
+                None => {}
+            }
+
+            if connection.poll_interrupt()? {
+                return Ok(stop_reply(SIGINT));
+            }
+        }
+    }
+}
+
+fn stop_reply(signal: u8) -> String {
+    format!("S{:02x}", signal)
+}
+
+// execute watchpoints are reported as software breakpoints
+fn watch_stop_reply(stop_reason: StopReason) -> String {
+    match stop_reason {
+        StopReason::Watchpoint {
+            address, access, ..
+        } => {
+            let kind = match access {
+                Access::Read => "rwatch",
+                Access::Write => "watch",
+                Access::Execute => "swbreak",
+            };
+
+            match access {
+                Access::Execute => format!("T{:02x}{}:;", SIGTRAP, kind),
+                _ => format!("T{:02x}{}:{:x};", SIGTRAP, kind, address),
+            }
+        }
+        StopReason::Breakpoint { .. } => format!("T{:02x}swbreak:;", SIGTRAP),
+        StopReason::Fault(fault) => stop_reply(fault_signal(fault)),
+    }
+}
+
+// invalid opcodes are illegal instructions, other faults bad accesses to the stack or memory
+fn fault_signal(fault: CpuFault) -> u8 {
+    match fault {
+        CpuFault::InvalidOpcode { .. } => SIGILL,
+        _ => SIGSEGV,
+    }
+}
+
+fn error(code: u8) -> String {
+    format!("E{:02x}", code)
+}
+
+// offset,length of a qXfer read, m when more data follows and l for the last chunk
+fn read_annex(annex: &str, range: &str) -> Option<String> {
+    let (offset, length) = range.split_once(',')?;
+    let offset = usize::from_str_radix(offset, 16).ok()?;
+    let length = usize::from_str_radix(length, 16).ok()?;
+
+    let data = annex.get(offset.min(annex.len())..)?;
+
+    if data.len() > length {
+        Some(format!("m{}", &data[..length]))
+    } else {
+        Some(format!("l{}", data))
+    }
+}
+
+fn decode_hex(data: &str) -> Option<Vec<u8>> {
+    if !data.len().is_multiple_of(2) {
+        return None;
+    }
+
+    (0..data.len())
+        .step_by(2)
+        .map(|index| u8::from_str_radix(data.get(index..index + 2)?, 16).ok())
+        .collect()
+}
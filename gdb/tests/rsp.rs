@@ -0,0 +1,179 @@
+// Scripted client talking to the server over a local socket
+
+use chip8_gdb::GdbServer;
+use chip8_lib::config::Config;
+use chip8_lib::emulator::Emulator;
+use chip8_lib::platform::Platform;
+use std::io::{Read, Write};
+use std::net::{TcpListener, TcpStream};
+use std::thread::{self, JoinHandle};
+
+// V0 += 1, I = 0x300, [I] = V0, jump back to 0x200
+const COUNTER: [u8; 8] = [0x70, 0x01, 0xA3, 0x00, 0xF0, 0x55, 0x12, 0x00];
+
+struct Client {
+    stream: TcpStream,
+}
+
+impl Client {
+    fn request(&mut self, data: &str) -> String {
+        self.send(data);
+        self.receive()
+    }
+
+    fn send(&mut self, data: &str) {
+        let checksum = data.bytes().fold(0u8, |sum, byte| sum.wrapping_add(byte));
+
+        write!(self.stream, "${}#{:02x}", data, checksum).unwrap();
+        assert_eq!(self.read_byte(), b'+');
+    }
+
+    fn receive(&mut self) -> String {
+        while self.read_byte() != b'$' {}
+
+        let mut data = Vec::new();
+        loop {
+            match self.read_byte() {
+                b'#' => break,
+                byte => data.push(byte),
+            }
+        }
+
+        let checksum = [self.read_byte(), self.read_byte()];
+        let checksum = u8::from_str_radix(std::str::from_utf8(&checksum).unwrap(), 16).unwrap();
+        assert_eq!(
+            checksum,
+            data.iter().fold(0u8, |sum, byte| sum.wrapping_add(*byte))
+        );
+
+        self.stream.write_all(b"+").unwrap();
+
+        String::from_utf8(data).unwrap()
+    }
+
+    fn read_byte(&mut self) -> u8 {
+        let mut byte = [0];
+        self.stream.read_exact(&mut byte).unwrap();
+
+        byte[0]
+    }
+}
+
+fn connect(rom: &[u8]) -> (Client, JoinHandle<()>) {
+    let emulator = Emulator::from_bytes(rom, Config::new(Platform::Chip8)).unwrap();
+    let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+    let address = listener.local_addr().unwrap();
+
+    let server = thread::spawn(move || {
+        let (stream, _) = listener.accept().unwrap();
+
+        GdbServer::new(emulator).serve(stream).unwrap();
+    });
+
+    let stream = TcpStream::connect(address).unwrap();
+    stream.set_nodelay(true).unwrap();
+
+    (Client { stream }, server)
+}
+
+#[test]
+fn handshake() {
+    let (mut client, server) = connect(&COUNTER);
+
+    assert!(client
+        .request("qSupported:swbreak+;xmlRegisters=i386")
+        .contains("qXfer:features:read+"));
+    assert_eq!(client.request("?"), "S05");
+
+    let target = client.request("qXfer:features:read:target.xml:0,fff");
+    assert!(target.starts_with('l'));
+    assert!(target.contains(r#"<reg name="pc" bitsize="16" type="code_ptr"/>"#));
+
+    // read in chunks
+    let chunk = client.request("qXfer:features:read:target.xml:0,10");
+    assert_eq!(chunk, "m<?xml version=\"1");
+
+    client.send("k");
+    server.join().unwrap();
+}
+
+#[test]
+fn registers_and_memory() {
+    let (mut client, server) = connect(&COUNTER);
+
+    // V0-VF, I, PC and the stack pointer
+    assert_eq!(
+        client.request("g"),
+        format!("{}00000200{}", "00".repeat(16), "00")
+    );
+    assert_eq!(client.request("p11"), "0200");
+    assert_eq!(client.request("p13"), "E01");
+
+    assert_eq!(client.request("m200,8"), "7001a300f0551200");
+    assert_eq!(client.request("mffe,2"), "0000");
+    assert_eq!(client.request("mfff,2"), "E01");
+
+    // V0 += 5
+    assert_eq!(client.request("M201,1:05"), "OK");
+    assert_eq!(client.request("s"), "S05");
+    assert_eq!(client.request("p0"), "05");
+    assert_eq!(client.request("p11"), "0202");
+
+    client.send("D");
+    assert_eq!(client.receive(), "OK");
+    server.join().unwrap();
+}
+
+#[test]
+fn breakpoints_and_watchpoints() {
+    let (mut client, server) = connect(&COUNTER);
+
+    // stops before the jump, every time around the loop
+    assert_eq!(client.request("Z0,206,2"), "OK");
+    assert_eq!(client.request("c"), "S05");
+    assert_eq!(client.request("p11"), "0206");
+    assert_eq!(client.request("c"), "S05");
+    assert_eq!(client.request("p0"), "02");
+    assert_eq!(client.request("z0,206,2"), "OK");
+
+    // fx55 writes V0 to 0x300
+    assert_eq!(client.request("Z2,300,1"), "OK");
+    assert_eq!(client.request("c"), "T05watch:300;");
+    assert_eq!(client.request("m300,1"), "03");
+    assert_eq!(client.request("z2,300,1"), "OK");
+
+    client.send("k");
+    server.join().unwrap();
+}
+
+#[test]
+fn interrupt_stops_a_running_rom() {
+    let (mut client, server) = connect(&COUNTER);
+
+    client.send("c");
+    client.stream.write_all(&[0x03]).unwrap();
+    assert_eq!(client.receive(), "S02");
+
+    // it ran for a while
+    assert_ne!(client.request("p0"), "00");
+
+    client.send("k");
+    server.join().unwrap();
+}
+
+#[test]
+fn no_ack_mode() {
+    let (mut client, server) = connect(&COUNTER);
+
+    assert_eq!(client.request("QStartNoAckMode"), "OK");
+
+    // neither side acknowledges anymore
+    client.stream.write_all(b"$g#67").unwrap();
+    assert_eq!(
+        client.receive(),
+        format!("{}00000200{}", "00".repeat(16), "00")
+    );
+
+    client.stream.write_all(b"$k#6b").unwrap();
+    server.join().unwrap();
+}
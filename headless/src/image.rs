@@ -0,0 +1,150 @@
+// Encoders for a captured screen, one byte per pixel holding its plane index (0 to 3)
+
+// colours of the pixels by plane index, same as the application
+const PALETTE: [[u8; 3]; 4] = [
+    [0x00, 0x00, 0x00],
+    [0xFF, 0xFF, 0xFF],
+    [0xAA, 0xAA, 0xAA],
+    [0x55, 0x55, 0x55],
+];
+
+// characters of the pixels by plane index
+const ASCII_PIXELS: [char; 4] = ['.', '#', '+', '@'];
+
+// one line per row
+pub fn to_ascii(screen: &[u8], width: usize) -> String {
+    screen
+        .chunks(width)
+        .map(|row| {
+            let mut line: String = row
+                .iter()
+                .map(|pixel| ASCII_PIXELS[(pixel & 0x3) as usize])
+                .collect();
+            line.push('\n');
+            line
+        })
+        .collect()
+}
+
+// binary portable bitmap, any lit plane is black
+pub fn to_pbm(screen: &[u8], width: usize, height: usize) -> Vec<u8> {
+    let mut bytes = format!("P4\n{} {}\n", width, height).into_bytes();
+
+    for row in screen.chunks(width) {
+        for pixels in row.chunks(8) {
+            let byte = pixels
+                .iter()
+                .enumerate()
+                .filter(|(_, pixel)| **pixel != 0)
+                .fold(0u8, |byte, (bit, _)| byte | (0x80 >> bit));
+            bytes.push(byte);
+        }
+    }
+
+    bytes
+}
+
+// indexed colour png, each pixel scaled to a square of scale x scale
+pub fn to_png(screen: &[u8], width: usize, height: usize, scale: usize) -> Vec<u8> {
+    let scaled_width = width * scale;
+    let scaled_height = height * scale;
+
+    let mut header = Vec::new();
+    header.extend_from_slice(&(scaled_width as u32).to_be_bytes());
+    header.extend_from_slice(&(scaled_height as u32).to_be_bytes());
+    // bit depth 8, indexed colour, default compression, filter and interlace
+    header.extend_from_slice(&[8, 3, 0, 0, 0]);
+
+    let palette: Vec<u8> = PALETTE.iter().flatten().copied().collect();
+
+    // each scanline starts with its filter type, 0 being none
+    let scanlines = screen.chunks(width).flat_map(|row| {
+        let scanline: Vec<u8> = std::iter::once(0)
+            .chain(
+                row.iter()
+                    .flat_map(|pixel| std::iter::repeat_n(pixel & 0x3, scale)),
+            )
+            .collect();
+
+        std::iter::repeat_n(scanline, scale)
+    });
+    let pixels: Vec<u8> = scanlines.flatten().collect();
+
+    let mut png = vec![0x89, b'P', b'N', b'G', 0x0D, 0x0A, 0x1A, 0x0A];
+    write_chunk(&mut png, b"IHDR", &header);
+    write_chunk(&mut png, b"PLTE", &palette);
+    write_chunk(&mut png, b"IDAT", &zlib_stored(&pixels));
+    write_chunk(&mut png, b"IEND", &[]);
+
+    png
+}
+
+// 64 bit FNV-1a of the dimensions and pixels
+pub fn screen_hash(screen: &[u8], width: usize, height: usize) -> u64 {
+    (width as u32)
+        .to_be_bytes()
+        .iter()
+        .chain((height as u32).to_be_bytes().iter())
+        .chain(screen.iter())
+        .fold(0xCBF29CE484222325, |hash, byte| {
+            (hash ^ *byte as u64).wrapping_mul(0x100000001B3)
+        })
+}
+
+fn write_chunk(png: &mut Vec<u8>, kind: &[u8; 4], data: &[u8]) {
+    png.extend_from_slice(&(data.len() as u32).to_be_bytes());
+
+    let start = png.len();
+    png.extend_from_slice(kind);
+    png.extend_from_slice(data);
+
+    let crc = crc32(&png[start..]);
+    png.extend_from_slice(&crc.to_be_bytes());
+}
+
+// zlib stream made of uncompressed deflate blocks, screens are small enough not to bother compressing
+fn zlib_stored(data: &[u8]) -> Vec<u8> {
+    let mut stream = vec![0x78, 0x01];
+    let mut blocks = data.chunks(0xFFFF).peekable();
+
+    if blocks.peek().is_none() {
+        stream.extend_from_slice(&[0x01, 0x00, 0x00, 0xFF, 0xFF]);
+    }
+
+    while let Some(block) = blocks.next() {
+        let last = blocks.peek().is_none();
+        let length = block.len() as u16;
+
+        stream.push(last as u8);
+        stream.extend_from_slice(&length.to_le_bytes());
+        stream.extend_from_slice(&(!length).to_le_bytes());
+        stream.extend_from_slice(block);
+    }
+
+    stream.extend_from_slice(&adler32(data).to_be_bytes());
+
+    stream
+}
+
+// CRC-32 of the png chunks, the one of zlib and ethernet
+pub fn crc32(data: &[u8]) -> u32 {
+    !data.iter().fold(0xFFFFFFFF, |crc, byte| {
+        (0..8).fold(crc ^ *byte as u32, |crc, _| {
+            if crc & 1 != 0 {
+                (crc >> 1) ^ 0xEDB88320
+            } else {
+                crc >> 1
+            }
+        })
+    })
+}
+
+// checksum ending zlib streams
+pub fn adler32(data: &[u8]) -> u32 {
+    let (a, b) = data.iter().fold((1u32, 0u32), |(a, b), byte| {
+        let a = (a + *byte as u32) % 65521;
+        (a, (b + a) % 65521)
+    });
+
+    (b << 16) | a
+}
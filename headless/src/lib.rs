@@ -0,0 +1,3 @@
+// Screen encoders of the headless runner
+
+pub mod image;
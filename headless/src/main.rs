@@ -0,0 +1,174 @@
+use chip8_headless::image;
+use chip8_lib::cli::ConfigArgs;
+use chip8_lib::emulator::Emulator;
+use chip8_lib::headless::{CaptureRenderer, NullController, NullSynthetizer};
+use chip8_lib::trace::{OpcodeFilter, Tracer};
+
+use clap::{Parser, ValueEnum};
+use std::fs;
+use std::io::{self, Write};
+use std::ops::RangeInclusive;
+use std::process::ExitCode;
+
+fn main() -> ExitCode {
+    // cli arguments
+    let args = Args::parse();
+
+    match run(&args) {
+        Ok(()) => ExitCode::SUCCESS,
+        Err(error) => {
+            eprintln!("{}", error);
+
+            ExitCode::FAILURE
+        }
+    }
+}
+
+fn run(args: &Args) -> Result<(), String> {
+    let config = args.config.config();
+
+    let mut emulator = Emulator::from_file(&args.path, config)
+        .map_err(|error| format!("{}: {}", args.path, error))?;
+
+    if let Some(path) = &args.trace {
+        let mut tracer = Tracer::to_file(path).map_err(|error| format!("{}: {}", path, error))?;
+
+        for range in &args.trace_range {
+            tracer = tracer.with_address_range(range.clone());
+        }
+
+        for filter in &args.trace_opcode {
+            tracer = tracer.with_opcode_filter(*filter);
+        }
+
+        if let Some(size) = args.trace_ring {
+            tracer = tracer.with_ring_buffer(size);
+        }
+
+        emulator.set_tracer(tracer);
+    }
+
+    let controller = NullController;
+    let mut renderer = CaptureRenderer::new();
+    let mut synthetizer = NullSynthetizer;
+
+    // run until the frame count is reached, or the rom ends
+    for _ in 0..args.frames {
+        emulator.step_frame(&controller, &mut renderer, &mut synthetizer);
+
+        if emulator.has_exited() || emulator.is_looping() || emulator.get_fault().is_some() {
+            break;
+        }
+    }
+
+    if let (Some(tracer), Some(path)) = (emulator.take_tracer(), &args.trace) {
+        tracer
+            .finish()
+            .map_err(|error| format!("{}: {}", path, error))?;
+    }
+
+    let screen = renderer.get_screen();
+    let width = renderer.get_width();
+    let height = renderer.get_height();
+
+    let output = match args.format {
+        Format::Ascii => image::to_ascii(screen, width).into_bytes(),
+        Format::Pbm => image::to_pbm(screen, width, height),
+        Format::Png => image::to_png(screen, width, height, args.scale as usize),
+    };
+
+    match &args.output {
+        Some(path) => {
+            fs::write(path, &output).map_err(|error| format!("{}: {}", path, error))?;
+        }
+        None => {
+            io::stdout()
+                .write_all(&output)
+                .map_err(|error| error.to_string())?;
+        }
+    }
+
+    if args.hash {
+        eprintln!("{:016x}", image::screen_hash(screen, width, height));
+    }
+
+    // the screen is dumped as the rom left it, before failing
+    match emulator.get_fault() {
+        Some(fault) => Err(fault.to_string()),
+        None => Ok(()),
+    }
+}
+
+// cli arguments struct
+#[derive(Parser, Debug)]
+#[command(version, about = "Runs a CHIP-8 rom without a display and dumps its final screen", long_about = None)]
+struct Args {
+    /// Path to the rom
+    #[arg(short, long)]
+    path: String,
+
+    #[command(flatten)]
+    config: ConfigArgs,
+
+    /// Frames to run, stops earlier when the rom jumps to itself or exits
+    #[arg(short, long, default_value_t = 600)]
+    frames: u32,
+
+    /// Format of the screen dump
+    #[arg(long, value_enum, default_value_t = Format::Ascii)]
+    format: Format,
+
+    /// File to write the screen to, defaults to the standard output
+    #[arg(short, long)]
+    output: Option<String>,
+
+    /// Size of a pixel in the png, in pixels
+    #[arg(long, default_value_t = 1, value_parser = clap::value_parser!(u32).range(1..))]
+    scale: u32,
+
+    /// Print a hash of the final screen on the standard error
+    #[arg(long)]
+    hash: bool,
+
+    /// File to log every executed instruction to
+    #[arg(long)]
+    trace: Option<String>,
+
+    /// Only log the instructions in the address range, e.g. 200-2ff, can be repeated
+    #[arg(long, value_parser = parse_range, requires = "trace")]
+    trace_range: Vec<RangeInclusive<u16>>,
+
+    /// Only log the opcodes matching the pattern, e.g. dxyn or 8xy4, can be repeated
+    #[arg(long, requires = "trace")]
+    trace_opcode: Vec<OpcodeFilter>,
+
+    /// Only keep the last instructions, written when an invalid opcode is hit
+    #[arg(long, requires = "trace")]
+    trace_ring: Option<usize>,
+}
+
+// hexadecimal addresses, first-last
+fn parse_range(text: &str) -> Result<RangeInclusive<u16>, String> {
+    let parse = |address: &str| {
+        u16::from_str_radix(address.trim_start_matches("0x"), 16)
+            .map_err(|_| format!("invalid address '{}'", address))
+    };
+
+    match text.split_once('-') {
+        Some((first, last)) => Ok(parse(first)?..=parse(last)?),
+        None => {
+            let address = parse(text)?;
+            Ok(address..=address)
+        }
+    }
+}
+
+#[derive(ValueEnum, Clone, Copy, Debug)]
+enum Format {
+    /// One character per pixel
+    Ascii,
+    /// Binary portable bitmap, black and white
+    Pbm,
+    /// Indexed colour png
+    Png,
+}
@@ -0,0 +1,93 @@
+// Screen encoders checked against known answers
+
+use chip8_headless::image::{adler32, crc32, screen_hash, to_ascii, to_pbm, to_png};
+
+#[test]
+fn crc32_known_answers() {
+    assert_eq!(crc32(b""), 0);
+    assert_eq!(crc32(b"123456789"), 0xCBF43926);
+    // the crc ending every png
+    assert_eq!(crc32(b"IEND"), 0xAE426082);
+}
+
+#[test]
+fn adler32_known_answers() {
+    assert_eq!(adler32(b""), 1);
+    assert_eq!(adler32(b"Wikipedia"), 0x11E60398);
+    // both sums wrap around at 65521
+    assert_eq!(adler32(&[0xFF; 1024]), 0x79A6FC2E);
+}
+
+#[test]
+fn ascii() {
+    let screen = [0, 1, 2, 3, 1, 0, 0, 0];
+
+    assert_eq!(to_ascii(&screen, 4), ".#+@\n#...\n");
+}
+
+#[test]
+fn pbm() {
+    // rows are padded to a whole byte, any plane is black
+    let mut screen = [0; 20];
+    screen[0] = 1;
+    screen[9] = 2;
+    screen[17] = 3;
+
+    let mut expected = b"P4\n10 2\n".to_vec();
+    expected.extend_from_slice(&[0x80, 0x40, 0x01, 0x00]);
+
+    assert_eq!(to_pbm(&screen, 10, 2), expected);
+}
+
+#[test]
+fn png() {
+    let png = to_png(&[1, 2], 2, 1, 2);
+
+    let mut expected = vec![0x89, b'P', b'N', b'G', 0x0D, 0x0A, 0x1A, 0x0A];
+    // 4x2, 8 bits indexed colour
+    expected.extend_from_slice(&[0, 0, 0, 13]);
+    expected.extend_from_slice(b"IHDR");
+    expected.extend_from_slice(&[0, 0, 0, 4, 0, 0, 0, 2, 8, 3, 0, 0, 0]);
+    expected.extend_from_slice(&[0x48, 0x76, 0x8D, 0x51]);
+    // the 4 colours of the palette
+    expected.extend_from_slice(&[0, 0, 0, 12]);
+    expected.extend_from_slice(b"PLTE");
+    expected.extend_from_slice(&[
+        0x00, 0x00, 0x00, 0xFF, 0xFF, 0xFF, 0xAA, 0xAA, 0xAA, 0x55, 0x55, 0x55,
+    ]);
+    expected.extend_from_slice(&crc32(&expected[expected.len() - 16..]).to_be_bytes());
+    // a single stored deflate block of 2 scanlines, each starting with filter 0
+    expected.extend_from_slice(&[0, 0, 0, 21]);
+    expected.extend_from_slice(b"IDAT");
+    expected.extend_from_slice(&[0x78, 0x01, 0x01, 0x0A, 0x00, 0xF5, 0xFF]);
+    expected.extend_from_slice(&[0, 1, 1, 2, 2, 0, 1, 1, 2, 2]);
+    expected.extend_from_slice(&[0x00, 0x42, 0x00, 0x0D]);
+    expected.extend_from_slice(&[0x37, 0x67, 0xBC, 0x50]);
+    expected.extend_from_slice(&[0, 0, 0, 0]);
+    expected.extend_from_slice(b"IEND");
+    expected.extend_from_slice(&[0xAE, 0x42, 0x60, 0x82]);
+
+    assert_eq!(png, expected);
+}
+
+#[test]
+fn png_splits_large_screens_in_blocks() {
+    // 128x64 scaled 8 times is more than the 65535 bytes of a stored block
+    let png = to_png(&[1; 128 * 64], 128, 64, 8);
+    let scanlines: usize = 64 * 8 * (1 + 128 * 8);
+
+    // after the signature, IHDR and PLTE: zlib header, a 5 bytes header per block, the data and the adler32
+    let blocks = scanlines.div_ceil(0xFFFF);
+    let idat_length = 2 + 5 * blocks + scanlines + 4;
+
+    assert_eq!(&png[57..61], (idat_length as u32).to_be_bytes());
+    assert_eq!(&png[61..65], b"IDAT");
+}
+
+#[test]
+fn hash_depends_on_the_dimensions() {
+    let screen = [0; 64 * 32];
+
+    assert_eq!(screen_hash(&screen, 64, 32), screen_hash(&screen, 64, 32));
+    assert_ne!(screen_hash(&screen, 64, 32), screen_hash(&screen, 32, 64));
+}
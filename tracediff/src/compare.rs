@@ -0,0 +1,120 @@
+use chip8_lib::emulator::Emulator;
+
+// what differs between the two emulators, as "name: first / second" lines
+pub fn differences(first: &Emulator, second: &Emulator) -> Vec<String> {
+    let mut differences = Vec::new();
+
+    let mut compare = |name: &str, first: String, second: String| {
+        if first != second {
+            differences.push(format!("{}: {} / {}", name, first, second));
+        }
+    };
+
+    compare(
+        "exited",
+        first.has_exited().to_string(),
+        second.has_exited().to_string(),
+    );
+
+    let (registers, other_registers) = (first.registers(), second.registers());
+
+    compare(
+        "PC",
+        format!("{:#06x}", registers.get_pc()),
+        format!("{:#06x}", other_registers.get_pc()),
+    );
+
+    for index in 0..16 {
+        compare(
+            &format!("V{:X}", index),
+            format!("{:#04x}", registers.get_vx(index)),
+            format!("{:#04x}", other_registers.get_vx(index)),
+        );
+    }
+
+    compare(
+        "I",
+        format!("{:#06x}", registers.get_i()),
+        format!("{:#06x}", other_registers.get_i()),
+    );
+
+    let (mmu, other_mmu) = (first.mmu(), second.mmu());
+
+    compare(
+        "stack",
+        format!("{:04x?}", mmu.get_stack()),
+        format!("{:04x?}", other_mmu.get_stack()),
+    );
+    compare(
+        "DT",
+        format!("{:#04x}", mmu.get_delay_timer()),
+        format!("{:#04x}", other_mmu.get_delay_timer()),
+    );
+    compare(
+        "ST",
+        format!("{:#04x}", mmu.get_sound_timer()),
+        format!("{:#04x}", other_mmu.get_sound_timer()),
+    );
+
+    // the first differing byte, and how many others differ
+    let memory = mmu.get_memory();
+    let other_memory = other_mmu.get_memory();
+    let mut addresses = (0..memory.len().min(other_memory.len()))
+        .filter(|address| memory[*address] != other_memory[*address]);
+
+    if let Some(address) = addresses.next() {
+        let others = addresses.count();
+
+        compare(
+            &format!("memory at {:#06x}", address),
+            format!("{:#04x}", memory[address]),
+            format!(
+                "{:#04x}{}",
+                other_memory[address],
+                plural(others, "other byte")
+            ),
+        );
+    }
+
+    compare(
+        "memory size",
+        memory.len().to_string(),
+        other_memory.len().to_string(),
+    );
+
+    let width = mmu.get_screen_width();
+    let other_width = other_mmu.get_screen_width();
+
+    compare(
+        "screen size",
+        format!("{}x{}", width, mmu.get_screen_height()),
+        format!("{}x{}", other_width, other_mmu.get_screen_height()),
+    );
+
+    if width == other_width {
+        let screen = mmu.get_screen();
+        let other_screen = other_mmu.get_screen();
+        let mut pixels = (0..screen.len().min(other_screen.len()))
+            .filter(|index| screen[*index] != other_screen[*index]);
+
+        if let Some(index) = pixels.next() {
+            let others = pixels.count();
+
+            compare(
+                &format!("pixel at {},{}", index % width, index / width),
+                screen[index].to_string(),
+                format!("{}{}", other_screen[index], plural(others, "other pixel")),
+            );
+        }
+    }
+
+    differences
+}
+
+fn plural(count: usize, name: &str) -> String {
+    match count {
+        0 => String::new(),
+        1 => format!(" (and 1 {})", name),
+        _ => format!(" (and {} {}s)", count, name),
+    }
+}
@@ -0,0 +1,3 @@
+// State comparison of the trace diff tool
+
+pub mod compare;
@@ -0,0 +1,319 @@
+use chip8_lib::cli::{ConfigArgs, PlatformArg, QuirksProfile};
+use chip8_lib::config::Config;
+use chip8_lib::emulator::Emulator;
+use chip8_lib::headless::{CaptureRenderer, NullSynthetizer};
+use chip8_lib::interfaces::Controller;
+use chip8_lib::trace::Tracer;
+use chip8_tracediff::compare;
+
+use clap::Parser;
+use std::cell::Cell;
+use std::collections::VecDeque;
+use std::fs;
+use std::io::{self, Write};
+use std::ops::Range;
+use std::process::ExitCode;
+use std::sync::{Arc, Mutex};
+
+// seed of the runs without --seed, which a trace compared with --against has to be recorded with
+const DEFAULT_SEED: u64 = 0;
+
+fn main() -> ExitCode {
+    // cli arguments
+    let args = Args::parse();
+
+    let result = match &args.against {
+        Some(path) => against_trace(&args, path),
+        None => against_config(&args),
+    };
+
+    match result {
+        Ok(true) => ExitCode::SUCCESS,
+        // the runs diverged
+        Ok(false) => ExitCode::from(1),
+        Err(error) => {
+            eprintln!("{}", error);
+
+            ExitCode::from(2)
+        }
+    }
+}
+
+// the same rom and input, run in lockstep under two configurations
+fn against_config(args: &Args) -> Result<bool, String> {
+    let other = ConfigArgs {
+        platform: args.other_platform.unwrap_or(args.config.platform),
+        quirks: args.other_quirks.or(args.config.quirks),
+        ..args.config.clone()
+    };
+
+    let mut first = Run::new(args, config(&args.config), args.context)?;
+    let mut second = Run::new(args, config(&other), args.context)?;
+
+    for cycle in 0..args.cycles {
+        if first.emulator.has_exited() && second.emulator.has_exited() {
+            break;
+        }
+
+        first.step();
+        second.step();
+
+        let differences = compare::differences(&first.emulator, &second.emulator);
+
+        if !differences.is_empty() {
+            println!(
+                "first difference after cycle {}, frame {}",
+                cycle,
+                first.emulator.get_frame_count()
+            );
+
+            for difference in differences {
+                println!("  {}", difference);
+            }
+
+            println!();
+            first.print_context("last instructions of the first run");
+            println!();
+            second.print_context("last instructions of the second run");
+
+            return Ok(false);
+        }
+    }
+
+    println!("no difference in {} cycles", args.cycles);
+
+    Ok(true)
+}
+
+// the rom run under the first configuration, against the lines of an unfiltered trace
+fn against_trace(args: &Args, path: &str) -> Result<bool, String> {
+    let trace = fs::read_to_string(path).map_err(|error| format!("{}: {}", path, error))?;
+    let mut run = Run::new(args, config(&args.config), 0)?;
+    let mut context: VecDeque<&str> = VecDeque::new();
+
+    // lines starting with # are dumps of the ring buffer, not instructions
+    let expected_lines = trace
+        .lines()
+        .filter(|line| !line.is_empty() && !line.starts_with('#'));
+
+    for (cycle, expected) in expected_lines.take(args.cycles as usize).enumerate() {
+        let actual = run.next_line(args);
+
+        if actual != expected {
+            println!(
+                "first difference at cycle {}, frame {}",
+                cycle,
+                run.emulator.get_frame_count()
+            );
+            println!("  trace: {}", expected);
+            println!("  run:   {}", actual);
+            println!();
+            println!("last instructions of the trace");
+
+            for line in context {
+                println!("  {}", line);
+            }
+
+            return Ok(false);
+        }
+
+        if context.len() == args.context {
+            context.pop_front();
+        }
+        context.push_back(expected);
+    }
+
+    println!("no difference with the trace");
+
+    Ok(true)
+}
+
+// both runs draw the same random numbers, from a fixed seed unless one is given
+fn config(args: &ConfigArgs) -> Config {
+    let config = args.config();
+
+    match config.seed {
+        Some(_) => config,
+        None => config.with_seed(DEFAULT_SEED),
+    }
+}
+
+// keeps what the tracer writes, while the emulator owns it
+#[derive(Clone, Default)]
+struct TraceBuffer(Arc<Mutex<Vec<u8>>>);
+
+impl Write for TraceBuffer {
+    fn write(&mut self, bytes: &[u8]) -> io::Result<usize> {
+        self.0.lock().unwrap().write(bytes)
+    }
+
+    fn flush(&mut self) -> io::Result<()> {
+        Ok(())
+    }
+}
+
+impl TraceBuffer {
+    fn take_lines(&self) -> Vec<String> {
+        let bytes = std::mem::take(&mut *self.0.lock().unwrap());
+
+        String::from_utf8_lossy(&bytes)
+            .lines()
+            .map(str::to_string)
+            .collect()
+    }
+}
+
+// presses the keys of the script during their frames
+struct ScriptedController {
+    frame: Cell<u64>,
+    script: Vec<(Range<u64>, usize)>,
+}
+
+impl Controller for ScriptedController {
+    fn is_key_down(&self, key_index: usize) -> bool {
+        let frame = self.frame.get();
+
+        self.script
+            .iter()
+            .any(|(frames, key)| *key == key_index && frames.contains(&frame))
+    }
+}
+
+struct Run {
+    emulator: Emulator,
+    controller: ScriptedController,
+    renderer: CaptureRenderer,
+    synthetizer: NullSynthetizer,
+    trace: TraceBuffer,
+    // the last lines of the trace
+    context: VecDeque<String>,
+    context_size: usize,
+}
+
+impl Run {
+    fn new(args: &Args, config: Config, context_size: usize) -> Result<Self, String> {
+        let mut emulator = Emulator::from_file(&args.path, config)
+            .map_err(|error| format!("{}: {}", args.path, error))?;
+        let trace = TraceBuffer::default();
+
+        emulator.set_tracer(Tracer::new(trace.clone()));
+
+        Ok(Self {
+            emulator,
+            controller: ScriptedController {
+                frame: Cell::new(0),
+                script: args.keys.clone(),
+            },
+            renderer: CaptureRenderer::new(),
+            synthetizer: NullSynthetizer,
+            trace,
+            context: VecDeque::new(),
+            context_size,
+        })
+    }
+
+    // runs an instruction, returning its trace line
+    fn step(&mut self) -> Option<String> {
+        self.controller.frame.set(self.emulator.get_frame_count());
+        self.emulator
+            .step_instruction(&self.controller, &mut self.renderer, &mut self.synthetizer);
+
+        let lines = self.trace.take_lines();
+
+        for line in &lines {
+            if self.context.len() == self.context_size {
+                self.context.pop_front();
+            }
+
+            if self.context_size > 0 {
+                self.context.push_back(line.clone());
+            }
+        }
+
+        lines.into_iter().find(|line| !line.starts_with('#'))
+    }
+
+    // runs until an instruction runs, as none does while waiting for a key
+    fn next_line(&mut self, args: &Args) -> String {
+        // frames after the last scripted key press nothing, the wait would never end
+        let last_key_frame = args.keys.iter().map(|(frames, _)| frames.end).max();
+
+        loop {
+            if self.emulator.has_exited() {
+                return "the rom exited".to_string();
+            }
+
+            if let Some(line) = self.step() {
+                return line;
+            }
+
+            if self.emulator.is_waiting_for_key()
+                && last_key_frame.is_none_or(|frame| self.emulator.get_frame_count() > frame)
+            {
+                return "the rom waits for a key".to_string();
+            }
+        }
+    }
+
+    fn print_context(&self, title: &str) {
+        println!("{}", title);
+
+        for line in &self.context {
+            println!("  {}", line);
+        }
+    }
+}
+
+// cli arguments struct
+#[derive(Parser, Debug)]
+#[command(version, about = "Runs a CHIP-8 rom twice in lockstep and reports the first difference", long_about = None)]
+struct Args {
+    /// Path to the rom
+    #[arg(short, long)]
+    path: String,
+
+    /// Configuration of the first run, and of the second one unless overridden
+    #[command(flatten)]
+    config: ConfigArgs,
+
+    /// Platform of the second run, defaults to the one of the first run
+    #[arg(long, value_enum)]
+    other_platform: Option<PlatformArg>,
+
+    /// Quirks of the second run, defaults to the ones of the first run
+    #[arg(long, value_enum)]
+    other_quirks: Option<QuirksProfile>,
+
+    /// Unfiltered trace to compare the first run against, instead of running it twice
+    #[arg(long, conflicts_with_all = ["other_platform", "other_quirks"])]
+    against: Option<String>,
+
+    /// Key held during frames, e.g. 60-64:a holds A from frame 60 to 63, can be repeated
+    #[arg(short, long = "key", value_parser = parse_key)]
+    keys: Vec<(Range<u64>, usize)>,
+
+    /// Instructions to compare at most
+    #[arg(short, long, default_value_t = 1_000_000)]
+    cycles: u64,
+
+    /// Instructions printed before the difference
+    #[arg(long, default_value_t = 10)]
+    context: usize,
+}
+
+// first-last:key, frames in decimal and the key in hexadecimal
+fn parse_key(text: &str) -> Result<(Range<u64>, usize), String> {
+    let error = || format!("invalid key '{}', expected first-last:key", text);
+
+    let (frames, key) = text.split_once(':').ok_or_else(error)?;
+    let (first, last) = frames.split_once('-').ok_or_else(error)?;
+
+    let first: u64 = first.parse().map_err(|_| error())?;
+    let last: u64 = last.parse().map_err(|_| error())?;
+    let key = usize::from_str_radix(key, 16)
+        .ok()
+        .filter(|key| *key < 16)
+        .ok_or_else(error)?;
+
+    Ok((first..last, key))
+}
@@ -0,0 +1,76 @@
+// Differences reported between the states of two emulators
+
+use chip8_lib::config::Config;
+use chip8_lib::emulator::Emulator;
+use chip8_lib::headless::{CaptureRenderer, NullController, NullSynthetizer};
+use chip8_tracediff::compare;
+
+fn emulator(rom: &[u8]) -> Emulator {
+    Emulator::from_bytes(rom, Config::default()).unwrap()
+}
+
+fn step(emulator: &mut Emulator, count: usize) {
+    for _ in 0..count {
+        emulator.step_instruction(
+            &NullController,
+            &mut CaptureRenderer::new(),
+            &mut NullSynthetizer,
+        );
+    }
+}
+
+#[test]
+fn same_states() {
+    let (mut first, mut second) = (emulator(&[0x60, 0x05]), emulator(&[0x60, 0x05]));
+
+    step(&mut first, 1);
+    step(&mut second, 1);
+
+    assert!(compare::differences(&first, &second).is_empty());
+}
+
+#[test]
+fn registers() {
+    let rom = [0x60, 0x05, 0x70, 0x01];
+    let (mut first, mut second) = (emulator(&rom), emulator(&rom));
+
+    // the first run is an instruction ahead
+    step(&mut first, 2);
+    step(&mut second, 1);
+
+    assert_eq!(
+        compare::differences(&first, &second),
+        ["PC: 0x0204 / 0x0202", "V0: 0x06 / 0x05"]
+    );
+}
+
+#[test]
+fn memory() {
+    let (mut first, second) = (emulator(&[0x00, 0xE0]), emulator(&[0x00, 0xE0]));
+
+    first.poke_byte(0x300, 0xAA);
+    first.poke_byte(0x301, 0xBB);
+
+    assert_eq!(
+        compare::differences(&first, &second),
+        ["memory at 0x0300: 0xaa / 0x00 (and 1 other byte)"]
+    );
+}
+
+#[test]
+fn screen() {
+    // draws the 0 of the font, 14 pixels, at 0,0
+    let rom = [0xF0, 0x29, 0xD0, 0x05];
+    let (mut first, mut second) = (emulator(&rom), emulator(&rom));
+
+    step(&mut first, 2);
+    step(&mut second, 1);
+
+    assert_eq!(
+        compare::differences(&first, &second),
+        [
+            "PC: 0x0204 / 0x0202",
+            "pixel at 0,0: 1 / 0 (and 13 other pixels)"
+        ]
+    );
+}
@@ -0,0 +1,129 @@
+// The tool run under two configurations, and against a trace
+
+use chip8_lib::config::Config;
+use chip8_lib::emulator::Emulator;
+use chip8_lib::headless::{CaptureRenderer, NullController, NullSynthetizer};
+use chip8_lib::trace::Tracer;
+use std::env;
+use std::fs;
+use std::path::PathBuf;
+use std::process::{Command, Output};
+
+// draws random numbers in a loop
+const RANDOM: [u8; 8] = [0xC0, 0xFF, 0xC1, 0xFF, 0xC2, 0xFF, 0x12, 0x00];
+
+// shifts V1 into V0 on the VIP, V0 in place on the SUPER-CHIP
+const SHIFT: [u8; 8] = [0x60, 0x01, 0x61, 0x02, 0x80, 0x16, 0x12, 0x06];
+
+// one per test, so they can run in parallel
+fn temp_path(name: &str, extension: &str) -> PathBuf {
+    env::temp_dir().join(format!(
+        "chip8-tracediff-{}-{}.{}",
+        name,
+        std::process::id(),
+        extension
+    ))
+}
+
+fn write_rom(name: &str, rom: &[u8]) -> PathBuf {
+    let path = temp_path(name, "ch8");
+    fs::write(&path, rom).unwrap();
+
+    path
+}
+
+// the unfiltered trace of the first instructions of the rom, as the headless runner writes it
+fn write_trace(name: &str, rom: &[u8], config: Config, instructions: usize) -> PathBuf {
+    let path = temp_path(name, "trace");
+    let mut emulator = Emulator::from_bytes(rom, config).unwrap();
+
+    emulator.set_tracer(Tracer::to_file(&path).unwrap());
+
+    for _ in 0..instructions {
+        emulator.step_instruction(
+            &NullController,
+            &mut CaptureRenderer::new(),
+            &mut NullSynthetizer,
+        );
+    }
+
+    emulator.take_tracer().unwrap().finish().unwrap();
+
+    path
+}
+
+fn tracediff(rom: &PathBuf, args: &[&str]) -> Output {
+    Command::new(env!("CARGO_BIN_EXE_chip8-tracediff"))
+        .arg("--path")
+        .arg(rom)
+        .args(args)
+        .output()
+        .unwrap()
+}
+
+fn stdout(output: &Output) -> String {
+    String::from_utf8_lossy(&output.stdout).into_owned()
+}
+
+#[test]
+fn same_random_numbers_in_both_runs() {
+    let rom = write_rom("random", &RANDOM);
+
+    // without --seed both runs use the same fixed one
+    let output = tracediff(&rom, &["--cycles", "1000"]);
+    assert_eq!(output.status.code(), Some(0), "{}", stdout(&output));
+    assert!(stdout(&output).contains("no difference in 1000 cycles"));
+
+    let output = tracediff(&rom, &["--cycles", "1000", "--random", "page-sum"]);
+    assert_eq!(output.status.code(), Some(0), "{}", stdout(&output));
+
+    fs::remove_file(rom).unwrap();
+}
+
+#[test]
+fn different_quirks() {
+    let rom = write_rom("quirks", &SHIFT);
+
+    let output = tracediff(&rom, &["--quirks", "vip", "--other-quirks", "schip"]);
+    assert_eq!(output.status.code(), Some(1));
+    assert!(stdout(&output).starts_with("first difference after cycle 2, frame 0"));
+    assert!(stdout(&output).contains("  V0: 0x01 / 0x00\n"));
+    assert!(stdout(&output).contains("last instructions of the second run"));
+
+    // the same quirks under another name
+    let output = tracediff(&rom, &["--quirks", "vip", "--cycles", "100"]);
+    assert_eq!(output.status.code(), Some(0));
+
+    fs::remove_file(rom).unwrap();
+}
+
+#[test]
+fn against_a_trace() {
+    let rom = write_rom("against", &RANDOM);
+    let trace = write_trace("against", &RANDOM, Config::default().with_seed(7), 100);
+    let against = trace.to_str().unwrap();
+
+    let output = tracediff(&rom, &["--against", against, "--seed", "7"]);
+    assert_eq!(output.status.code(), Some(0), "{}", stdout(&output));
+    assert!(stdout(&output).contains("no difference with the trace"));
+
+    // other random numbers
+    let output = tracediff(&rom, &["--against", against, "--seed", "8"]);
+    assert_eq!(output.status.code(), Some(1));
+    assert!(stdout(&output).starts_with("first difference at cycle 0, frame 0"));
+
+    fs::remove_file(rom).unwrap();
+    fs::remove_file(trace).unwrap();
+}
+
+#[test]
+fn against_a_trace_with_the_default_seed() {
+    let rom = write_rom("default-seed", &RANDOM);
+    let trace = write_trace("default-seed", &RANDOM, Config::default().with_seed(0), 100);
+
+    let output = tracediff(&rom, &["--against", trace.to_str().unwrap()]);
+    assert_eq!(output.status.code(), Some(0), "{}", stdout(&output));
+
+    fs::remove_file(rom).unwrap();
+    fs::remove_file(trace).unwrap();
+}